@@ -0,0 +1,29 @@
+extern crate criterion;
+extern crate redis_ts;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use redis_ts::{TsColumns, TsRange};
+
+const SAMPLES: u64 = 1_000_000;
+
+fn bench_stats(c: &mut Criterion) {
+    let columns: TsColumns = (0..SAMPLES).map(|i| (i * 1000, i as f64 * 0.25)).collect();
+    let range: TsRange<u64, f64> = columns.clone().into_range();
+    let mut group = c.benchmark_group("stats");
+    group.throughput(Throughput::Elements(SAMPLES));
+    group.bench_function("columns_sum", |b| b.iter(|| black_box(&columns).sum()));
+    group.bench_function("rows_sum", |b| {
+        b.iter(|| black_box(&range).values.iter().map(|v| v.1).sum::<f64>())
+    });
+    group.bench_function("columns_min_max", |b| {
+        b.iter(|| (black_box(&columns).min(), black_box(&columns).max()))
+    });
+    group.bench_function("columns_range_mean", |b| {
+        b.iter(|| black_box(&columns).range(250_000_000, 750_000_000).mean())
+    });
+    group.bench_function("rows_columns", |b| b.iter(|| black_box(&range).columns()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_stats);
+criterion_main!(benches);
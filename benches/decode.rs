@@ -0,0 +1,123 @@
+extern crate criterion;
+extern crate redis;
+extern crate redis_ts;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use redis::{FromRedisValue, Value};
+use redis_ts::{TsColumns, TsInfo, TsMcolumns, TsMrange, TsRange, TsValue};
+
+const SAMPLES: usize = 1_000_000;
+const SERIES: usize = 100;
+
+fn samples(count: usize) -> Value {
+    Value::Array(
+        (0..count)
+            .map(|i| {
+                Value::Array(vec![
+                    Value::Int(1_600_000_000_000 + i as i64 * 1000),
+                    Value::BulkString(format!("{}", i as f64 * 0.25).into_bytes()),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn mrange(series: usize, count: usize) -> Value {
+    Value::Array(
+        (0..series)
+            .map(|s| {
+                Value::Array(vec![
+                    Value::BulkString(format!("sensor:{}", s).into_bytes()),
+                    Value::Array(vec![Value::Array(vec![
+                        Value::BulkString(b"sensor".to_vec()),
+                        Value::BulkString(format!("{}", s).into_bytes()),
+                    ])]),
+                    samples(count),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn info() -> Value {
+    let mut values = vec![];
+    for (name, value) in [
+        ("totalSamples", Value::Int(SAMPLES as i64)),
+        ("memoryUsage", Value::Int(4096)),
+        ("firstTimestamp", Value::Int(1)),
+        ("lastTimestamp", Value::Int(SAMPLES as i64)),
+        ("retentionTime", Value::Int(0)),
+        ("chunkCount", Value::Int(250)),
+        ("chunkSize", Value::Int(4096)),
+    ] {
+        values.push(Value::BulkString(name.as_bytes().to_vec()));
+        values.push(value);
+    }
+    values.push(Value::BulkString(b"labels".to_vec()));
+    values.push(Value::Array(
+        (0..32)
+            .map(|i| {
+                Value::Array(vec![
+                    Value::BulkString(format!("label{}", i).into_bytes()),
+                    Value::BulkString(vec![b'x'; 256]),
+                ])
+            })
+            .collect(),
+    ));
+    Value::Array(values)
+}
+
+fn bench_range(c: &mut Criterion) {
+    let reply = samples(SAMPLES);
+    let mut group = c.benchmark_group("range");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(SAMPLES as u64));
+    group.bench_function("rows", |b| {
+        b.iter(|| TsRange::<u64, f64>::from_redis_value(black_box(&reply)).unwrap())
+    });
+    group.bench_function("rows_ts_value", |b| {
+        b.iter(|| TsRange::<u64, TsValue>::from_redis_value(black_box(&reply)).unwrap())
+    });
+    group.bench_function("rows_owned", |b| {
+        b.iter_batched(
+            || reply.clone(),
+            |reply| TsRange::<u64, f64>::from_owned_redis_value(reply).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("columns", |b| {
+        b.iter(|| TsColumns::from_redis_value(black_box(&reply)).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_mrange(c: &mut Criterion) {
+    let reply = mrange(SERIES, SAMPLES / SERIES);
+    let mut group = c.benchmark_group("mrange");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(SAMPLES as u64));
+    group.bench_function("rows", |b| {
+        b.iter(|| TsMrange::<u64, f64>::from_redis_value(black_box(&reply)).unwrap())
+    });
+    group.bench_function("rows_owned", |b| {
+        b.iter_batched(
+            || reply.clone(),
+            |reply| TsMrange::<u64, f64>::from_owned_redis_value(reply).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("columns", |b| {
+        b.iter(|| TsMcolumns::from_redis_value(black_box(&reply)).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_info(c: &mut Criterion) {
+    let reply = info();
+    c.bench_function("info", |b| {
+        b.iter(|| TsInfo::from_redis_value(black_box(&reply)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_range, bench_mrange, bench_info);
+criterion_main!(benches);
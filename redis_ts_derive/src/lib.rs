@@ -0,0 +1,543 @@
+//! Derive macros for [redis_ts](https://docs.rs/redis_ts). Enable them with the
+//! `derive` feature of redis_ts, they are re-exported from there.
+//!
+//! `#[derive(TsRecord)]` implements `redis_ts::TsRecord` for a struct that is
+//! written as one sample per value field. Fields are marked with
+//! `#[ts(timestamp)]`, `#[ts(label)]`, `#[ts(value)]` or `#[ts(skip)]`, skipped
+//! fields are read back with their Default value.
+//!
+//! ```rust,ignore
+//! use redis_ts::TsRecord;
+//!
+//! #[derive(TsRecord)]
+//! #[ts(name = "cpu", key = "cpu:{host}:{field}")]
+//! struct Cpu {
+//!     #[ts(timestamp)]
+//!     ts: u64,
+//!     #[ts(label)]
+//!     host: String,
+//!     #[ts(value)]
+//!     user: f64,
+//!     #[ts(value, rename = "sys")]
+//!     system: Option<f64>,
+//! }
+//! ```
+//!
+//! `#[derive(TsSeries)]` implements `redis_ts::schema::TsSeries` for a struct
+//! whose fields are the labels of a series. Fields are rendered with Display,
+//! `Option` fields are optional labels.
+//!
+//! ```rust,ignore
+//! use redis_ts::schema::TsSeries;
+//!
+//! #[derive(TsSeries)]
+//! #[ts(name = "sensor", key = "sensor:{site}:{kind}", retention = 86400000)]
+//! #[ts(duplicate_policy = "last")]
+//! #[ts(compaction(suffix = "1h", aggregation = "avg", bucket = 3600000, retention = 2592000000))]
+//! struct Sensor {
+//!     site: String,
+//!     #[ts(values("temperature", "humidity"))]
+//!     kind: String,
+//!     #[ts(rename = "fw")]
+//!     firmware: Option<String>,
+//!     #[ts(skip)]
+//!     note: String,
+//! }
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Fields, Ident, LitInt, LitStr, Result, Type,
+};
+
+/// Returns the TsAggregationType variant of an aggregation name.
+fn aggregation(name: &LitStr) -> Result<Ident> {
+    let variant = match name.value().as_str() {
+        "avg" => "Avg",
+        "sum" => "Sum",
+        "min" => "Min",
+        "max" => "Max",
+        "range" => "Range",
+        "count" => "Count",
+        "first" => "First",
+        "last" => "Last",
+        "std.p" => "StdP",
+        "std.s" => "StdS",
+        "var.p" => "VarP",
+        "var.s" => "VarS",
+        "twa" => "Twa",
+        other => {
+            return Err(Error::new(
+                name.span(),
+                format!("unknown aggregation {other}"),
+            ))
+        }
+    };
+    Ok(Ident::new(variant, name.span()))
+}
+
+fn duplicate_policy(name: &LitStr) -> TokenStream2 {
+    let variant = match name.value().to_lowercase().as_str() {
+        "block" => "Block",
+        "first" => "First",
+        "last" => "Last",
+        "min" => "Min",
+        "max" => "Max",
+        "sum" => "Sum",
+        _ => {
+            let other = name.value().to_uppercase();
+            return quote! { ::redis_ts::TsDuplicatePolicy::Other(#other.to_string()) };
+        }
+    };
+    let variant = Ident::new(variant, name.span());
+    quote! { ::redis_ts::TsDuplicatePolicy::#variant }
+}
+
+/// Returns the inner type if the type is an `Option`.
+fn option_type(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn lit_values(meta: &ParseNestedMeta) -> Result<Vec<LitStr>> {
+    let mut values = vec![];
+    let content;
+    syn::parenthesized!(content in meta.input);
+    while !content.is_empty() {
+        values.push(content.parse::<LitStr>()?);
+        if !content.is_empty() {
+            content.parse::<syn::Token![,]>()?;
+        }
+    }
+    Ok(values)
+}
+
+#[derive(Default)]
+struct SeriesAttrs {
+    name: Option<LitStr>,
+    key: Option<LitStr>,
+    retention: Option<LitInt>,
+    duplicate_policy: Option<LitStr>,
+    compactions: Vec<TokenStream2>,
+}
+
+fn compaction(meta: &ParseNestedMeta) -> Result<TokenStream2> {
+    let mut suffix: Option<LitStr> = None;
+    let mut agg: Option<LitStr> = None;
+    let mut bucket: Option<LitInt> = None;
+    let mut retention: Option<LitInt> = None;
+    meta.parse_nested_meta(|m| {
+        if m.path.is_ident("suffix") {
+            suffix = Some(m.value()?.parse()?);
+        } else if m.path.is_ident("aggregation") {
+            agg = Some(m.value()?.parse()?);
+        } else if m.path.is_ident("bucket") {
+            bucket = Some(m.value()?.parse()?);
+        } else if m.path.is_ident("retention") {
+            retention = Some(m.value()?.parse()?);
+        } else {
+            return Err(m.error("unknown compaction attribute"));
+        }
+        Ok(())
+    })?;
+    let missing = |name: &str| meta.error(format!("compaction requires {name}"));
+    let suffix = suffix.ok_or_else(|| missing("suffix"))?;
+    let variant = aggregation(&agg.ok_or_else(|| missing("aggregation"))?)?;
+    let bucket = bucket.ok_or_else(|| missing("bucket"))?;
+    let retention = retention.ok_or_else(|| missing("retention"))?;
+    Ok(quote! {
+        .compaction(#suffix, ::redis_ts::TsAggregationType::#variant(#bucket), #retention)
+    })
+}
+
+fn series_attrs(input: &DeriveInput) -> Result<SeriesAttrs> {
+    let mut attrs = SeriesAttrs::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("ts")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                attrs.name = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("key") {
+                attrs.key = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("retention") {
+                attrs.retention = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("duplicate_policy") {
+                attrs.duplicate_policy = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("compaction") {
+                attrs.compactions.push(compaction(&meta)?);
+            } else {
+                return Err(meta.error("unknown ts attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
+
+#[derive(Default)]
+struct LabelAttrs {
+    rename: Option<LitStr>,
+    values: Vec<LitStr>,
+    skip: bool,
+}
+
+fn label_attrs(field: &syn::Field) -> Result<LabelAttrs> {
+    let mut attrs = LabelAttrs::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("ts")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                attrs.rename = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("values") {
+                attrs.values = lit_values(&meta)?;
+            } else if meta.path.is_ident("skip") {
+                attrs.skip = true;
+            } else {
+                return Err(meta.error("unknown ts field attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attrs)
+}
+
+fn named_fields(input: &DeriveInput) -> Result<&syn::FieldsNamed> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields),
+            _ => Err(Error::new(Span::call_site(), "expected named fields")),
+        },
+        _ => Err(Error::new(Span::call_site(), "expected a struct")),
+    }
+}
+
+/// Pushes the Display value of a label field onto `labels` if it is set.
+fn push_label(label: &LitStr, field: &Ident, optional: bool) -> TokenStream2 {
+    match optional {
+        false => quote! {
+            labels.push((#label.to_string(), ::std::string::ToString::to_string(&self.#field)));
+        },
+        true => quote! {
+            if let Some(value) = &self.#field {
+                labels.push((#label.to_string(), ::std::string::ToString::to_string(value)));
+            }
+        },
+    }
+}
+
+fn derive_series(input: DeriveInput) -> Result<TokenStream2> {
+    let ident = &input.ident;
+    let attrs = series_attrs(&input)?;
+    let key = attrs
+        .key
+        .ok_or_else(|| Error::new(Span::call_site(), "missing #[ts(key = \"...\")]"))?;
+    let name = attrs
+        .name
+        .unwrap_or_else(|| LitStr::new(&ident.to_string().to_lowercase(), ident.span()));
+
+    let mut specs = vec![];
+    let mut labels = vec![];
+    for field in named_fields(&input)?.named.iter() {
+        let field_attrs = label_attrs(field)?;
+        if field_attrs.skip {
+            continue;
+        }
+        let field_ident = field.ident.as_ref().unwrap();
+        let label = field_attrs
+            .rename
+            .unwrap_or_else(|| LitStr::new(&field_ident.to_string(), field_ident.span()));
+        let values = &field_attrs.values;
+        let optional = option_type(&field.ty).is_some();
+        specs.push(match (optional, values.is_empty()) {
+            (false, true) => quote! { .label(#label) },
+            (false, false) => quote! { .label_values(#label, &[#(#values),*]) },
+            (true, true) => quote! { .optional_label(#label) },
+            (true, false) => quote! { .optional_label_values(#label, &[#(#values),*]) },
+        });
+        labels.push(push_label(&label, field_ident, optional));
+    }
+
+    let retention = attrs.retention.map(|r| quote! { .retention(#r) });
+    let policy = attrs.duplicate_policy.map(|p| {
+        let policy = duplicate_policy(&p);
+        quote! { .duplicate_policy(#policy) }
+    });
+    let compactions = &attrs.compactions;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::redis_ts::schema::TsSeries for #ident #ty_generics #where_clause {
+            fn schema() -> ::redis_ts::schema::TsSchema {
+                ::redis_ts::schema::TsSchema::new(#name, #key)
+                    #(#specs)*
+                    #retention
+                    #policy
+                    #(#compactions)*
+            }
+
+            fn labels(&self) -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+                let mut labels = ::std::vec::Vec::new();
+                #(#labels)*
+                labels
+            }
+        }
+    })
+}
+
+/// Derives `redis_ts::schema::TsSeries` for a struct holding label values.
+#[proc_macro_derive(TsSeries, attributes(ts))]
+pub fn ts_series(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive_series(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+#[derive(PartialEq)]
+enum RecordRole {
+    Timestamp,
+    Label,
+    Value,
+    Skip,
+}
+
+fn record_attrs(field: &syn::Field) -> Result<(RecordRole, Option<LitStr>)> {
+    let mut role = None;
+    let mut rename = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("ts")) {
+        attr.parse_nested_meta(|meta| {
+            let next = if meta.path.is_ident("timestamp") {
+                RecordRole::Timestamp
+            } else if meta.path.is_ident("label") {
+                RecordRole::Label
+            } else if meta.path.is_ident("value") {
+                RecordRole::Value
+            } else if meta.path.is_ident("skip") {
+                RecordRole::Skip
+            } else if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse()?);
+                return Ok(());
+            } else {
+                return Err(meta.error("unknown ts field attribute"));
+            };
+            if role.is_some() {
+                return Err(
+                    meta.error("a field can only have one of timestamp, label, value or skip")
+                );
+            }
+            role = Some(next);
+            Ok(())
+        })?;
+    }
+    let role = role.ok_or_else(|| {
+        Error::new_spanned(field, "mark the field with timestamp, label, value or skip")
+    })?;
+    Ok((role, rename))
+}
+
+fn derive_record(input: DeriveInput) -> Result<TokenStream2> {
+    let ident = &input.ident;
+    let mut name: Option<LitStr> = None;
+    let mut key: Option<LitStr> = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("ts")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("key") {
+                key = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("unknown ts attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    let name = name.unwrap_or_else(|| LitStr::new(&ident.to_string().to_lowercase(), ident.span()));
+
+    let mut timestamp = None;
+    let mut label_names = vec![];
+    let mut labels = vec![];
+    let mut key_labels = vec![];
+    let mut values = vec![];
+    let mut fields = vec![];
+    for field in named_fields(&input)?.named.iter() {
+        let (role, rename) = record_attrs(field)?;
+        let field_ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let inner = option_type(ty);
+        let label =
+            rename.unwrap_or_else(|| LitStr::new(&field_ident.to_string(), field_ident.span()));
+        match role {
+            RecordRole::Timestamp => {
+                if timestamp.is_some() {
+                    return Err(Error::new_spanned(
+                        field,
+                        "only one timestamp field is allowed",
+                    ));
+                }
+                timestamp = Some(quote! { self.#field_ident as u64 });
+                fields.push(quote! { #field_ident: ts as #ty });
+            }
+            RecordRole::Label => {
+                label_names.push(label.value());
+                labels.push(push_label(&label, field_ident, inner.is_some()));
+                let placeholder = format!("{{{}}}", label.value());
+                key_labels.push(match inner {
+                    None => quote! {
+                        key = key.replace(#placeholder, &::std::string::ToString::to_string(&self.#field_ident));
+                    },
+                    Some(_) => quote! {
+                        key = key.replace(
+                            #placeholder,
+                            &self.#field_ident.as_ref().map(::std::string::ToString::to_string).unwrap_or_default(),
+                        );
+                    },
+                });
+                let invalid = format!("invalid value of label {}", label.value());
+                fields.push(match inner {
+                    None => {
+                        let missing = format!("missing label {}", label.value());
+                        quote! {
+                            #field_ident: label(#label)
+                                .ok_or_else(|| #missing.to_string())?
+                                .parse::<#ty>()
+                                .map_err(|_| #invalid.to_string())?
+                        }
+                    }
+                    Some(inner) => quote! {
+                        #field_ident: match label(#label) {
+                            Some(value) => Some(value.parse::<#inner>().map_err(|_| #invalid.to_string())?),
+                            None => None,
+                        }
+                    },
+                });
+            }
+            RecordRole::Value => {
+                values.push(match inner {
+                    None => quote! { values.push((#label, self.#field_ident as f64)); },
+                    Some(_) => quote! {
+                        if let Some(value) = self.#field_ident {
+                            values.push((#label, value as f64));
+                        }
+                    },
+                });
+                fields.push(match inner {
+                    None => {
+                        let missing = format!("missing field {}", label.value());
+                        quote! { #field_ident: (value(#label).ok_or_else(|| #missing.to_string())? as #ty) }
+                    }
+                    Some(inner) => quote! { #field_ident: value(#label).map(|v| v as #inner) },
+                });
+            }
+            RecordRole::Skip => {
+                fields.push(quote! { #field_ident: ::std::default::Default::default() });
+            }
+        }
+    }
+    let timestamp =
+        timestamp.ok_or_else(|| Error::new(Span::call_site(), "missing #[ts(timestamp)] field"))?;
+
+    let key = match key {
+        Some(key) => {
+            let template = key.value();
+            let mut rest = template.as_str();
+            while let Some(start) = rest.find('{') {
+                let end = rest[start..]
+                    .find('}')
+                    .map(|e| start + e)
+                    .unwrap_or(rest.len());
+                let placeholder = &rest[start + 1..end];
+                if placeholder != "field" && !label_names.iter().any(|l| l == placeholder) {
+                    return Err(Error::new(
+                        key.span(),
+                        format!("unknown key label {placeholder}"),
+                    ));
+                }
+                rest = &rest[end.min(rest.len())..];
+                rest = rest.strip_prefix('}').unwrap_or(rest);
+            }
+            if values.len() > 1 && !template.contains("{field}") {
+                return Err(Error::new(
+                    key.span(),
+                    "a key without {field} requires a single value field",
+                ));
+            }
+            key
+        }
+        None => {
+            let mut parts = vec![name.value()];
+            parts.extend(label_names.iter().map(|l| format!("{{{l}}}")));
+            parts.push("{field}".to_string());
+            LitStr::new(&parts.join(":"), Span::call_site())
+        }
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::redis_ts::TsRecord for #ident #ty_generics #where_clause {
+            fn record_name() -> &'static str {
+                #name
+            }
+
+            fn timestamp(&self) -> u64 {
+                #timestamp
+            }
+
+            fn labels(&self) -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+                let mut labels = ::std::vec::Vec::new();
+                #(#labels)*
+                labels
+            }
+
+            fn values(&self) -> ::std::vec::Vec<(&'static str, f64)> {
+                let mut values = ::std::vec::Vec::new();
+                #(#values)*
+                values
+            }
+
+            fn key(&self, field: &str) -> ::std::string::String {
+                let mut key = ::std::string::String::from(#key);
+                #(#key_labels)*
+                key.replace("{field}", field)
+            }
+
+            fn from_parts(
+                ts: u64,
+                labels: &[(::std::string::String, ::std::string::String)],
+                values: &[(::std::string::String, f64)],
+            ) -> ::std::result::Result<Self, ::std::string::String> {
+                #[allow(unused_variables)]
+                let label = |name: &str| labels.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+                #[allow(unused_variables)]
+                let value = |name: &str| values.iter().find(|(n, _)| n == name).map(|(_, v)| *v);
+                Ok(#ident {
+                    #(#fields),*
+                })
+            }
+        }
+    })
+}
+
+/// Derives `redis_ts::TsRecord` for a struct with a timestamp, label and value
+/// fields.
+#[proc_macro_derive(TsRecord, attributes(ts))]
+pub fn ts_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive_record(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
@@ -0,0 +1,489 @@
+//! Threshold alerting over time series.
+//!
+//! A rule aggregates all series matching a filter over a window, eg. the avg of
+//! the last 5 minutes, and compares the result with a threshold. Every series
+//! has its own state: A breached threshold makes it pending, once breached for
+//! the configured amount of consecutive evaluations it fires and when the
+//! threshold is no longer breached it is resolved. State changes are sent to a
+//! [TsAlertNotifier].
+//!
+//! Rules can be built in code or parsed from a config with one rule per line:
+//!
+//! ```text
+//! # name: aggregation(window) filter... operator threshold [for evaluations]
+//! api_load: avg(5m) service=api > 0.9 for 3
+//! disk_free: min(1m) host=(db1,db2) mount=/data < 10
+//! ```
+//!
+//! The engine reads the current time from a [TsClock], so it can be evaluated
+//! deterministically with in-memory results via [TsAlertEngine::queries] and
+//! [TsAlertEngine::apply].
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! use redis_ts::alert::{parse_rules, TsAlertEngine, TsAlertEvent, TsAlertNotifier};
+//!
+//! struct Log;
+//!
+//! impl TsAlertNotifier for Log {
+//!     fn notify(&mut self, event: &TsAlertEvent) -> redis::RedisResult<()> {
+//!         println!("{} {} {:?} {:?}", event.rule, event.key, event.state, event.value);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/")?;
+//! let mut con = client.get_connection()?;
+//!
+//! let rules = parse_rules("api_load: avg(5m) service=api > 0.9 for 3")?;
+//! let mut engine = TsAlertEngine::new(Log).rules(rules);
+//! loop {
+//!     engine.evaluate(&mut con)?;
+//!     std::thread::sleep(std::time::Duration::from_secs(60));
+//! }
+//! # }
+//! ```
+//!
+use crate::commands::TsCommands;
+use crate::io::{aggregation, parse_filter};
+use crate::types::*;
+use redis::{RedisError, RedisResult};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of the current time in unix millis.
+pub trait TsClock {
+    fn now(&self) -> u64;
+}
+
+/// The system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TsSystemClock;
+
+impl TsClock for TsSystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct TsManualClock(Arc<AtomicU64>);
+
+impl TsManualClock {
+    /// Creates a clock set to the given time.
+    pub fn new(now: u64) -> Self {
+        TsManualClock(Arc::new(AtomicU64::new(now)))
+    }
+
+    /// Sets the current time.
+    pub fn set(&self, now: u64) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+
+    /// Moves the current time forward.
+    pub fn advance(&self, millis: u64) {
+        self.0.fetch_add(millis, Ordering::SeqCst);
+    }
+}
+
+impl TsClock for TsManualClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// The comparison of an aggregated value with the threshold of a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsAlertOp {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+impl TsAlertOp {
+    fn parse(op: &str) -> Option<Self> {
+        Some(match op {
+            ">" => TsAlertOp::Gt,
+            ">=" => TsAlertOp::Ge,
+            "<" => TsAlertOp::Lt,
+            "<=" => TsAlertOp::Le,
+            "==" => TsAlertOp::Eq,
+            "!=" => TsAlertOp::Ne,
+            _ => return None,
+        })
+    }
+
+    /// Returns true if the value breaches the threshold.
+    pub fn breached(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            TsAlertOp::Gt => value > threshold,
+            TsAlertOp::Ge => value >= threshold,
+            TsAlertOp::Lt => value < threshold,
+            TsAlertOp::Le => value <= threshold,
+            TsAlertOp::Eq => value == threshold,
+            TsAlertOp::Ne => value != threshold,
+        }
+    }
+}
+
+/// A threshold rule over all series matching a filter. The bucket of the
+/// aggregation is the evaluated window.
+///
+/// ```rust
+/// use redis_ts::alert::{TsAlertOp, TsAlertRule};
+/// use redis_ts::{TsAggregationType, TsFilterOptions};
+///
+/// let rule = TsAlertRule::new(
+///     "api_load",
+///     TsFilterOptions::default().equals("service", "api"),
+///     TsAggregationType::Avg(300000),
+/// )
+/// .condition(TsAlertOp::Gt, 0.9)
+/// .for_evaluations(3);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct TsAlertRule {
+    pub name: String,
+    filter: TsFilterOptions,
+    aggregation: TsAggregationType,
+    op: TsAlertOp,
+    threshold: f64,
+    evaluations: u32,
+}
+
+fn config_error(line: &str, reason: &str) -> RedisError {
+    RedisError::from(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("invalid alert rule {line} ({reason})"),
+    ))
+}
+
+fn parse_duration(value: &str) -> Option<u64> {
+    let digits = value.chars().take_while(|c| c.is_ascii_digit()).count();
+    let amount: u64 = value[..digits].parse().ok()?;
+    let millis = match &value[digits..] {
+        "" | "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => return None,
+    };
+    Some(amount * millis).filter(|d| *d > 0)
+}
+
+impl TsAlertRule {
+    /// Creates a rule that fires as soon as the aggregated value of a series is
+    /// above 0.
+    pub fn new(name: &str, filter: TsFilterOptions, aggregation: TsAggregationType) -> Self {
+        TsAlertRule {
+            name: name.to_string(),
+            filter,
+            aggregation,
+            op: TsAlertOp::Gt,
+            threshold: 0.0,
+            evaluations: 1,
+        }
+    }
+
+    /// Parses a rule like `api_load: avg(5m) service=api > 0.9 for 3`.
+    pub fn parse(line: &str) -> RedisResult<Self> {
+        let (name, rest) = line
+            .split_once(':')
+            .ok_or_else(|| config_error(line, "missing name"))?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(config_error(line, "invalid name"));
+        }
+
+        let mut tokens: Vec<&str> = rest.split_whitespace().collect();
+        let mut evaluations = 1;
+        if tokens.len() > 2 && tokens[tokens.len() - 2] == "for" {
+            evaluations = tokens[tokens.len() - 1]
+                .parse()
+                .map_err(|_| config_error(line, "invalid evaluations"))?;
+            tokens.truncate(tokens.len() - 2);
+        }
+        if tokens.len() < 4 {
+            return Err(config_error(
+                line,
+                "expected aggregation, filter and condition",
+            ));
+        }
+
+        let (agg, window) = tokens[0]
+            .strip_suffix(')')
+            .and_then(|t| t.split_once('('))
+            .ok_or_else(|| config_error(line, "expected aggregation(window)"))?;
+        let window = parse_duration(window).ok_or_else(|| config_error(line, "invalid window"))?;
+        let aggregation =
+            aggregation(agg, window).ok_or_else(|| config_error(line, "unknown aggregation"))?;
+        let op = TsAlertOp::parse(tokens[tokens.len() - 2])
+            .ok_or_else(|| config_error(line, "invalid operator"))?;
+        let threshold = tokens[tokens.len() - 1]
+            .parse()
+            .map_err(|_| config_error(line, "invalid threshold"))?;
+        let filter = parse_filter(
+            TsFilterOptions::default(),
+            &tokens[1..tokens.len() - 2].join(" "),
+        )?;
+
+        Ok(TsAlertRule::new(name, filter, aggregation)
+            .condition(op, threshold)
+            .for_evaluations(evaluations))
+    }
+
+    /// The comparison that breaches the rule.
+    pub fn condition(mut self, op: TsAlertOp, threshold: f64) -> Self {
+        self.op = op;
+        self.threshold = threshold;
+        self
+    }
+
+    /// The amount of consecutive breaching evaluations before the rule fires.
+    /// Defaults to 1.
+    pub fn for_evaluations(mut self, evaluations: u32) -> Self {
+        self.evaluations = evaluations.max(1);
+        self
+    }
+
+    /// Returns the query for the window ending at now. The window is a single
+    /// aggregation bucket.
+    pub fn query(&self, now: u64) -> (TsRangeQuery, TsFilterOptions) {
+        let window = self.aggregation.bucket();
+        let from = now.saturating_sub(window);
+        let query = TsRangeQuery::default()
+            .from(from)
+            .to(now.saturating_sub(1))
+            .align(TsAlign::Start)
+            .aggregation_type(self.aggregation);
+        (query, self.filter.clone().with_labels(true))
+    }
+}
+
+/// Parses a config with one rule per line. Empty lines and lines starting with
+/// `#` are skipped.
+pub fn parse_rules(config: &str) -> RedisResult<Vec<TsAlertRule>> {
+    config
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(TsAlertRule::parse)
+        .collect()
+}
+
+/// The state of a series within a rule.
+/// - Inactive: The threshold is not breached.
+/// - Pending: The threshold is breached, but not yet for enough evaluations.
+/// - Firing: The threshold is breached for enough evaluations.
+/// - Resolved: The threshold is no longer breached after firing. Only reported
+///   in events, the series is inactive afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsAlertState {
+    Inactive,
+    Pending,
+    Firing,
+    Resolved,
+}
+
+/// A state change of a series. The value is None if the series had no samples
+/// in the window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsAlertEvent {
+    pub rule: String,
+    pub key: String,
+    pub labels: Vec<(String, String)>,
+    pub state: TsAlertState,
+    pub value: Option<f64>,
+    pub ts: u64,
+}
+
+/// Receives the state changes of an alert engine.
+pub trait TsAlertNotifier {
+    fn notify(&mut self, event: &TsAlertEvent) -> RedisResult<()>;
+}
+
+/// Collects all events, eg. for tests or to process them after an evaluation.
+impl TsAlertNotifier for Vec<TsAlertEvent> {
+    fn notify(&mut self, event: &TsAlertEvent) -> RedisResult<()> {
+        self.push(event.clone());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TsAlertSeries {
+    state: TsAlertState,
+    breaches: u32,
+    labels: Vec<(String, String)>,
+}
+
+/// Evaluates alert rules and keeps the state of every series.
+pub struct TsAlertEngine<N: TsAlertNotifier, K: TsClock = TsSystemClock> {
+    rules: Vec<TsAlertRule>,
+    series: HashMap<(String, String), TsAlertSeries>,
+    notifier: N,
+    clock: K,
+}
+
+impl<N: TsAlertNotifier> TsAlertEngine<N> {
+    /// Creates an engine without rules that uses the system time.
+    pub fn new(notifier: N) -> Self {
+        TsAlertEngine {
+            rules: vec![],
+            series: HashMap::new(),
+            notifier,
+            clock: TsSystemClock,
+        }
+    }
+}
+
+impl<N: TsAlertNotifier, K: TsClock> TsAlertEngine<N, K> {
+    /// Replaces the clock used to determine the evaluated windows.
+    pub fn clock<C: TsClock>(self, clock: C) -> TsAlertEngine<N, C> {
+        TsAlertEngine {
+            rules: self.rules,
+            series: self.series,
+            notifier: self.notifier,
+            clock,
+        }
+    }
+
+    /// Adds a rule.
+    pub fn rule(mut self, rule: TsAlertRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Adds multiple rules, eg. from parse_rules.
+    pub fn rules(mut self, rules: Vec<TsAlertRule>) -> Self {
+        self.rules.extend(rules);
+        self
+    }
+
+    /// Returns the notifier.
+    pub fn notifier(&self) -> &N {
+        &self.notifier
+    }
+
+    /// Returns the current state of a series within a rule.
+    pub fn state(&self, rule: &str, key: &str) -> TsAlertState {
+        self.series
+            .get(&(rule.to_string(), key.to_string()))
+            .map(|s| s.state)
+            .unwrap_or(TsAlertState::Inactive)
+    }
+
+    /// Returns the queries of all rules at the current time, in rule order.
+    pub fn queries(&self) -> Vec<(TsRangeQuery, TsFilterOptions)> {
+        let now = self.clock.now();
+        self.rules.iter().map(|r| r.query(now)).collect()
+    }
+
+    /// Updates the states with the results of the queries, one per rule, and
+    /// notifies all state changes. Series that are tracked but missing in the
+    /// results count as not breached.
+    pub fn apply(&mut self, results: Vec<TsMrange<u64, f64>>) -> RedisResult<Vec<TsAlertEvent>> {
+        let now = self.clock.now();
+        let mut events = vec![];
+        for (rule, result) in self.rules.iter().zip(results) {
+            let mut entries = result.values;
+            let mut missing: Vec<TsMrangeEntry<u64, f64>> = self
+                .series
+                .iter()
+                .filter(|((r, key), _)| r == &rule.name && !entries.iter().any(|e| &e.key == key))
+                .map(|((_, key), s)| TsMrangeEntry {
+                    key: key.clone(),
+                    labels: s.labels.clone(),
+                    values: vec![],
+                })
+                .collect();
+            missing.sort_by(|a, b| a.key.cmp(&b.key));
+            entries.extend(missing);
+
+            for entry in entries {
+                let TsMrangeEntry {
+                    key,
+                    labels,
+                    values,
+                } = entry;
+                let value = values.last().map(|(_, v)| *v);
+                let breached = value.is_some_and(|v| rule.op.breached(v, rule.threshold));
+                let id = (rule.name.clone(), key.clone());
+                let series = self.series.entry(id.clone()).or_insert(TsAlertSeries {
+                    state: TsAlertState::Inactive,
+                    breaches: 0,
+                    labels: labels.clone(),
+                });
+                series.labels = labels.clone();
+
+                let previous = series.state;
+                if breached {
+                    series.breaches += 1;
+                    series.state = if series.breaches >= rule.evaluations {
+                        TsAlertState::Firing
+                    } else {
+                        TsAlertState::Pending
+                    };
+                } else {
+                    series.breaches = 0;
+                    series.state = TsAlertState::Inactive;
+                }
+
+                let reported = match (previous, series.state) {
+                    (TsAlertState::Firing, TsAlertState::Inactive) => Some(TsAlertState::Resolved),
+                    (from, to) if from != to && to != TsAlertState::Inactive => Some(to),
+                    _ => None,
+                };
+                if series.state == TsAlertState::Inactive {
+                    self.series.remove(&id);
+                }
+                if let Some(state) = reported {
+                    let event = TsAlertEvent {
+                        rule: rule.name.clone(),
+                        key,
+                        labels,
+                        state,
+                        value,
+                        ts: now,
+                    };
+                    self.notifier.notify(&event)?;
+                    events.push(event);
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    /// Queries all rules and applies the results.
+    pub fn evaluate<C: TsCommands>(&mut self, con: &mut C) -> RedisResult<Vec<TsAlertEvent>> {
+        let mut results = vec![];
+        for (query, filter) in self.queries() {
+            results.push(con.ts_mrange(query, filter)?);
+        }
+        self.apply(results)
+    }
+
+    /// Async version of evaluate.
+    #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+    pub async fn evaluate_async<C: crate::AsyncTsCommands>(
+        &mut self,
+        con: &mut C,
+    ) -> RedisResult<Vec<TsAlertEvent>> {
+        let mut results = vec![];
+        for (query, filter) in self.queries() {
+            results.push(con.ts_mrange(query, filter).await?);
+        }
+        self.apply(results)
+    }
+}
@@ -0,0 +1,287 @@
+use crate::types::*;
+use std::collections::VecDeque;
+
+/// Methods to score samples against a baseline. A sample is an anomaly if the
+/// absolute value of its score exceeds the threshold.
+/// - ZScore: Distance to the mean of the previous `window` samples in standard
+///   deviations.
+/// - Mad: Modified z-score (`0.6745 * (x - median) / MAD`) over the previous
+///   `window` samples, robust against outliers within the window. A threshold of
+///   3.5 is common.
+/// - Ewma: Distance to an exponentially weighted moving average in exponentially
+///   weighted standard deviations. Higher alpha values adapt faster. Samples are
+///   scored after a warm-up of `1 / alpha` samples (at least 2), before that
+///   the average and deviation are not meaningful yet.
+/// - Seasonal: Relative deviation from the mean of the samples one or more
+///   periods (eg. a day or week) earlier. Earlier samples are matched if they are
+///   at most `tolerance` millis away from the exact time.
+///
+/// NaN samples are never anomalies and are not part of any baseline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TsAnomalyDetector {
+    ZScore {
+        window: usize,
+        threshold: f64,
+    },
+    Mad {
+        window: usize,
+        threshold: f64,
+    },
+    Ewma {
+        alpha: f64,
+        threshold: f64,
+    },
+    Seasonal {
+        period: u64,
+        seasons: usize,
+        tolerance: u64,
+        threshold: f64,
+    },
+}
+
+const DAY: u64 = 86_400_000;
+
+impl TsAnomalyDetector {
+    /// Compares every sample with the samples of the previous `seasons` days.
+    pub fn daily(seasons: usize, tolerance: u64, threshold: f64) -> Self {
+        TsAnomalyDetector::Seasonal {
+            period: DAY,
+            seasons,
+            tolerance,
+            threshold,
+        }
+    }
+
+    /// Compares every sample with the samples of the previous `seasons` weeks.
+    pub fn weekly(seasons: usize, tolerance: u64, threshold: f64) -> Self {
+        TsAnomalyDetector::Seasonal {
+            period: 7 * DAY,
+            seasons,
+            tolerance,
+            threshold,
+        }
+    }
+
+    fn threshold(&self) -> f64 {
+        match *self {
+            TsAnomalyDetector::ZScore { threshold, .. }
+            | TsAnomalyDetector::Mad { threshold, .. }
+            | TsAnomalyDetector::Ewma { threshold, .. }
+            | TsAnomalyDetector::Seasonal { threshold, .. } => threshold,
+        }
+    }
+}
+
+/// A sample annotated with its baseline and score. Both are None if there is no
+/// baseline yet, eg. for the first samples of a window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TsAnomalySample {
+    pub ts: u64,
+    pub value: f64,
+    pub baseline: Option<f64>,
+    pub score: Option<f64>,
+    pub anomaly: bool,
+}
+
+/// Consecutive anomalous samples from start to end (inclusive) with the highest
+/// absolute score among them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TsAnomalyInterval {
+    pub start: u64,
+    pub end: u64,
+    pub samples: u64,
+    pub max_score: f64,
+}
+
+/// Returns the score of a deviation for a spread, infinite if a constant
+/// baseline is left.
+fn score(deviation: f64, spread: f64) -> f64 {
+    if spread > 0.0 {
+        deviation / spread
+    } else if deviation == 0.0 {
+        0.0
+    } else {
+        f64::INFINITY.copysign(deviation)
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Returns the baseline and score of every sample.
+fn baselines(values: &[(u64, f64)], detector: TsAnomalyDetector) -> Vec<Option<(f64, f64)>> {
+    let mut result = Vec::with_capacity(values.len());
+    match detector {
+        TsAnomalyDetector::ZScore { window, .. } | TsAnomalyDetector::Mad { window, .. } => {
+            let mut previous: VecDeque<f64> = VecDeque::with_capacity(window);
+            for &(_, value) in values {
+                if value.is_nan() {
+                    result.push(None);
+                    continue;
+                }
+                result.push(if previous.len() < window.max(2) {
+                    None
+                } else if let TsAnomalyDetector::Mad { .. } = detector {
+                    let mut window: Vec<f64> = previous.iter().copied().collect();
+                    let center = median(&mut window);
+                    let mut deviations: Vec<f64> =
+                        window.iter().map(|v| (v - center).abs()).collect();
+                    let mad = median(&mut deviations);
+                    Some((center, score(0.6745 * (value - center), mad)))
+                } else {
+                    let n = previous.len() as f64;
+                    let mean = previous.iter().sum::<f64>() / n;
+                    let variance = previous.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+                    Some((mean, score(value - mean, variance.sqrt())))
+                });
+                if previous.len() == window.max(2) {
+                    previous.pop_front();
+                }
+                previous.push_back(value);
+            }
+        }
+        TsAnomalyDetector::Ewma { alpha, .. } => {
+            let warm_up = (1.0 / alpha).ceil().max(2.0) as usize;
+            let mut samples = 0;
+            let mut state: Option<(f64, f64)> = None;
+            for &(_, value) in values {
+                if value.is_nan() {
+                    result.push(None);
+                    continue;
+                }
+                result.push(
+                    state
+                        .filter(|_| samples >= warm_up)
+                        .map(|(mean, variance)| (mean, score(value - mean, variance.sqrt()))),
+                );
+                samples += 1;
+                state = Some(match state {
+                    None => (value, 0.0),
+                    Some((mean, variance)) => {
+                        let diff = value - mean;
+                        let increment = alpha * diff;
+                        (
+                            mean + increment,
+                            (1.0 - alpha) * (variance + diff * increment),
+                        )
+                    }
+                });
+            }
+        }
+        TsAnomalyDetector::Seasonal {
+            period,
+            seasons,
+            tolerance,
+            ..
+        } => {
+            let nearest = |ts: u64| -> Option<f64> {
+                let from = ts.saturating_sub(tolerance);
+                let start = values.partition_point(|(t, _)| *t < from);
+                values[start..]
+                    .iter()
+                    .take_while(|(t, _)| *t <= ts + tolerance)
+                    .filter(|(_, v)| !v.is_nan())
+                    .min_by_key(|(t, _)| t.abs_diff(ts))
+                    .map(|(_, v)| *v)
+            };
+            for &(ts, value) in values {
+                let earlier: Vec<f64> = (1..=seasons as u64)
+                    .filter_map(|k| ts.checked_sub(k * period))
+                    .filter_map(nearest)
+                    .collect();
+                if value.is_nan() || earlier.is_empty() {
+                    result.push(None);
+                    continue;
+                }
+                let baseline = earlier.iter().sum::<f64>() / earlier.len() as f64;
+                result.push(Some((baseline, score(value - baseline, baseline.abs()))));
+            }
+        }
+    }
+    result
+}
+
+fn annotate(values: &[(u64, f64)], detector: TsAnomalyDetector) -> Vec<TsAnomalySample> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by_key(|v| v.0);
+    let threshold = detector.threshold();
+    baselines(&sorted, detector)
+        .into_iter()
+        .zip(sorted)
+        .map(|(baseline, (ts, value))| TsAnomalySample {
+            ts,
+            value,
+            baseline: baseline.map(|b| b.0),
+            score: baseline.map(|b| b.1),
+            anomaly: baseline.is_some_and(|b| b.1.abs() > threshold),
+        })
+        .collect()
+}
+
+fn intervals(samples: &[TsAnomalySample]) -> Vec<TsAnomalyInterval> {
+    let mut result: Vec<TsAnomalyInterval> = vec![];
+    let mut open = false;
+    for sample in samples.iter().filter(|s| !s.value.is_nan()) {
+        if !sample.anomaly {
+            open = false;
+            continue;
+        }
+        let score = sample.score.unwrap_or_default().abs();
+        match result.last_mut() {
+            Some(interval) if open => {
+                interval.end = sample.ts;
+                interval.samples += 1;
+                interval.max_score = interval.max_score.max(score);
+            }
+            _ => result.push(TsAnomalyInterval {
+                start: sample.ts,
+                end: sample.ts,
+                samples: 1,
+                max_score: score,
+            }),
+        }
+        open = true;
+    }
+    result
+}
+
+impl TsRange<u64, f64> {
+    /// Scores all samples with the given detector in ascending order.
+    pub fn anomaly_scores(&self, detector: TsAnomalyDetector) -> Vec<TsAnomalySample> {
+        annotate(&self.values, detector)
+    }
+
+    /// Returns the intervals of consecutive anomalous samples.
+    pub fn anomalies(&self, detector: TsAnomalyDetector) -> Vec<TsAnomalyInterval> {
+        intervals(&self.anomaly_scores(detector))
+    }
+}
+
+impl TsMrangeEntry<u64, f64> {
+    /// Scores all samples with the given detector in ascending order.
+    pub fn anomaly_scores(&self, detector: TsAnomalyDetector) -> Vec<TsAnomalySample> {
+        annotate(&self.values, detector)
+    }
+
+    /// Returns the intervals of consecutive anomalous samples.
+    pub fn anomalies(&self, detector: TsAnomalyDetector) -> Vec<TsAnomalyInterval> {
+        intervals(&self.anomaly_scores(detector))
+    }
+}
+
+impl TsMrange<u64, f64> {
+    /// Returns the anomaly intervals of all series together with the series key.
+    pub fn anomalies(&self, detector: TsAnomalyDetector) -> Vec<(String, Vec<TsAnomalyInterval>)> {
+        self.values
+            .iter()
+            .map(|e| (e.key.clone(), e.anomalies(detector)))
+            .collect()
+    }
+}
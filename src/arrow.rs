@@ -0,0 +1,368 @@
+//! Conversions of time series query results into Apache Arrow record batches.
+//! Timestamps are represented as millisecond timestamps and values as 64 bit
+//! floats. Series keys and labels of multi series results are stored in
+//! dictionary encoded columns, labels of a single series in the schema metadata.
+//!
+//! ```rust
+//! use redis_ts::TsRange;
+//! use redis_ts::arrow::range_to_record_batch;
+//!
+//! let range = TsRange { values: vec![(1000, 1.0), (2000, 2.0)] };
+//! let batch = range_to_record_batch(&range).unwrap();
+//! assert_eq!(batch.num_rows(), 2);
+//! ```
+//!
+use crate::commands::TsCommands;
+use crate::types::*;
+use arrow_array::builder::{
+    ArrayBuilder, Float64Builder, StringDictionaryBuilder, TimestampMillisecondBuilder,
+};
+use arrow_array::types::Int32Type;
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use redis::{RedisError, RedisResult, ToRedisArgs};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// Schema metadata entry holding the series key.
+pub const KEY_METADATA: &str = "redis_ts.key";
+
+/// Prefix of schema metadata entries holding the series labels.
+pub const LABEL_METADATA_PREFIX: &str = "redis_ts.label.";
+
+fn timestamp_field(nullable: bool) -> Field {
+    Field::new(
+        "timestamp",
+        DataType::Timestamp(TimeUnit::Millisecond, None),
+        nullable,
+    )
+}
+
+fn value_field(nullable: bool) -> Field {
+    Field::new("value", DataType::Float64, nullable)
+}
+
+fn dictionary_field(name: &str, nullable: bool) -> Field {
+    Field::new(
+        name,
+        DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+        nullable,
+    )
+}
+
+fn series_metadata(key: &str, labels: &[(String, String)]) -> HashMap<String, String> {
+    let mut metadata = HashMap::new();
+    metadata.insert(KEY_METADATA.to_string(), key.to_string());
+    for (name, value) in labels {
+        metadata.insert(format!("{LABEL_METADATA_PREFIX}{name}"), value.to_string());
+    }
+    metadata
+}
+
+fn label_names<'a, I: Iterator<Item = &'a Vec<(String, String)>>>(labels: I) -> Vec<String> {
+    labels
+        .flat_map(|l| l.iter().map(|(name, _)| name.clone()))
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+fn label_value<'a>(labels: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    labels
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+/// Converts all samples into a single batch regardless of the builder batch size.
+fn single_batch(
+    mut builder: TsRecordBatchBuilder,
+    samples: &[(u64, f64)],
+) -> Result<RecordBatch, ArrowError> {
+    builder.batch_size = usize::MAX;
+    builder.append(samples)?;
+    let schema = builder.schema();
+    Ok(builder
+        .finish()?
+        .unwrap_or_else(|| RecordBatch::new_empty(schema)))
+}
+
+/// Converts a TS.RANGE result into a record batch with a timestamp and a value
+/// column.
+pub fn range_to_record_batch(range: &TsRange<u64, f64>) -> Result<RecordBatch, ArrowError> {
+    single_batch(TsRecordBatchBuilder::new(range.values.len()), &range.values)
+}
+
+/// Converts a single TS.MRANGE series into a record batch with a timestamp and
+/// a value column. The series key and labels are stored in the schema metadata.
+pub fn mrange_entry_to_record_batch(
+    entry: &TsMrangeEntry<u64, f64>,
+) -> Result<RecordBatch, ArrowError> {
+    let builder =
+        TsRecordBatchBuilder::new(entry.values.len()).with_metadata(&entry.key, &entry.labels);
+    single_batch(builder, &entry.values)
+}
+
+/// Converts a TS.MRANGE result into a single record batch in long format. Every
+/// row holds the series key, one dictionary column per label name (null if the
+/// series does not have that label), the timestamp and the value.
+pub fn mrange_to_record_batch(mrange: &TsMrange<u64, f64>) -> Result<RecordBatch, ArrowError> {
+    let names = label_names(mrange.values.iter().map(|e| &e.labels));
+    let mut fields = vec![dictionary_field("key", false)];
+    fields.extend(names.iter().map(|n| dictionary_field(n, true)));
+    fields.push(timestamp_field(false));
+    fields.push(value_field(false));
+
+    let rows: usize = mrange.values.iter().map(|e| e.values.len()).sum();
+    let mut keys = StringDictionaryBuilder::<Int32Type>::new();
+    let mut labels: Vec<StringDictionaryBuilder<Int32Type>> = names
+        .iter()
+        .map(|_| StringDictionaryBuilder::new())
+        .collect();
+    let mut timestamps = TimestampMillisecondBuilder::with_capacity(rows);
+    let mut values = Float64Builder::with_capacity(rows);
+
+    for entry in mrange.values.iter() {
+        for (ts, value) in entry.values.iter() {
+            keys.append_value(&entry.key);
+            for (name, builder) in names.iter().zip(labels.iter_mut()) {
+                builder.append_option(label_value(&entry.labels, name));
+            }
+            timestamps.append_value(*ts as i64);
+            values.append_value(*value);
+        }
+    }
+
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(keys.finish())];
+    columns.extend(labels.iter_mut().map(|b| Arc::new(b.finish()) as ArrayRef));
+    columns.push(Arc::new(timestamps.finish()));
+    columns.push(Arc::new(values.finish()));
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+/// Converts a TS.MGET result into a record batch with one row per series. Series
+/// without a sample have null timestamp and value.
+pub fn mget_to_record_batch(mget: &TsMget<u64, f64>) -> Result<RecordBatch, ArrowError> {
+    let names = label_names(mget.values.iter().map(|e| &e.labels));
+    let mut fields = vec![dictionary_field("key", false)];
+    fields.extend(names.iter().map(|n| dictionary_field(n, true)));
+    fields.push(timestamp_field(true));
+    fields.push(value_field(true));
+
+    let mut keys = StringDictionaryBuilder::<Int32Type>::new();
+    let mut labels: Vec<StringDictionaryBuilder<Int32Type>> = names
+        .iter()
+        .map(|_| StringDictionaryBuilder::new())
+        .collect();
+    let mut timestamps = TimestampMillisecondBuilder::with_capacity(mget.values.len());
+    let mut values = Float64Builder::with_capacity(mget.values.len());
+
+    for entry in mget.values.iter() {
+        keys.append_value(&entry.key);
+        for (name, builder) in names.iter().zip(labels.iter_mut()) {
+            builder.append_option(label_value(&entry.labels, name));
+        }
+        timestamps.append_option(entry.value.map(|(ts, _)| ts as i64));
+        values.append_option(entry.value.map(|(_, v)| v));
+    }
+
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(keys.finish())];
+    columns.extend(labels.iter_mut().map(|b| Arc::new(b.finish()) as ArrayRef));
+    columns.push(Arc::new(timestamps.finish()));
+    columns.push(Arc::new(values.finish()));
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+/// Incrementally builds record batches of a fixed maximum size from samples of a
+/// single series. Useful to convert large ranges that are fetched page by page.
+///
+/// ```rust
+/// use redis_ts::arrow::TsRecordBatchBuilder;
+///
+/// let mut builder = TsRecordBatchBuilder::new(2).with_metadata("my_ts", &[]);
+/// let full = builder.append(&[(1, 1.0), (2, 2.0), (3, 3.0)]).unwrap();
+/// assert_eq!(full.len(), 1);
+/// let rest = builder.finish().unwrap().unwrap();
+/// assert_eq!(rest.num_rows(), 1);
+/// ```
+///
+pub struct TsRecordBatchBuilder {
+    schema: SchemaRef,
+    batch_size: usize,
+    timestamps: TimestampMillisecondBuilder,
+    values: Float64Builder,
+}
+
+impl TsRecordBatchBuilder {
+    /// Creates a builder that emits batches with at most batch_size rows.
+    pub fn new(batch_size: usize) -> Self {
+        let batch_size = batch_size.max(1);
+        TsRecordBatchBuilder {
+            schema: Arc::new(Schema::new(vec![
+                timestamp_field(false),
+                value_field(false),
+            ])),
+            batch_size,
+            timestamps: TimestampMillisecondBuilder::with_capacity(batch_size),
+            values: Float64Builder::with_capacity(batch_size),
+        }
+    }
+
+    /// Stores the series key and labels in the schema metadata of all batches.
+    pub fn with_metadata(mut self, key: &str, labels: &[(String, String)]) -> Self {
+        let schema =
+            Schema::new(self.schema.fields().clone()).with_metadata(series_metadata(key, labels));
+        self.schema = Arc::new(schema);
+        self
+    }
+
+    /// Returns the schema of the emitted batches.
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Appends samples to the builder and returns all batches that reached the
+    /// configured batch size.
+    pub fn append(&mut self, samples: &[(u64, f64)]) -> Result<Vec<RecordBatch>, ArrowError> {
+        let mut batches = vec![];
+        for (ts, value) in samples {
+            self.timestamps.append_value(*ts as i64);
+            self.values.append_value(*value);
+            if self.values.len() >= self.batch_size {
+                batches.push(self.flush()?);
+            }
+        }
+        Ok(batches)
+    }
+
+    /// Returns a batch of all remaining samples or None if there are none.
+    pub fn finish(&mut self) -> Result<Option<RecordBatch>, ArrowError> {
+        if self.values.is_empty() {
+            return Ok(None);
+        }
+        self.flush().map(Some)
+    }
+
+    fn flush(&mut self) -> Result<RecordBatch, ArrowError> {
+        RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(self.timestamps.finish()),
+                Arc::new(self.values.finish()),
+            ],
+        )
+    }
+}
+
+fn arrow_error(e: ArrowError) -> RedisError {
+    RedisError::from(std::io::Error::other(e))
+}
+
+/// Paging state of a range fetched with at most page_size samples per request.
+/// The count of the query limits the samples of all pages together.
+struct TsRangePages {
+    query: TsRangeQuery,
+    page_size: u64,
+    remaining: Option<u64>,
+    from: Option<u64>,
+    done: bool,
+}
+
+impl TsRangePages {
+    fn new(query: TsRangeQuery, page_size: u64) -> RedisResult<Self> {
+        // The next page of an aggregated query would start inside the last
+        // bucket and return it again, partially aggregated.
+        if query.get_aggregation_type().is_some() {
+            return Err(RedisError::from(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "aggregated range queries can not be fetched page by page",
+            )));
+        }
+        Ok(TsRangePages {
+            remaining: query.get_count(),
+            query,
+            page_size: page_size.max(1),
+            from: None,
+            done: false,
+        })
+    }
+
+    /// Returns the query and sample count of the next page or None if all
+    /// pages have been fetched.
+    fn next_query(&self) -> Option<(TsRangeQuery, u64)> {
+        let count = self
+            .remaining
+            .map_or(self.page_size, |r| r.min(self.page_size));
+        if self.done || count == 0 {
+            return None;
+        }
+        let query = match self.from {
+            Some(from) => self.query.clone().from(from),
+            None => self.query.clone(),
+        };
+        Some((query.count(count), count))
+    }
+
+    /// Converts a fetched page into a record batch and continues after its
+    /// last sample. Returns None for an empty page.
+    fn next_batch(
+        &mut self,
+        page: RedisResult<TsRange<u64, f64>>,
+        count: u64,
+    ) -> Option<RedisResult<RecordBatch>> {
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+        let len = page.values.len() as u64;
+        self.done = len < count;
+        self.remaining = self.remaining.map(|r| r.saturating_sub(len));
+        let (ts, _) = page.values.last()?;
+        self.from = Some(ts + 1);
+        Some(range_to_record_batch(&page).map_err(arrow_error))
+    }
+}
+
+/// Fetches a range page by page with at most page_size samples per request and
+/// returns an iterator yielding one record batch per page. Pages are only
+/// fetched when the iterator is advanced. A count set on the query limits the
+/// number of samples of all batches. Aggregated queries are rejected, since
+/// buckets can not be split across pages.
+pub fn range_record_batches<'a, C: TsCommands, K: ToRedisArgs + Copy + 'a>(
+    con: &'a mut C,
+    key: K,
+    query: TsRangeQuery,
+    page_size: u64,
+) -> RedisResult<impl Iterator<Item = RedisResult<RecordBatch>> + 'a> {
+    let mut pages = TsRangePages::new(query, page_size)?;
+    Ok(std::iter::from_fn(move || {
+        let (query, count) = pages.next_query()?;
+        pages.next_batch(con.ts_range(key, query), count)
+    }))
+}
+
+/// Async version of range_record_batches, returning a stream of record batches.
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+pub fn range_record_batches_async<
+    'a,
+    C: crate::AsyncTsCommands,
+    K: ToRedisArgs + Send + Sync + Copy + 'a,
+>(
+    con: &'a mut C,
+    key: K,
+    query: TsRangeQuery,
+    page_size: u64,
+) -> RedisResult<impl futures_util::Stream<Item = RedisResult<RecordBatch>> + 'a> {
+    let pages = TsRangePages::new(query, page_size)?;
+    Ok(futures_util::stream::unfold(
+        (con, pages),
+        move |(con, mut pages)| async move {
+            let (query, count) = pages.next_query()?;
+            let batch = pages.next_batch(con.ts_range(key, query).await, count)?;
+            Some((batch, (con, pages)))
+        },
+    ))
+}
@@ -1,6 +1,8 @@
+use crate::candles::*;
+use crate::columns::{TsColumns, TsMcolumns};
 use crate::types::*;
 use redis::aio::ConnectionLike;
-use redis::{cmd, FromRedisValue, RedisFuture, ToRedisArgs};
+use redis::{cmd, pipe, FromRedisValue, RedisFuture, ToRedisArgs};
 
 /// Provides a high level synchronous API to work with redis time series data types. Uses some abstractions
 /// for easier handling of time series related redis command arguments. All commands are directly
@@ -11,7 +13,7 @@ use redis::{cmd, FromRedisValue, RedisFuture, ToRedisArgs};
 /// use redis_ts::{AsyncTsCommands, TsOptions};
 ///
 /// let client = redis::Client::open("redis://127.0.0.1/")?;
-/// let mut con = client.get_async_connection().await?;
+/// let mut con = client.get_multiplexed_async_connection().await?;
 ///
 /// let _:() = con.ts_create("my_ts", TsOptions::default()).await?;
 /// let ts:u64 = con.ts_add_now("my_ts", 2.0).await?;
@@ -21,7 +23,10 @@ use redis::{cmd, FromRedisValue, RedisFuture, ToRedisArgs};
 ///
 pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
     /// Returns information about a redis time series key.
-    fn ts_info<'a, K: ToRedisArgs + Send + Sync + 'a>(&'a mut self, key: K) -> RedisFuture<TsInfo> {
+    fn ts_info<'a, K: ToRedisArgs + Send + Sync + 'a>(
+        &'a mut self,
+        key: K,
+    ) -> RedisFuture<'a, TsInfo> {
         Box::pin(async move { cmd("TS.INFO").arg(key).query_async(self).await })
     }
 
@@ -30,7 +35,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         &'a mut self,
         key: K,
         options: TsOptions,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.CREATE")
                 .arg(key)
@@ -45,7 +50,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         &'a mut self,
         key: K,
         options: TsOptions,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.ALTER")
                 .arg(key)
@@ -67,7 +72,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         key: K,
         ts: TS,
         value: V,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.ADD")
                 .arg(key)
@@ -89,7 +94,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         &'a mut self,
         key: K,
         value: V,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.ADD")
                 .arg(key)
@@ -114,7 +119,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         ts: TS,
         value: V,
         options: TsOptions,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.ADD")
                 .arg(key)
@@ -136,7 +141,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
     >(
         &'a mut self,
         values: &'a [(K, TS, V)],
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move { cmd("TS.MADD").arg(values).query_async(self).await })
     }
 
@@ -150,7 +155,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         &'a mut self,
         key: K,
         value: V,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move { cmd("TS.INCRBY").arg(key).arg(value).query_async(self).await })
     }
 
@@ -166,7 +171,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         key: K,
         ts: TS,
         value: V,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.INCRBY")
                 .arg(key)
@@ -192,7 +197,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         ts: TS,
         value: V,
         options: TsOptions,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.INCRBY")
                 .arg(key)
@@ -215,7 +220,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         &'a mut self,
         key: K,
         value: V,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move { cmd("TS.DECRBY").arg(key).arg(value).query_async(self).await })
     }
 
@@ -231,7 +236,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         key: K,
         ts: TS,
         value: V,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.DECRBY")
                 .arg(key)
@@ -257,7 +262,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         ts: TS,
         value: V,
         options: TsOptions,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.DECRBY")
                 .arg(key)
@@ -276,7 +281,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         source_key: K,
         dest_key: K,
         aggregation_type: TsAggregationType,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.CREATERULE")
                 .arg(source_key)
@@ -292,7 +297,7 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         &'a mut self,
         source_key: K,
         dest_key: K,
-    ) -> RedisFuture<RV> {
+    ) -> RedisFuture<'a, RV> {
         Box::pin(async move {
             cmd("TS.DELETERULE")
                 .arg(source_key)
@@ -302,19 +307,107 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
         })
     }
 
+    /// Returns open/high/low/close candles between from and to where each candle
+    /// covers a bucket of the given size in millis. The underlying aggregations
+    /// share the same alignment and are sent in a single MULTI/EXEC transaction
+    /// so all of them see the same samples.
+    fn ts_candles<'a, K: ToRedisArgs + Send + Sync + 'a>(
+        &'a mut self,
+        key: K,
+        from: u64,
+        to: u64,
+        bucket: u64,
+    ) -> RedisFuture<'a, Vec<TsCandle>> {
+        let mut p = pipe();
+        p.atomic();
+        for query in candle_queries(from, to, bucket) {
+            p.cmd("TS.RANGE").arg(&key).arg(query);
+        }
+        Box::pin(async move {
+            let (first, max, min, last, count): CandleRanges = p.query_async(self).await?;
+            Ok(merge_candles([
+                first.values,
+                max.values,
+                min.values,
+                last.values,
+                count.values,
+            ]))
+        })
+    }
+
+    /// Returns open/high/low/close candles for all time series matching the given
+    /// filter, keyed by series. Like ts_candles the queries run in one transaction.
+    fn ts_mcandles(
+        &mut self,
+        from: u64,
+        to: u64,
+        bucket: u64,
+        filter_options: TsFilterOptions,
+    ) -> RedisFuture<'_, TsMcandles> {
+        let mut p = pipe();
+        p.atomic();
+        for query in candle_queries(from, to, bucket) {
+            p.cmd("TS.MRANGE").arg(query).arg(filter_options.clone());
+        }
+        Box::pin(async move {
+            let (first, max, min, last, count): CandleMranges = p.query_async(self).await?;
+            Ok(merge_mcandles([first, max, min, last, count]))
+        })
+    }
+
+    /// Queries a counter and returns its per second rate over the window (in
+    /// millis) ending at each sample. See TsRange::rate for details.
+    fn ts_rate<'a, K: ToRedisArgs + Send + Sync + 'a>(
+        &'a mut self,
+        key: K,
+        query: TsRangeQuery,
+        window: u64,
+    ) -> RedisFuture<'a, TsRange<u64, f64>> {
+        Box::pin(async move {
+            let range: TsRange<u64, f64> = self.ts_range(key, query).await?;
+            Ok(range.rate(window))
+        })
+    }
+
+    /// Executes a redis time series range query and decodes the samples
+    /// straight into a timestamp and a value column.
+    fn ts_range_columns<'a, K: ToRedisArgs + Send + Sync + 'a>(
+        &'a mut self,
+        key: K,
+        query: TsRangeQuery,
+    ) -> RedisFuture<'a, TsColumns> {
+        Box::pin(async move { cmd("TS.RANGE").arg(key).arg(query).query_async(self).await })
+    }
+
+    /// Executes multiple redis time series range queries and decodes the
+    /// samples of every series straight into columns.
+    fn ts_mrange_columns(
+        &mut self,
+        query: TsRangeQuery,
+        filter_options: TsFilterOptions,
+    ) -> RedisFuture<'_, TsMcolumns> {
+        Box::pin(async move {
+            cmd("TS.MRANGE")
+                .arg(query)
+                .arg(filter_options)
+                .query_async(self)
+                .await
+        })
+    }
+
     /// Returns the latest (current) value in a redis time series.
     fn ts_get<'a, K: ToRedisArgs + Send + Sync + 'a, TS: FromRedisValue, V: FromRedisValue>(
         &'a mut self,
         key: K,
-    ) -> RedisFuture<Option<(TS, V)>> {
+    ) -> RedisFuture<'a, Option<(TS, V)>> {
         Box::pin(async move { cmd("TS.GET").arg(key).query_async(self).await.or(Ok(None)) })
     }
 
     /// Returns the latest (current) value from multiple redis time series.
     fn ts_mget<'a, TS: Default + FromRedisValue + 'a, V: Default + FromRedisValue + 'a>(
-        &mut self,
+        &'a mut self,
         filter_options: TsFilterOptions,
-    ) -> RedisFuture<TsMget<TS, V>> {
+    ) -> RedisFuture<'a, TsMget<TS, V>> {
         Box::pin(async move { cmd("TS.MGET").arg(filter_options).query_async(self).await })
     }
 
@@ -322,14 +415,14 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
     fn range<
         'a,
         K: ToRedisArgs + Send + Sync + 'a,
-        TS: Default + FromRedisValue + Copy,
-        V: Default + FromRedisValue + Copy,
+        TS: Default + FromRedisValue,
+        V: Default + FromRedisValue,
     >(
         &'a mut self,
         command: &str,
         key: K,
         query: TsRangeQuery,
-    ) -> RedisFuture<TsRange<TS, V>> {
+    ) -> RedisFuture<'a, TsRange<TS, V>> {
         let mut c = cmd(command);
         c.arg(key).arg(query);
         Box::pin(async move { c.query_async(self).await })
@@ -339,13 +432,13 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
     fn ts_range<
         'a,
         K: ToRedisArgs + Send + Sync + 'a,
-        TS: Default + FromRedisValue + Copy,
-        V: Default + FromRedisValue + Copy,
+        TS: Default + FromRedisValue,
+        V: Default + FromRedisValue,
     >(
         &'a mut self,
         key: K,
         query: TsRangeQuery,
-    ) -> RedisFuture<TsRange<TS, V>> {
+    ) -> RedisFuture<'a, TsRange<TS, V>> {
         self.range("TS.RANGE", key, query)
     }
 
@@ -353,23 +446,23 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
     fn ts_revrange<
         'a,
         K: ToRedisArgs + Send + Sync + 'a,
-        TS: Default + FromRedisValue + Copy,
-        V: Default + FromRedisValue + Copy,
+        TS: Default + FromRedisValue,
+        V: Default + FromRedisValue,
     >(
         &'a mut self,
         key: K,
         query: TsRangeQuery,
-    ) -> RedisFuture<TsRange<TS, V>> {
+    ) -> RedisFuture<'a, TsRange<TS, V>> {
         self.range("TS.REVRANGE", key, query)
     }
 
     #[doc(hidden)]
-    fn mrange<'a, TS: Default + FromRedisValue + Copy, V: Default + FromRedisValue + Copy>(
-        &mut self,
+    fn mrange<'a, TS: Default + FromRedisValue, V: Default + FromRedisValue>(
+        &'a mut self,
         command: &str,
         query: TsRangeQuery,
         filter_options: TsFilterOptions,
-    ) -> RedisFuture<TsMrange<TS, V>> {
+    ) -> RedisFuture<'a, TsMrange<TS, V>> {
         let mut c = cmd(command);
         c.arg(query).arg(filter_options);
 
@@ -377,25 +470,25 @@ pub trait AsyncTsCommands: ConnectionLike + Send + Sized {
     }
 
     /// Executes multiple redis time series range queries.
-    fn ts_mrange<'a, TS: Default + FromRedisValue + Copy, V: Default + FromRedisValue + Copy>(
-        &mut self,
+    fn ts_mrange<'a, TS: Default + FromRedisValue, V: Default + FromRedisValue>(
+        &'a mut self,
         query: TsRangeQuery,
         filter_options: TsFilterOptions,
-    ) -> RedisFuture<TsMrange<TS, V>> {
+    ) -> RedisFuture<'a, TsMrange<TS, V>> {
         self.mrange("TS.MRANGE", query, filter_options)
     }
 
     /// Executes multiple redis time series revrange queries.
-    fn ts_mrevrange<'a, TS: Default + FromRedisValue + Copy, V: Default + FromRedisValue + Copy>(
-        &mut self,
+    fn ts_mrevrange<'a, TS: Default + FromRedisValue, V: Default + FromRedisValue>(
+        &'a mut self,
         query: TsRangeQuery,
         filter_options: TsFilterOptions,
-    ) -> RedisFuture<TsMrange<TS, V>> {
+    ) -> RedisFuture<'a, TsMrange<TS, V>> {
         self.mrange("TS.MREVRANGE", query, filter_options)
     }
 
     /// Returns a filtered list of redis time series keys.
-    fn ts_queryindex(&mut self, filter_options: TsFilterOptions) -> RedisFuture<Vec<String>> {
+    fn ts_queryindex(&mut self, filter_options: TsFilterOptions) -> RedisFuture<'_, Vec<String>> {
         Box::pin(async move {
             cmd("TS.QUERYINDEX")
                 .arg(filter_options.get_filters())
@@ -0,0 +1,708 @@
+//! `redis-ts`: A command line tool to inspect and administrate redis time series.
+//!
+//! All commands print aligned tables by default and JSON with `--json`. Keys can
+//! be selected one at a time or with a filter expression in TS.MRANGE syntax,
+//! eg. `--filter "sensor=temperature room!=(attic,cellar)"`.
+//!
+//! ```text
+//! redis-ts info engine:1
+//! redis-ts list --filter sensor=temperature
+//! redis-ts range engine:1 --from 2021-01-01T00:00:00Z --agg avg:60000
+//! redis-ts rule add engine:1 engine:1:avg --agg avg:60000
+//! redis-ts export csv --filter sensor=temperature --output temperatures.csv
+//! redis-ts tail --filter sensor=temperature
+//! ```
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use redis::{Connection, RedisResult};
+use redis_ts::io::csv::{
+    import, parse_timestamp, write_mrange, write_range, TsCsvFormat, TsCsvImport, TsTimestampFormat,
+};
+use redis_ts::io::{aggregation, parse_filter};
+use redis_ts::{
+    TsAggregationType, TsCommands, TsDuplicatePolicy, TsFilterOptions, TsInfo, TsMget, TsMrange,
+    TsMrangeEntry, TsOptions, TsRange, TsRangeQuery,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(
+    name = "redis-ts",
+    version,
+    about = "Inspect and administrate redis time series"
+)]
+struct Cli {
+    /// The redis connection url.
+    #[arg(
+        long,
+        env = "REDIS_URL",
+        default_value = "redis://127.0.0.1/",
+        global = true
+    )]
+    url: String,
+
+    /// Prints JSON instead of tables.
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Shows the TS.INFO of a key.
+    Info { key: String },
+    /// Lists all keys matching a filter.
+    List {
+        #[arg(long)]
+        filter: String,
+    },
+    /// Prints the samples of a key or all keys matching a filter.
+    Range(RangeArgs),
+    /// Prints the latest sample of all keys matching a filter.
+    Mget {
+        #[arg(long)]
+        filter: String,
+    },
+    /// Creates a new key.
+    Create {
+        key: String,
+        #[command(flatten)]
+        options: OptionArgs,
+        /// Stores samples uncompressed.
+        #[arg(long)]
+        uncompressed: bool,
+    },
+    /// Changes the retention, labels or policies of a key.
+    Alter {
+        key: String,
+        #[command(flatten)]
+        options: OptionArgs,
+    },
+    /// Adds or deletes compaction rules.
+    #[command(subcommand)]
+    Rule(RuleCommand),
+    /// Imports samples from a file.
+    #[command(subcommand)]
+    Import(ImportCommand),
+    /// Exports the samples of a key or all keys matching a filter.
+    Export {
+        #[arg(value_enum)]
+        format: ExportFormat,
+        #[command(flatten)]
+        range: RangeArgs,
+        /// Writes wide instead of long CSV.
+        #[arg(long)]
+        wide: bool,
+        /// The file to write, defaults to stdout.
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+    /// Prints new samples of a key or all keys matching a filter as they arrive.
+    Tail {
+        #[command(flatten)]
+        target: TargetArgs,
+        /// The poll interval in millis.
+        #[arg(long, default_value_t = 1000)]
+        interval: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum RuleCommand {
+    /// Creates a compaction rule from source to dest.
+    Add {
+        source: String,
+        dest: String,
+        /// The aggregation as name:bucket, eg. avg:60000.
+        #[arg(long, value_parser = parse_aggregation)]
+        agg: TsAggregationType,
+    },
+    /// Deletes the compaction rule from source to dest.
+    Del { source: String, dest: String },
+}
+
+#[derive(Subcommand)]
+enum ImportCommand {
+    /// Imports CSV data in long format (one sample per row) or wide format (one
+    /// column per key).
+    Csv(CsvImportArgs),
+}
+
+#[derive(Args)]
+struct CsvImportArgs {
+    file: String,
+    /// Reads wide CSV with one column per key.
+    #[arg(long)]
+    wide: bool,
+    #[arg(long, default_value = "key")]
+    key_column: String,
+    #[arg(long, default_value = "timestamp")]
+    timestamp_column: String,
+    #[arg(long, default_value = "value")]
+    value_column: String,
+    /// Reads labels of created keys from this column of long CSV.
+    #[arg(long)]
+    labels_column: Option<String>,
+    #[arg(long, value_enum, default_value = "millis")]
+    timestamp_format: TimestampFormat,
+    /// Prepended to all imported keys.
+    #[arg(long, default_value = "")]
+    prefix: String,
+    /// Creates missing keys with the given options.
+    #[arg(long)]
+    create: bool,
+    #[command(flatten)]
+    options: OptionArgs,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct TargetArgs {
+    /// A single key.
+    key: Option<String>,
+    /// A filter expression, eg. "sensor=temperature room!=(attic,cellar)".
+    #[arg(long)]
+    filter: Option<String>,
+}
+
+#[derive(Args)]
+struct RangeArgs {
+    #[command(flatten)]
+    target: TargetArgs,
+    /// Start as millis or RFC3339, defaults to the first sample.
+    #[arg(long, value_parser = parse_time)]
+    from: Option<u64>,
+    /// End as millis or RFC3339, defaults to the last sample.
+    #[arg(long, value_parser = parse_time)]
+    to: Option<u64>,
+    /// The aggregation as name:bucket, eg. avg:60000.
+    #[arg(long, value_parser = parse_aggregation)]
+    agg: Option<TsAggregationType>,
+    /// The max amount of samples per key.
+    #[arg(long)]
+    count: Option<u64>,
+}
+
+#[derive(Args)]
+struct OptionArgs {
+    /// The retention time in millis.
+    #[arg(long)]
+    retention: Option<u64>,
+    /// A label as name=value, can be repeated.
+    #[arg(long = "label", value_parser = parse_label)]
+    labels: Vec<(String, String)>,
+    /// The duplicate policy, eg. last or block.
+    #[arg(long, value_parser = parse_duplicate_policy)]
+    duplicate_policy: Option<TsDuplicatePolicy>,
+    /// The chunk size in bytes.
+    #[arg(long)]
+    chunk_size: Option<u64>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TimestampFormat {
+    Millis,
+    Seconds,
+    Rfc3339,
+}
+
+fn parse_aggregation(value: &str) -> Result<TsAggregationType, String> {
+    let (name, bucket) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected name:bucket, got {value}"))?;
+    let bucket = bucket
+        .parse::<u64>()
+        .map_err(|_| format!("invalid bucket {bucket}"))?;
+    aggregation(name, bucket).ok_or_else(|| format!("unknown aggregation {name}"))
+}
+
+fn parse_time(value: &str) -> Result<u64, String> {
+    let format = if value.bytes().all(|c| c.is_ascii_digit()) {
+        TsTimestampFormat::Millis
+    } else {
+        TsTimestampFormat::Rfc3339
+    };
+    parse_timestamp(value, format).map_err(|_| format!("invalid time {value}"))
+}
+
+fn parse_label(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .ok_or_else(|| format!("expected name=value, got {value}"))
+}
+
+fn parse_duplicate_policy(value: &str) -> Result<TsDuplicatePolicy, String> {
+    Ok(match value.to_lowercase().as_str() {
+        "block" => TsDuplicatePolicy::Block,
+        "first" => TsDuplicatePolicy::First,
+        "last" => TsDuplicatePolicy::Last,
+        "min" => TsDuplicatePolicy::Min,
+        "max" => TsDuplicatePolicy::Max,
+        "sum" => TsDuplicatePolicy::Sum,
+        other => TsDuplicatePolicy::Other(other.to_uppercase()),
+    })
+}
+
+impl OptionArgs {
+    fn ts_options(&self) -> TsOptions {
+        let mut options = TsOptions::default();
+        if let Some(retention) = self.retention {
+            options = options.retention_time(retention);
+        }
+        for (name, value) in self.labels.iter() {
+            options = options.label(name, value);
+        }
+        if let Some(policy) = self.duplicate_policy.clone() {
+            options = options.duplicate_policy(policy);
+        }
+        if let Some(size) = self.chunk_size {
+            options = options.chunk_size(size);
+        }
+        options
+    }
+}
+
+impl RangeArgs {
+    fn query(&self) -> TsRangeQuery {
+        let mut query = TsRangeQuery::default();
+        if let Some(from) = self.from {
+            query = query.from(from);
+        }
+        if let Some(to) = self.to {
+            query = query.to(to);
+        }
+        if let Some(agg) = self.agg {
+            query = query.aggregation_type(agg);
+        }
+        if let Some(count) = self.count {
+            query = query.count(count);
+        }
+        query
+    }
+}
+
+fn filter(expression: &str) -> RedisResult<TsFilterOptions> {
+    parse_filter(TsFilterOptions::default().with_labels(true), expression)
+}
+
+/// Runs a range query on a single key or all keys matching the filter.
+fn fetch(
+    con: &mut Connection,
+    target: &TargetArgs,
+    query: TsRangeQuery,
+) -> RedisResult<TsMrange<u64, f64>> {
+    match (&target.key, &target.filter) {
+        (Some(key), _) => {
+            let range: TsRange<u64, f64> = con.ts_range(key.as_str(), query)?;
+            Ok(TsMrange {
+                values: vec![TsMrangeEntry {
+                    key: key.clone(),
+                    labels: vec![],
+                    values: range.values,
+                }],
+            })
+        }
+        (None, Some(expression)) => con.ts_mrange(query, filter(expression)?),
+        (None, None) => unreachable!("clap requires a key or filter"),
+    }
+}
+
+fn format_labels(labels: &[(String, String)]) -> String {
+    labels
+        .iter()
+        .map(|(n, v)| format!("{n}={v}"))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn labels_json(labels: &[(String, String)]) -> Value {
+    Value::Object(
+        labels
+            .iter()
+            .map(|(n, v)| (n.clone(), Value::String(v.clone())))
+            .collect(),
+    )
+}
+
+/// Prints rows as a table with aligned columns below the header.
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<String>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(c, w)| format!("{c:<w$}"))
+            .collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(headers.iter().map(|h| h.to_string()).collect());
+    line(widths.iter().map(|w| "-".repeat(*w)).collect());
+    for row in rows {
+        line(row);
+    }
+}
+
+fn mrange_json(mrange: &TsMrange<u64, f64>) -> Value {
+    Value::Array(
+        mrange
+            .values
+            .iter()
+            .map(|e| {
+                json!({
+                    "key": e.key,
+                    "labels": labels_json(&e.labels),
+                    "samples": e.values.iter().map(|(ts, v)| json!([ts, v])).collect::<Vec<_>>(),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn info_json(key: &str, info: &TsInfo) -> Value {
+    json!({
+        "key": key,
+        "totalSamples": info.total_samples,
+        "memoryUsage": info.memory_usage,
+        "firstTimestamp": info.first_timestamp,
+        "lastTimestamp": info.last_timestamp,
+        "retentionTime": info.retention_time,
+        "chunkCount": info.chunk_count,
+        "chunkSize": info.chunk_size,
+        "duplicatePolicy": info.duplicate_policy.as_ref().map(|p| format!("{p:?}").to_lowercase()),
+        "labels": labels_json(&info.labels),
+        "sourceKey": info.source_key,
+        "rules": info.rules.iter().map(|(dest, bucket, agg)| json!({
+            "dest": dest,
+            "bucket": bucket,
+            "aggregation": agg,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn print_info(key: &str, info: &TsInfo) {
+    let rules: Vec<String> = info
+        .rules
+        .iter()
+        .map(|(dest, bucket, agg)| format!("{dest} ({agg}:{bucket})"))
+        .collect();
+    let policy = info
+        .duplicate_policy
+        .as_ref()
+        .map(|p| format!("{p:?}").to_lowercase())
+        .unwrap_or_default();
+    let rows = vec![
+        ("key", key.to_string()),
+        ("total samples", info.total_samples.to_string()),
+        ("memory usage", info.memory_usage.to_string()),
+        ("first timestamp", info.first_timestamp.to_string()),
+        ("last timestamp", info.last_timestamp.to_string()),
+        ("retention time", info.retention_time.to_string()),
+        ("chunk count", info.chunk_count.to_string()),
+        ("chunk size", info.chunk_size.to_string()),
+        ("duplicate policy", policy),
+        ("labels", format_labels(&info.labels)),
+        ("source key", info.source_key.clone().unwrap_or_default()),
+        ("rules", rules.join(", ")),
+    ];
+    print_table(
+        &["field", "value"],
+        rows.into_iter()
+            .map(|(field, value)| vec![field.to_string(), value])
+            .collect(),
+    );
+}
+
+fn print_samples(mrange: &TsMrange<u64, f64>) {
+    let rows = mrange
+        .values
+        .iter()
+        .flat_map(|e| {
+            e.values
+                .iter()
+                .map(move |(ts, v)| vec![e.key.clone(), ts.to_string(), v.to_string()])
+        })
+        .collect();
+    print_table(&["key", "timestamp", "value"], rows);
+}
+
+fn print_json(value: &Value) {
+    println!("{value:#}");
+}
+
+/// Prints the outcome of a command that changes data.
+fn print_status(json: bool, message: String, value: Value) {
+    if json {
+        print_json(&value);
+    } else {
+        println!("{message}");
+    }
+}
+
+/// Returns the latest sample of a single key or all keys matching the filter.
+fn latest(con: &mut Connection, target: &TargetArgs) -> RedisResult<Vec<(String, u64, f64)>> {
+    let samples = match (&target.key, &target.filter) {
+        (Some(key), _) => {
+            let value: Option<(u64, f64)> = con.ts_get(key.as_str())?;
+            value
+                .map(|(ts, v)| (key.clone(), ts, v))
+                .into_iter()
+                .collect()
+        }
+        (None, Some(expression)) => {
+            let mget: TsMget<u64, f64> = con.ts_mget(filter(expression)?)?;
+            mget.values
+                .into_iter()
+                .filter_map(|e| e.value.map(|(ts, v)| (e.key, ts, v)))
+                .collect()
+        }
+        (None, None) => unreachable!("clap requires a key or filter"),
+    };
+    Ok(samples)
+}
+
+/// Returns a single key or all keys currently matching the filter.
+fn keys(con: &mut Connection, target: &TargetArgs) -> RedisResult<Vec<String>> {
+    match (&target.key, &target.filter) {
+        (Some(key), _) => Ok(vec![key.clone()]),
+        (None, Some(expression)) => con.ts_queryindex(filter(expression)?),
+        (None, None) => unreachable!("clap requires a key or filter"),
+    }
+}
+
+fn tail(con: &mut Connection, target: &TargetArgs, interval: u64, json: bool) -> RedisResult<()> {
+    let mut stdout = std::io::stdout();
+    // The timestamp of the last printed sample per key.
+    let mut last: HashMap<String, u64> = HashMap::new();
+    let mut samples = latest(con, target)?;
+    loop {
+        for (key, ts, value) in samples {
+            if json {
+                writeln!(
+                    stdout,
+                    "{}",
+                    json!({"key": key, "timestamp": ts, "value": value})
+                )?;
+            } else {
+                writeln!(stdout, "{key}  {ts}  {value}")?;
+            }
+            last.insert(key, ts);
+        }
+        stdout.flush()?;
+        std::thread::sleep(Duration::from_millis(interval));
+
+        // Every key is read after its own last printed sample, keys that got
+        // their first samples or started matching since are read from the
+        // beginning.
+        samples = vec![];
+        for key in keys(con, target)? {
+            let from = last.get(&key).map(|ts| ts + 1).unwrap_or(0);
+            let range: TsRange<u64, f64> =
+                con.ts_range(key.as_str(), TsRangeQuery::default().from(from))?;
+            samples.extend(range.values.into_iter().map(|(ts, v)| (key.clone(), ts, v)));
+        }
+    }
+}
+
+fn run(cli: Cli) -> RedisResult<()> {
+    let mut con = redis::Client::open(cli.url.as_str())?.get_connection()?;
+    match cli.command {
+        Command::Info { key } => {
+            let info = con.ts_info(key.as_str())?;
+            if cli.json {
+                print_json(&info_json(&key, &info));
+            } else {
+                print_info(&key, &info);
+            }
+        }
+        Command::List { filter: expression } => {
+            let mut keys = con.ts_queryindex(filter(&expression)?)?;
+            keys.sort();
+            if cli.json {
+                print_json(&json!(keys));
+            } else {
+                print_table(&["key"], keys.into_iter().map(|k| vec![k]).collect());
+            }
+        }
+        Command::Range(args) => {
+            let mrange = fetch(&mut con, &args.target, args.query())?;
+            if cli.json {
+                print_json(&mrange_json(&mrange));
+            } else {
+                print_samples(&mrange);
+            }
+        }
+        Command::Mget { filter: expression } => {
+            let mget: TsMget<u64, f64> = con.ts_mget(filter(&expression)?)?;
+            if cli.json {
+                let values: Vec<Value> = mget
+                    .values
+                    .iter()
+                    .map(|e| {
+                        json!({
+                            "key": e.key,
+                            "labels": labels_json(&e.labels),
+                            "timestamp": e.value.map(|(ts, _)| ts),
+                            "value": e.value.map(|(_, v)| v),
+                        })
+                    })
+                    .collect();
+                print_json(&Value::Array(values));
+            } else {
+                let rows = mget
+                    .values
+                    .iter()
+                    .map(|e| {
+                        let (ts, value) = match e.value {
+                            Some((ts, v)) => (ts.to_string(), v.to_string()),
+                            None => (String::new(), String::new()),
+                        };
+                        vec![e.key.clone(), ts, value, format_labels(&e.labels)]
+                    })
+                    .collect();
+                print_table(&["key", "timestamp", "value", "labels"], rows);
+            }
+        }
+        Command::Create {
+            key,
+            options,
+            uncompressed,
+        } => {
+            let options = options.ts_options().uncompressed(uncompressed);
+            let _: () = con.ts_create(key.as_str(), options)?;
+            print_status(cli.json, format!("created {key}"), json!({"created": key}));
+        }
+        Command::Alter { key, options } => {
+            let _: () = con.ts_alter(key.as_str(), options.ts_options())?;
+            print_status(cli.json, format!("altered {key}"), json!({"altered": key}));
+        }
+        Command::Rule(RuleCommand::Add { source, dest, agg }) => {
+            let _: () = con.ts_createrule(source.as_str(), dest.as_str(), agg)?;
+            print_status(
+                cli.json,
+                format!("added rule {source} -> {dest}"),
+                json!({"added": {"source": source, "dest": dest}}),
+            );
+        }
+        Command::Rule(RuleCommand::Del { source, dest }) => {
+            let _: () = con.ts_deleterule(source.as_str(), dest.as_str())?;
+            print_status(
+                cli.json,
+                format!("deleted rule {source} -> {dest}"),
+                json!({"deleted": {"source": source, "dest": dest}}),
+            );
+        }
+        Command::Import(ImportCommand::Csv(args)) => {
+            let mut config = if args.wide {
+                TsCsvImport::wide(&args.timestamp_column)
+            } else {
+                let config =
+                    TsCsvImport::long(&args.key_column, &args.timestamp_column, &args.value_column);
+                match &args.labels_column {
+                    Some(column) => config.labels_column(column),
+                    None => config,
+                }
+            };
+            config = config
+                .timestamp_format(match args.timestamp_format {
+                    TimestampFormat::Millis => TsTimestampFormat::Millis,
+                    TimestampFormat::Seconds => TsTimestampFormat::Seconds,
+                    TimestampFormat::Rfc3339 => TsTimestampFormat::Rfc3339,
+                })
+                .key_prefix(&args.prefix);
+            if args.create {
+                config = config.options(args.options.ts_options());
+            }
+            let report = import(&mut con, File::open(&args.file)?, &config)?;
+            let rejected: Vec<Value> = report
+                .rejected
+                .iter()
+                .map(|r| {
+                    json!({
+                        "line": r.line,
+                        "key": r.key,
+                        "timestamp": r.ts,
+                        "value": r.value,
+                        "reason": r.reason,
+                    })
+                })
+                .collect();
+            if !cli.json {
+                for r in report.rejected.iter() {
+                    eprintln!(
+                        "line {}: rejected {} {} {}: {}",
+                        r.line, r.key, r.ts, r.value, r.reason
+                    );
+                }
+            }
+            let message = match report.rejected.len() {
+                0 => format!("imported {} samples", report.imported),
+                n => format!("imported {} samples, rejected {n}", report.imported),
+            };
+            print_status(
+                cli.json,
+                message,
+                json!({"imported": report.imported, "rejected": rejected}),
+            );
+        }
+        Command::Export {
+            format,
+            range,
+            wide,
+            output,
+        } => {
+            // Fetch first, so a failing query does not truncate the output file.
+            let mrange = fetch(&mut con, &range.target, range.query())?;
+            let writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(std::io::stdout()),
+            };
+            match format {
+                ExportFormat::Json => {
+                    let mut writer = writer;
+                    writeln!(writer, "{}", mrange_json(&mrange))?;
+                }
+                ExportFormat::Csv if wide => write_mrange(writer, &mrange, TsCsvFormat::Wide)?,
+                ExportFormat::Csv => match &range.target.key {
+                    Some(key) => write_range(
+                        writer,
+                        key,
+                        &TsRange {
+                            values: mrange.values[0].values.clone(),
+                        },
+                    )?,
+                    None => write_mrange(writer, &mrange, TsCsvFormat::Long)?,
+                },
+            }
+        }
+        Command::Tail { target, interval } => tail(&mut con, &target, interval, cli.json)?,
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
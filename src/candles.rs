@@ -0,0 +1,131 @@
+use crate::types::*;
+use std::collections::BTreeMap;
+
+/// Represents a single open/high/low/close bar of a time series bucket. Candles are
+/// computed from the server side FIRST, MAX, MIN, LAST and COUNT aggregations of
+/// the same bucket.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TsCandle {
+    pub ts: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub count: u64,
+}
+
+/// Represents the candles of multiple time series as returned by a ts_mcandles
+/// query.
+#[derive(Debug, Default)]
+pub struct TsMcandles {
+    pub values: Vec<TsMcandlesEntry>,
+}
+
+/// Represents the candles of a single time series within a ts_mcandles result.
+#[derive(Debug, Default)]
+pub struct TsMcandlesEntry {
+    pub key: String,
+    pub labels: Vec<(String, String)>,
+    pub candles: Vec<TsCandle>,
+}
+
+/// Pipeline reply of the candle range queries.
+pub(crate) type CandleRanges = (
+    TsRange<u64, f64>,
+    TsRange<u64, f64>,
+    TsRange<u64, f64>,
+    TsRange<u64, f64>,
+    TsRange<u64, f64>,
+);
+
+/// Pipeline reply of the candle multi range queries.
+pub(crate) type CandleMranges = (
+    TsMrange<u64, f64>,
+    TsMrange<u64, f64>,
+    TsMrange<u64, f64>,
+    TsMrange<u64, f64>,
+    TsMrange<u64, f64>,
+);
+
+/// The aggregations needed to build a candle, in the order they are queried.
+fn candle_aggregations(bucket: u64) -> [TsAggregationType; 5] {
+    [
+        TsAggregationType::First(bucket),
+        TsAggregationType::Max(bucket),
+        TsAggregationType::Min(bucket),
+        TsAggregationType::Last(bucket),
+        TsAggregationType::Count(bucket),
+    ]
+}
+
+/// Builds the range queries for all candle aggregations. All queries share the
+/// same start alignment so that their buckets line up.
+pub(crate) fn candle_queries(from: u64, to: u64, bucket: u64) -> Vec<TsRangeQuery> {
+    candle_aggregations(bucket)
+        .iter()
+        .map(|agg| {
+            TsRangeQuery::default()
+                .from(from)
+                .to(to)
+                .align(TsAlign::Start)
+                .aggregation_type(*agg)
+        })
+        .collect()
+}
+
+/// Merges the aggregated first, max, min, last and count values into candles.
+/// Buckets that are missing in any of the aggregations are skipped.
+pub(crate) fn merge_candles(aggregations: [Vec<(u64, f64)>; 5]) -> Vec<TsCandle> {
+    let mut buckets: BTreeMap<u64, [Option<f64>; 5]> = BTreeMap::new();
+    for (idx, values) in aggregations.iter().enumerate() {
+        for (ts, value) in values {
+            buckets.entry(*ts).or_insert([None; 5])[idx] = Some(*value);
+        }
+    }
+
+    buckets
+        .into_iter()
+        .filter_map(|(ts, bucket)| match bucket {
+            [Some(open), Some(high), Some(low), Some(close), Some(count)] => Some(TsCandle {
+                ts,
+                open,
+                high,
+                low,
+                close,
+                count: count as u64,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Merges the per aggregation multi range results into candles grouped by series
+/// key. Series keep the order of the first aggregation result.
+pub(crate) fn merge_mcandles(aggregations: [TsMrange<u64, f64>; 5]) -> TsMcandles {
+    let mut keys: Vec<(String, Vec<(String, String)>)> = vec![];
+    let mut series: BTreeMap<String, [Vec<(u64, f64)>; 5]> = BTreeMap::new();
+
+    for (idx, mrange) in IntoIterator::into_iter(aggregations).enumerate() {
+        for entry in mrange.values {
+            let values = series.entry(entry.key.clone()).or_insert_with(|| {
+                keys.push((entry.key.clone(), entry.labels.clone()));
+                Default::default()
+            });
+            values[idx] = entry.values;
+        }
+    }
+
+    TsMcandles {
+        values: keys
+            .into_iter()
+            .map(|(key, labels)| {
+                let candles = series.remove(&key).map(merge_candles).unwrap_or_default();
+                TsMcandlesEntry {
+                    key,
+                    labels,
+                    candles,
+                }
+            })
+            .collect(),
+    }
+}
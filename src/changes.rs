@@ -0,0 +1,247 @@
+use crate::async_commands::AsyncTsCommands;
+use crate::types::TsFilterOptions;
+use futures_util::stream::{self, Stream, StreamExt};
+use redis::aio::PubSub;
+use redis::{Msg, RedisResult};
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+/// The kind of change reported by a keyspace notification.
+/// - Create, Alter: The series was created or its options changed.
+/// - Add, IncrBy, DecrBy: A sample was added or changed.
+/// - Del: Samples were deleted with TS.DEL.
+/// - CreateRule, DeleteRule: A compaction rule was added or removed.
+/// - Compaction: A compaction rule wrote into the series.
+/// - Removed: The key itself was deleted, expired, evicted or renamed.
+/// - Other: Any other event, eg. of non time series keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TsChangeKind {
+    Create,
+    Alter,
+    Add,
+    IncrBy,
+    DecrBy,
+    Del,
+    CreateRule,
+    DeleteRule,
+    Compaction,
+    Removed,
+    Other(String),
+}
+
+impl From<&str> for TsChangeKind {
+    fn from(event: &str) -> Self {
+        match event {
+            "ts.create" => TsChangeKind::Create,
+            "ts.alter" => TsChangeKind::Alter,
+            "ts.add" => TsChangeKind::Add,
+            "ts.incrby" => TsChangeKind::IncrBy,
+            "ts.decrby" => TsChangeKind::DecrBy,
+            "ts.del" => TsChangeKind::Del,
+            "ts.createrule" | "ts.createrule:src" | "ts.createrule:dest" => {
+                TsChangeKind::CreateRule
+            }
+            "ts.deleterule" | "ts.deleterule:src" | "ts.deleterule:dest" => {
+                TsChangeKind::DeleteRule
+            }
+            "ts.add:dest" => TsChangeKind::Compaction,
+            "del" | "expired" | "evicted" | "rename_from" => TsChangeKind::Removed,
+            other => TsChangeKind::Other(other.to_string()),
+        }
+    }
+}
+
+/// A change of a time series key. The sample is the latest sample of the key
+/// at the time the event was received, it is only fetched for sample changes
+/// and if enabled in the options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsChangeEvent {
+    pub key: String,
+    pub kind: TsChangeKind,
+    pub sample: Option<(u64, f64)>,
+}
+
+/// Options for ts_changes.
+///
+/// ```rust
+/// use redis_ts::{TsChangeOptions, TsFilterOptions};
+/// use std::time::Duration;
+///
+/// let options = TsChangeOptions::default()
+///     .filter(TsFilterOptions::default().equals("sensor", "temperature"))
+///     .refresh(Duration::from_secs(30))
+///     .fetch_sample(true);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct TsChangeOptions {
+    filter: Option<TsFilterOptions>,
+    refresh: Duration,
+    fetch_sample: bool,
+    db: Option<u16>,
+}
+
+impl Default for TsChangeOptions {
+    fn default() -> Self {
+        TsChangeOptions {
+            filter: None,
+            refresh: Duration::from_secs(60),
+            fetch_sample: false,
+            db: None,
+        }
+    }
+}
+
+impl TsChangeOptions {
+    /// Only yields changes of keys matching the filter. The matching keys are
+    /// resolved with TS.QUERYINDEX and refreshed periodically as well as on
+    /// every create or alter event.
+    pub fn filter(mut self, filter: TsFilterOptions) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// The interval in which matching keys are refreshed. Defaults to one
+    /// minute.
+    pub fn refresh(mut self, interval: Duration) -> Self {
+        self.refresh = interval;
+        self
+    }
+
+    /// Fetches the latest sample with TS.GET for add, incrby, decrby and
+    /// compaction events. Disabled by default.
+    pub fn fetch_sample(mut self, value: bool) -> Self {
+        self.fetch_sample = value;
+        self
+    }
+
+    /// Only listens to notifications of the given database. Defaults to all
+    /// databases.
+    pub fn db(mut self, db: u16) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    fn pattern(&self) -> String {
+        match self.db {
+            Some(db) => format!("__keyspace@{db}__:*"),
+            None => "__keyspace@*__:*".to_string(),
+        }
+    }
+}
+
+struct TsChangeState<C> {
+    messages: Pin<Box<dyn Stream<Item = Msg> + Send>>,
+    con: C,
+    options: TsChangeOptions,
+    keys: HashSet<String>,
+    refreshed: Option<Instant>,
+}
+
+impl<C: AsyncTsCommands> TsChangeState<C> {
+    async fn refresh(&mut self, force: bool) -> RedisResult<()> {
+        let filter = match &self.options.filter {
+            Some(filter) => filter.clone(),
+            None => return Ok(()),
+        };
+        let due = self
+            .refreshed
+            .is_none_or(|at| at.elapsed() >= self.options.refresh);
+        if force || due {
+            self.keys = self.con.ts_queryindex(filter).await?.into_iter().collect();
+            self.refreshed = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    async fn event(&mut self, msg: Msg) -> RedisResult<Option<TsChangeEvent>> {
+        let key = match msg.get_channel_name().split_once("__:") {
+            Some((_, key)) => key.to_string(),
+            None => return Ok(None),
+        };
+        let kind = TsChangeKind::from(msg.get_payload::<String>()?.as_str());
+
+        if self.options.filter.is_some() {
+            let changed = matches!(kind, TsChangeKind::Create | TsChangeKind::Alter);
+            self.refresh(changed).await?;
+            if !self.keys.contains(&key) {
+                return Ok(None);
+            }
+            if kind == TsChangeKind::Removed {
+                self.keys.remove(&key);
+            }
+        }
+
+        let sample = match kind {
+            TsChangeKind::Add
+            | TsChangeKind::IncrBy
+            | TsChangeKind::DecrBy
+            | TsChangeKind::Compaction
+                if self.options.fetch_sample =>
+            {
+                self.con.ts_get(key.as_str()).await?
+            }
+            _ => None,
+        };
+        Ok(Some(TsChangeEvent { key, kind, sample }))
+    }
+
+    async fn next(&mut self) -> Option<RedisResult<TsChangeEvent>> {
+        loop {
+            let msg = self.messages.next().await?;
+            match self.event(msg).await {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Subscribes to keyspace notifications and returns a stream of changes of time
+/// series keys. The connection is used to resolve the label filter and to fetch
+/// samples. The stream ends when the pubsub connection is closed.
+///
+/// Keyspace notifications have to be enabled on the server, eg. with
+/// `CONFIG SET notify-keyspace-events Kgd` for generic and module events.
+///
+/// ```rust,no_run
+/// # async fn run() -> redis::RedisResult<()> {
+/// use futures::StreamExt;
+/// use redis_ts::{ts_changes, TsChangeOptions, TsFilterOptions};
+///
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let pubsub = client.get_async_pubsub().await?;
+/// let con = client.get_multiplexed_async_connection().await?;
+///
+/// let options = TsChangeOptions::default()
+///     .filter(TsFilterOptions::default().equals("sensor", "temperature"))
+///     .fetch_sample(true);
+/// let mut changes = Box::pin(ts_changes(pubsub, con, options).await?);
+/// while let Some(change) = changes.next().await {
+///     let change = change?;
+///     println!("{} {:?} {:?}", change.key, change.kind, change.sample);
+/// }
+/// # Ok(()) }
+/// ```
+///
+pub async fn ts_changes<C: AsyncTsCommands>(
+    mut pubsub: PubSub,
+    con: C,
+    options: TsChangeOptions,
+) -> RedisResult<impl Stream<Item = RedisResult<TsChangeEvent>>> {
+    pubsub.psubscribe(options.pattern()).await?;
+    let mut state = TsChangeState {
+        messages: Box::pin(pubsub.into_on_message()),
+        con,
+        options,
+        keys: HashSet::new(),
+        refreshed: None,
+    };
+    state.refresh(true).await?;
+    Ok(stream::unfold(state, |mut state| async move {
+        let next = state.next().await?;
+        Some((next, state))
+    }))
+}
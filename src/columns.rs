@@ -0,0 +1,412 @@
+use crate::types::*;
+use redis::{from_redis_value, FromRedisValue, RedisError, RedisResult, Value};
+use std::iter::FromIterator;
+use std::slice::Iter;
+
+/// A TS.RANGE result decoded straight into a timestamp and a value column.
+/// Avoids the intermediate row vector of TsRange for large replies, values
+/// include `nan`, `inf` and `-inf` like TsValue. TsRange and TsMrangeEntry
+/// can be converted with `columns()`.
+///
+/// ```rust,no_run
+/// # fn run() -> redis::RedisResult<()> {
+/// use redis_ts::{TsColumns, TsCommands, TsRangeQuery};
+///
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let mut con = client.get_connection()?;
+///
+/// let columns: TsColumns = con.ts_range_columns("my_engine", TsRangeQuery::default())?;
+/// let last_minute = columns.range(60000, 120000);
+/// let mean = last_minute.mean();
+/// let max = last_minute.max();
+/// for (ts, value) in &columns {
+///     println!("{} {}", ts, value);
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TsColumns {
+    pub timestamps: Vec<u64>,
+    pub values: Vec<f64>,
+}
+
+impl TsColumns {
+    /// Creates empty columns with room for the given number of samples.
+    pub fn with_capacity(capacity: usize) -> Self {
+        TsColumns {
+            timestamps: Vec::with_capacity(capacity),
+            values: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// Appends a sample.
+    pub fn push(&mut self, ts: u64, value: f64) {
+        self.timestamps.push(ts);
+        self.values.push(value);
+    }
+
+    /// Returns a view on all samples.
+    pub fn as_slice(&self) -> TsColumnsSlice<'_> {
+        let len = self.timestamps.len().min(self.values.len());
+        TsColumnsSlice {
+            timestamps: &self.timestamps[..len],
+            values: &self.values[..len],
+        }
+    }
+
+    /// Iterates the samples as `(timestamp, value)` pairs without copying the
+    /// columns.
+    pub fn iter(&self) -> TsColumnsIter<'_> {
+        self.as_slice().iter()
+    }
+
+    /// Returns a view on the samples between from and to (inclusive), like
+    /// TS.RANGE. Timestamps have to be in ascending order.
+    pub fn range(&self, from: u64, to: u64) -> TsColumnsSlice<'_> {
+        self.as_slice().range(from, to)
+    }
+
+    /// Binary searches a timestamp, see TsColumnsSlice::search.
+    pub fn search(&self, ts: u64) -> Result<usize, usize> {
+        self.as_slice().search(ts)
+    }
+
+    /// Returns the value at the given timestamp.
+    pub fn get(&self, ts: u64) -> Option<f64> {
+        self.as_slice().get(ts)
+    }
+
+    /// Returns the smallest value ignoring NaN, None if there is none.
+    pub fn min(&self) -> Option<f64> {
+        self.as_slice().min()
+    }
+
+    /// Returns the largest value ignoring NaN, None if there is none.
+    pub fn max(&self) -> Option<f64> {
+        self.as_slice().max()
+    }
+
+    /// Returns the sum of all values ignoring NaN, eg. of empty buckets.
+    pub fn sum(&self) -> f64 {
+        self.as_slice().sum()
+    }
+
+    /// Returns the mean of all values ignoring NaN, None if there are no other
+    /// values.
+    pub fn mean(&self) -> Option<f64> {
+        self.as_slice().mean()
+    }
+
+    /// Converts the columns into a row based range.
+    pub fn into_range(self) -> TsRange<u64, f64> {
+        TsRange {
+            values: self.timestamps.into_iter().zip(self.values).collect(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a TsColumns {
+    type Item = (u64, f64);
+    type IntoIter = TsColumnsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<(u64, f64)> for TsColumns {
+    fn from_iter<I: IntoIterator<Item = (u64, f64)>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut columns = TsColumns::with_capacity(iter.size_hint().0);
+        for (ts, value) in iter {
+            columns.push(ts, value);
+        }
+        columns
+    }
+}
+
+/// Iterator over the `(timestamp, value)` pairs of columns.
+pub type TsColumnsIter<'a> =
+    std::iter::Zip<std::iter::Copied<Iter<'a, u64>>, std::iter::Copied<Iter<'a, f64>>>;
+
+/// A borrowed view on a part of TsColumns, eg. a time range. Provides the same
+/// lookups and summary statistics as the columns it was taken from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TsColumnsSlice<'a> {
+    pub timestamps: &'a [u64],
+    pub values: &'a [f64],
+}
+
+impl<'a> TsColumnsSlice<'a> {
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// Iterates the samples as `(timestamp, value)` pairs.
+    pub fn iter(&self) -> TsColumnsIter<'a> {
+        self.timestamps
+            .iter()
+            .copied()
+            .zip(self.values.iter().copied())
+    }
+
+    /// Returns a view on the samples between from and to (inclusive).
+    /// Timestamps have to be in ascending order.
+    pub fn range(&self, from: u64, to: u64) -> TsColumnsSlice<'a> {
+        let start = self.timestamps.partition_point(|ts| *ts < from);
+        let end = self.timestamps.partition_point(|ts| *ts <= to).max(start);
+        TsColumnsSlice {
+            timestamps: &self.timestamps[start..end],
+            values: &self.values[start..end],
+        }
+    }
+
+    /// Binary searches a timestamp. Returns the index of the sample if found,
+    /// otherwise the index where a sample with that timestamp would be
+    /// inserted. Timestamps have to be in ascending order.
+    pub fn search(&self, ts: u64) -> Result<usize, usize> {
+        self.timestamps.binary_search(&ts)
+    }
+
+    /// Returns the value at the given timestamp.
+    pub fn get(&self, ts: u64) -> Option<f64> {
+        self.search(ts).ok().map(|idx| self.values[idx])
+    }
+
+    /// Returns the smallest value ignoring NaN, None if there is none.
+    pub fn min(&self) -> Option<f64> {
+        let min = lanes(self.values, f64::NAN, f64::min)
+            .iter()
+            .fold(f64::NAN, |a, b| a.min(*b));
+        Some(min).filter(|m| !m.is_nan())
+    }
+
+    /// Returns the largest value ignoring NaN, None if there is none.
+    pub fn max(&self) -> Option<f64> {
+        let max = lanes(self.values, f64::NAN, f64::max)
+            .iter()
+            .fold(f64::NAN, |a, b| a.max(*b));
+        Some(max).filter(|m| !m.is_nan())
+    }
+
+    /// Returns the sum of all values ignoring NaN, eg. of empty buckets.
+    pub fn sum(&self) -> f64 {
+        lanes(self.values, 0.0, |a, b| if b.is_nan() { a } else { a + b })
+            .iter()
+            .sum()
+    }
+
+    /// Returns the mean of all values ignoring NaN, None if there are no other
+    /// values.
+    pub fn mean(&self) -> Option<f64> {
+        let count: f64 = lanes(self.values, 0.0, |a, b| a + (!b.is_nan()) as u8 as f64)
+            .iter()
+            .sum();
+        Some(self.sum() / count).filter(|_| count > 0.0)
+    }
+
+    /// Copies the samples into owned columns.
+    pub fn to_columns(&self) -> TsColumns {
+        TsColumns {
+            timestamps: self.timestamps.to_vec(),
+            values: self.values.to_vec(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for TsColumnsSlice<'a> {
+    type Item = (u64, f64);
+    type IntoIter = TsColumnsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The number of independent accumulators used for summary statistics, which
+/// allows the compiler to vectorize the loops.
+const LANES: usize = 8;
+
+/// Folds the values into LANES accumulators, which have to be reduced by the
+/// caller.
+fn lanes<F>(values: &[f64], init: f64, f: F) -> [f64; LANES]
+where
+    F: Fn(f64, f64) -> f64,
+{
+    let mut acc = [init; LANES];
+    let chunks = values.chunks_exact(LANES);
+    let remainder = chunks.remainder();
+    for chunk in chunks {
+        for (a, v) in acc.iter_mut().zip(chunk) {
+            *a = f(*a, *v);
+        }
+    }
+    for (a, v) in acc.iter_mut().zip(remainder) {
+        *a = f(*a, *v);
+    }
+    acc
+}
+
+impl<TS: FromRedisValue + Clone + Into<u64>, V: FromRedisValue + Clone + Into<f64>> TsRange<TS, V> {
+    /// Returns the samples as a timestamp and a value column.
+    pub fn columns(&self) -> TsColumns {
+        self.values
+            .iter()
+            .map(|(ts, value)| (ts.clone().into(), value.clone().into()))
+            .collect()
+    }
+}
+
+impl<TS: FromRedisValue + Into<u64>, V: FromRedisValue + Into<f64>> From<TsRange<TS, V>>
+    for TsColumns
+{
+    fn from(range: TsRange<TS, V>) -> Self {
+        range
+            .values
+            .into_iter()
+            .map(|(ts, value)| (ts.into(), value.into()))
+            .collect()
+    }
+}
+
+impl From<TsColumns> for TsRange<u64, f64> {
+    fn from(columns: TsColumns) -> Self {
+        columns.into_range()
+    }
+}
+
+impl<TS: FromRedisValue + Clone + Into<u64>, V: FromRedisValue + Clone + Into<f64>>
+    TsMrangeEntry<TS, V>
+{
+    /// Returns the samples as a timestamp and a value column.
+    pub fn columns(&self) -> TsColumns {
+        self.values
+            .iter()
+            .map(|(ts, value)| (ts.clone().into(), value.clone().into()))
+            .collect()
+    }
+}
+
+fn no_range_data() -> RedisError {
+    RedisError::from(std::io::Error::other("no_range_data"))
+}
+
+fn timestamp(v: &Value) -> RedisResult<u64> {
+    match *v {
+        Value::Int(ts) if ts >= 0 => Ok(ts as u64),
+        _ => from_redis_value(v),
+    }
+}
+
+fn value(v: &Value) -> RedisResult<f64> {
+    match *v {
+        Value::BulkString(ref bytes) => std::str::from_utf8(bytes)
+            .ok()
+            .and_then(|text| text.parse().ok())
+            .ok_or_else(no_range_data),
+        _ => Ok(TsValue::from_redis_value(v)?.0),
+    }
+}
+
+/// Decodes a list of `[timestamp, value]` samples into columns.
+fn columns(samples: &[Value]) -> RedisResult<TsColumns> {
+    let mut result = TsColumns::with_capacity(samples.len());
+    for sample in samples {
+        match *sample {
+            Value::Array(ref pair) if pair.len() == 2 => {
+                result.timestamps.push(timestamp(&pair[0])?);
+                result.values.push(value(&pair[1])?);
+            }
+            _ => return Err(no_range_data()),
+        }
+    }
+    Ok(result)
+}
+
+impl FromRedisValue for TsColumns {
+    fn from_redis_value(v: &Value) -> RedisResult<Self> {
+        match *v {
+            Value::Array(ref samples) => columns(samples),
+            _ => Err(no_range_data()),
+        }
+    }
+}
+
+/// A TS.MRANGE result with the samples of every series decoded into columns.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TsMcolumns {
+    pub values: Vec<TsMcolumnsEntry>,
+}
+
+impl FromRedisValue for TsMcolumns {
+    fn from_redis_value(v: &Value) -> RedisResult<Self> {
+        Ok(TsMcolumns {
+            values: entries(v, TsMcolumnsEntry::from_parts)?,
+        })
+    }
+
+    fn from_owned_redis_value(v: Value) -> RedisResult<Self> {
+        Ok(TsMcolumns {
+            values: owned_entries(v, TsMcolumnsEntry::from_owned_parts)?,
+        })
+    }
+}
+
+/// A single series of a TsMcolumns result.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TsMcolumnsEntry {
+    pub key: String,
+    pub labels: Vec<(String, String)>,
+    pub columns: TsColumns,
+}
+
+impl TsMcolumnsEntry {
+    fn from_parts(key: &Value, labels: &Value, samples: &Value) -> RedisResult<Self> {
+        Ok(TsMcolumnsEntry {
+            key: from_redis_value(key)?,
+            labels: self::labels(labels)?,
+            columns: match *samples {
+                Value::Array(ref samples) => columns(samples)?,
+                _ => TsColumns::default(),
+            },
+        })
+    }
+
+    fn from_owned_parts(key: Value, labels: Value, samples: Value) -> RedisResult<Self> {
+        Ok(TsMcolumnsEntry {
+            key: String::from_owned_redis_value(key)?,
+            labels: owned_labels(labels)?,
+            columns: match samples {
+                Value::Array(ref samples) => columns(samples)?,
+                _ => TsColumns::default(),
+            },
+        })
+    }
+}
+
+impl FromRedisValue for TsMcolumnsEntry {
+    fn from_redis_value(v: &Value) -> RedisResult<Self> {
+        match *v {
+            Value::Array(ref values) if values.len() >= 3 => {
+                TsMcolumnsEntry::from_parts(&values[0], &values[1], &values[values.len() - 1])
+            }
+            _ => Err(no_range_data()),
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use crate::candles::*;
+use crate::columns::{TsColumns, TsMcolumns};
 use crate::types::*;
-use redis::{cmd, ConnectionLike, FromRedisValue, RedisResult, ToRedisArgs};
+use redis::{cmd, pipe, ConnectionLike, FromRedisValue, RedisResult, ToRedisArgs};
 
 /// Provides a high level synchronous API to work with redis time series data types. Uses some abstractions
 /// for easier handling of time series related redis command arguments. All commands are directly
@@ -196,7 +198,7 @@ pub trait TsCommands: ConnectionLike + Sized {
     }
 
     #[doc(hidden)]
-    fn range<K: ToRedisArgs, TS: Copy + FromRedisValue, V: Copy + FromRedisValue>(
+    fn range<K: ToRedisArgs, TS: FromRedisValue, V: FromRedisValue>(
         &mut self,
         command: &str,
         key: K,
@@ -207,7 +209,7 @@ pub trait TsCommands: ConnectionLike + Sized {
     }
 
     /// Executes a redis time series range query.
-    fn ts_range<K: ToRedisArgs, TS: Copy + FromRedisValue, V: Copy + FromRedisValue>(
+    fn ts_range<K: ToRedisArgs, TS: FromRedisValue, V: FromRedisValue>(
         &mut self,
         key: K,
         query: TsRangeQuery,
@@ -216,7 +218,7 @@ pub trait TsCommands: ConnectionLike + Sized {
     }
 
     /// Executes a redis time series revrange query.
-    fn ts_revrange<K: ToRedisArgs, TS: Copy + FromRedisValue, V: Copy + FromRedisValue>(
+    fn ts_revrange<K: ToRedisArgs, TS: FromRedisValue, V: FromRedisValue>(
         &mut self,
         key: K,
         query: TsRangeQuery,
@@ -225,7 +227,7 @@ pub trait TsCommands: ConnectionLike + Sized {
     }
 
     #[doc(hidden)]
-    fn mrange<TS: Default + FromRedisValue + Copy, V: Default + FromRedisValue + Copy>(
+    fn mrange<TS: Default + FromRedisValue, V: Default + FromRedisValue>(
         &mut self,
         command: &str,
         query: TsRangeQuery,
@@ -237,7 +239,7 @@ pub trait TsCommands: ConnectionLike + Sized {
     }
 
     /// Executes multiple redis time series range queries.
-    fn ts_mrange<TS: Default + FromRedisValue + Copy, V: Default + FromRedisValue + Copy>(
+    fn ts_mrange<TS: Default + FromRedisValue, V: Default + FromRedisValue>(
         &mut self,
         query: TsRangeQuery,
         filter_options: TsFilterOptions,
@@ -246,7 +248,7 @@ pub trait TsCommands: ConnectionLike + Sized {
     }
 
     /// Executes multiple redis time series revrange queries.
-    fn ts_mrevrange<TS: Default + FromRedisValue + Copy, V: Default + FromRedisValue + Copy>(
+    fn ts_mrevrange<TS: Default + FromRedisValue, V: Default + FromRedisValue>(
         &mut self,
         query: TsRangeQuery,
         filter_options: TsFilterOptions,
@@ -254,6 +256,82 @@ pub trait TsCommands: ConnectionLike + Sized {
         self.mrange("TS.MREVRANGE", query, filter_options)
     }
 
+    /// Returns open/high/low/close candles between from and to where each candle
+    /// covers a bucket of the given size in millis. The underlying aggregations
+    /// share the same alignment and are sent in a single MULTI/EXEC transaction
+    /// so all of them see the same samples.
+    fn ts_candles<K: ToRedisArgs>(
+        &mut self,
+        key: K,
+        from: u64,
+        to: u64,
+        bucket: u64,
+    ) -> RedisResult<Vec<TsCandle>> {
+        let mut p = pipe();
+        p.atomic();
+        for query in candle_queries(from, to, bucket) {
+            p.cmd("TS.RANGE").arg(&key).arg(query);
+        }
+        let (first, max, min, last, count): CandleRanges = p.query(self)?;
+        Ok(merge_candles([
+            first.values,
+            max.values,
+            min.values,
+            last.values,
+            count.values,
+        ]))
+    }
+
+    /// Returns open/high/low/close candles for all time series matching the given
+    /// filter, keyed by series. Like ts_candles the queries run in one transaction.
+    fn ts_mcandles(
+        &mut self,
+        from: u64,
+        to: u64,
+        bucket: u64,
+        filter_options: TsFilterOptions,
+    ) -> RedisResult<TsMcandles> {
+        let mut p = pipe();
+        p.atomic();
+        for query in candle_queries(from, to, bucket) {
+            p.cmd("TS.MRANGE").arg(query).arg(filter_options.clone());
+        }
+        let (first, max, min, last, count): CandleMranges = p.query(self)?;
+        Ok(merge_mcandles([first, max, min, last, count]))
+    }
+
+    /// Queries a counter and returns its per second rate over the window (in
+    /// millis) ending at each sample. See TsRange::rate for details.
+    fn ts_rate<K: ToRedisArgs>(
+        &mut self,
+        key: K,
+        query: TsRangeQuery,
+        window: u64,
+    ) -> RedisResult<TsRange<u64, f64>> {
+        let range: TsRange<u64, f64> = self.ts_range(key, query)?;
+        Ok(range.rate(window))
+    }
+
+    /// Executes a redis time series range query and decodes the samples
+    /// straight into a timestamp and a value column.
+    fn ts_range_columns<K: ToRedisArgs>(
+        &mut self,
+        key: K,
+        query: TsRangeQuery,
+    ) -> RedisResult<TsColumns> {
+        cmd("TS.RANGE").arg(key).arg(query).query(self)
+    }
+
+    /// Executes multiple redis time series range queries and decodes the
+    /// samples of every series straight into columns.
+    fn ts_mrange_columns(
+        &mut self,
+        query: TsRangeQuery,
+        filter_options: TsFilterOptions,
+    ) -> RedisResult<TsMcolumns> {
+        cmd("TS.MRANGE").arg(query).arg(filter_options).query(self)
+    }
+
     /// Returns the latest (current) value in a redis time series.
     fn ts_get<K: ToRedisArgs, TS: FromRedisValue, V: FromRedisValue>(
         &mut self,
@@ -0,0 +1,269 @@
+use crate::async_commands::AsyncTsCommands;
+use crate::types::*;
+use futures_timer::Delay;
+use futures_util::stream::{self, Stream, StreamExt};
+use redis::{pipe, RedisResult};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::time::Duration;
+
+/// Options for following time series with ts_follow and ts_mfollow.
+///
+/// ```rust
+/// use redis_ts::TsFollowOptions;
+/// use std::time::Duration;
+///
+/// let options = TsFollowOptions::default()
+///     .interval(Duration::from_millis(500))
+///     .lookback(10000);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct TsFollowOptions {
+    interval: Duration,
+    lookback: u64,
+    from: Option<u64>,
+}
+
+impl Default for TsFollowOptions {
+    fn default() -> Self {
+        TsFollowOptions {
+            interval: Duration::from_secs(1),
+            lookback: 0,
+            from: None,
+        }
+    }
+}
+
+impl TsFollowOptions {
+    /// The time to wait between two polls. Defaults to one second.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Samples that are added up to this many millis before the latest seen
+    /// sample are still yielded. Samples added later than that are skipped.
+    /// Defaults to 0.
+    pub fn lookback(mut self, millis: u64) -> Self {
+        self.lookback = millis;
+        self
+    }
+
+    /// Yields all samples starting at the given timestamp. By default only
+    /// samples added after the latest sample at the time of the first poll
+    /// are yielded.
+    pub fn from(mut self, ts: u64) -> Self {
+        self.from = Some(ts);
+        self
+    }
+}
+
+/// A new sample of a followed time series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsFollowSample {
+    pub key: String,
+    pub labels: Vec<(String, String)>,
+    pub ts: u64,
+    pub value: f64,
+}
+
+enum TsFollowTarget {
+    Key(String),
+    Filter(TsFilterOptions),
+}
+
+struct TsFollowState<C> {
+    con: C,
+    target: TsFollowTarget,
+    options: TsFollowOptions,
+    started: bool,
+    polled: bool,
+    baseline: HashMap<String, u64>,
+    seen: HashMap<String, BTreeSet<u64>>,
+    pending: VecDeque<TsFollowSample>,
+}
+
+impl<C: AsyncTsCommands> TsFollowState<C> {
+    fn new(con: C, target: TsFollowTarget, options: TsFollowOptions) -> Self {
+        TsFollowState {
+            con,
+            target,
+            options,
+            started: false,
+            polled: false,
+            baseline: HashMap::new(),
+            seen: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Returns the timestamp of the latest sample of every followed series.
+    async fn latest(&mut self) -> RedisResult<HashMap<String, u64>> {
+        match &self.target {
+            TsFollowTarget::Key(key) => {
+                let value: Option<(u64, f64)> = self.con.ts_get(key.as_str()).await?;
+                Ok(value.map(|(ts, _)| (key.clone(), ts)).into_iter().collect())
+            }
+            TsFollowTarget::Filter(filter) => {
+                let mget: TsMget<u64, f64> = self.con.ts_mget(filter.clone()).await?;
+                Ok(mget
+                    .values
+                    .into_iter()
+                    .filter_map(|e| e.value.map(|(ts, _)| (e.key, ts)))
+                    .collect())
+            }
+        }
+    }
+
+    /// Returns the start of the lookback window of a series, the series is
+    /// read from its watermark (its latest seen sample) minus the lookback.
+    fn window(&self, key: &str) -> Option<u64> {
+        self.seen
+            .get(key)
+            .and_then(|seen| seen.iter().next_back())
+            .or_else(|| self.baseline.get(key))
+            .map(|watermark| watermark.saturating_sub(self.options.lookback))
+    }
+
+    /// Returns the keys and labels of all followed series.
+    async fn series(&mut self) -> RedisResult<Vec<(String, Vec<(String, String)>)>> {
+        match &self.target {
+            TsFollowTarget::Key(key) => Ok(vec![(key.clone(), vec![])]),
+            TsFollowTarget::Filter(filter) => {
+                let mget: TsMget<u64, f64> = self.con.ts_mget(filter.clone()).await?;
+                Ok(mget
+                    .values
+                    .into_iter()
+                    .filter(|e| e.value.is_some())
+                    .map(|e| (e.key, e.labels))
+                    .collect())
+            }
+        }
+    }
+
+    /// Reads every series from the start of its own window, so series lagging
+    /// behind do not make the others read their history again, and queues the
+    /// samples that have not been seen yet, ordered by timestamp.
+    async fn poll(&mut self) -> RedisResult<()> {
+        if !self.started {
+            if self.options.from.is_none() {
+                self.baseline = self.latest().await?;
+            }
+            self.started = true;
+        }
+
+        let start = self.options.from.unwrap_or(0);
+        let series = self.series().await?;
+        if series.is_empty() {
+            return Ok(());
+        }
+        let mut ranges = pipe();
+        for (key, _) in series.iter() {
+            let from = self.window(key).unwrap_or(0).max(start);
+            ranges
+                .cmd("TS.RANGE")
+                .arg(key)
+                .arg(TsRangeQuery::default().from(from));
+        }
+        let ranges: Vec<TsRange<u64, f64>> = ranges.query_async(&mut self.con).await?;
+
+        let mut new = vec![];
+        for ((key, labels), range) in series.into_iter().zip(ranges) {
+            let window = self.window(&key).unwrap_or(0).max(start);
+            let baseline = self.baseline.get(&key).copied();
+            let seen = self.seen.entry(key.clone()).or_default();
+            for (ts, value) in range.values {
+                if ts < window || !seen.insert(ts) {
+                    continue;
+                }
+                if baseline.is_some_and(|b| ts <= b) {
+                    continue;
+                }
+                new.push(TsFollowSample {
+                    key: key.clone(),
+                    labels: labels.clone(),
+                    ts,
+                    value,
+                });
+            }
+            if let Some(&watermark) = seen.iter().next_back() {
+                *seen = seen.split_off(&watermark.saturating_sub(self.options.lookback));
+            }
+        }
+        self.baseline.clear();
+        new.sort_by_key(|s| s.ts);
+        self.pending.extend(new);
+        Ok(())
+    }
+
+    async fn next(&mut self) -> RedisResult<TsFollowSample> {
+        loop {
+            if let Some(sample) = self.pending.pop_front() {
+                return Ok(sample);
+            }
+            if self.polled {
+                Delay::new(self.options.interval).await;
+            }
+            self.polled = true;
+            self.poll().await?;
+        }
+    }
+}
+
+fn follow<C: AsyncTsCommands>(
+    con: C,
+    target: TsFollowTarget,
+    options: TsFollowOptions,
+) -> impl Stream<Item = RedisResult<TsFollowSample>> {
+    stream::unfold(
+        TsFollowState::new(con, target, options),
+        |mut state| async move {
+            let next = state.next().await;
+            Some((next, state))
+        },
+    )
+}
+
+/// Returns an endless stream of the new samples of a time series. The series is
+/// polled with TS.RANGE from the latest seen timestamp and every sample is
+/// yielded exactly once. Errors are yielded as well, polling continues with
+/// the next interval.
+///
+/// ```rust,no_run
+/// # async fn run() -> redis::RedisResult<()> {
+/// use futures::StreamExt;
+/// use redis_ts::{ts_follow, TsFollowOptions};
+///
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let con = client.get_multiplexed_async_connection().await?;
+///
+/// let mut samples = Box::pin(ts_follow(con, "my_engine", TsFollowOptions::default()));
+/// while let Some(sample) = samples.next().await {
+///     let (ts, value) = sample?;
+/// }
+/// # Ok(()) }
+/// ```
+///
+pub fn ts_follow<C: AsyncTsCommands>(
+    con: C,
+    key: &str,
+    options: TsFollowOptions,
+) -> impl Stream<Item = RedisResult<(u64, f64)>> {
+    follow(con, TsFollowTarget::Key(key.to_string()), options)
+        .map(|sample| sample.map(|s| (s.ts, s.value)))
+}
+
+/// Returns an endless stream of the new samples of all time series matching
+/// the filter. Every poll finds the series with TS.MGET and reads each of them
+/// with TS.RANGE from its own watermark, all in a single pipeline. So the
+/// lookback applies to the latest sample of every series and series lagging
+/// behind the others neither lose samples nor slow down the others. Samples of
+/// series that start matching later are included. Labels are set if the filter
+/// requests them.
+pub fn ts_mfollow<C: AsyncTsCommands>(
+    con: C,
+    filter_options: TsFilterOptions,
+    options: TsFollowOptions,
+) -> impl Stream<Item = RedisResult<TsFollowSample>> {
+    follow(con, TsFollowTarget::Filter(filter_options), options)
+}
@@ -0,0 +1,181 @@
+use crate::types::*;
+use std::collections::BTreeSet;
+
+/// Controls how the timestamps of multiple series are joined into a frame.
+/// - Outer: All timestamps of any series, missing values are None.
+/// - Inner: Only timestamps that are present in all series.
+/// - AsOf(tolerance): The timestamps of the first series. All other series use
+///   their nearest previous value if it is at most tolerance millis older.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsJoin {
+    Outer,
+    Inner,
+    AsOf(u64),
+}
+
+/// A single named column of a TsFrame with the labels of its series.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TsFrameColumn {
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+    pub values: Vec<Option<f64>>,
+}
+
+/// A columnar table of multiple time series joined on timestamp. Every column
+/// holds one value per entry in timestamps.
+///
+/// ```rust
+/// use redis_ts::{TsFrame, TsJoin, TsRange};
+///
+/// let a = TsRange { values: vec![(10, 1.0), (20, 2.0)] };
+/// let b = TsRange { values: vec![(20, 5.0), (30, 6.0)] };
+///
+/// let frame = TsFrame::from_ranges(vec![("a", &a), ("b", &b)], TsJoin::Outer);
+/// assert_eq!(frame.timestamps, vec![10, 20, 30]);
+/// assert_eq!(frame.column("b").unwrap().values, vec![None, Some(5.0), Some(6.0)]);
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TsFrame {
+    pub timestamps: Vec<u64>,
+    pub columns: Vec<TsFrameColumn>,
+}
+
+struct Source<'a> {
+    name: String,
+    labels: Vec<(String, String)>,
+    values: &'a [(u64, f64)],
+}
+
+impl TsFrame {
+    /// Builds a frame from a TS.MRANGE result. Columns are named after the series
+    /// keys and carry their labels.
+    pub fn from_mrange(mrange: &TsMrange<u64, f64>, join: TsJoin) -> TsFrame {
+        let sources = mrange
+            .values
+            .iter()
+            .map(|e| Source {
+                name: e.key.clone(),
+                labels: e.labels.clone(),
+                values: &e.values,
+            })
+            .collect();
+        TsFrame::join(sources, join)
+    }
+
+    /// Builds a frame from multiple named TS.RANGE results. Labels can be attached
+    /// afterwards with set_labels.
+    pub fn from_ranges(ranges: Vec<(&str, &TsRange<u64, f64>)>, join: TsJoin) -> TsFrame {
+        let sources = ranges
+            .into_iter()
+            .map(|(name, range)| Source {
+                name: name.to_string(),
+                labels: vec![],
+                values: &range.values,
+            })
+            .collect();
+        TsFrame::join(sources, join)
+    }
+
+    /// Replaces the labels of the column with the given name.
+    pub fn set_labels(&mut self, column: &str, labels: Vec<(&str, &str)>) {
+        if let Some(c) = self.columns.iter_mut().find(|c| c.name == column) {
+            c.labels = labels
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+        }
+    }
+
+    /// Returns the column with the given name.
+    pub fn column(&self, name: &str) -> Option<&TsFrameColumn> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    /// Returns the timestamp and all column values of the row at the given index.
+    pub fn row(&self, idx: usize) -> Option<(u64, Vec<Option<f64>>)> {
+        let ts = *self.timestamps.get(idx)?;
+        Some((ts, self.columns.iter().map(|c| c.values[idx]).collect()))
+    }
+
+    /// Returns the number of rows in this frame.
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Returns true if this frame has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    fn join(sources: Vec<Source>, join: TsJoin) -> TsFrame {
+        let sorted: Vec<Vec<(u64, f64)>> = sources
+            .iter()
+            .map(|s| {
+                let mut values = s.values.to_vec();
+                values.sort_by_key(|v| v.0);
+                values
+            })
+            .collect();
+
+        let timestamps: Vec<u64> = match join {
+            TsJoin::Outer => sorted
+                .iter()
+                .flat_map(|values| values.iter().map(|v| v.0))
+                .collect::<BTreeSet<u64>>()
+                .into_iter()
+                .collect(),
+            TsJoin::Inner => match sorted.split_first() {
+                Some((first, rest)) => first
+                    .iter()
+                    .map(|v| v.0)
+                    .filter(|ts| rest.iter().all(|values| exact(values, *ts).is_some()))
+                    .collect(),
+                None => vec![],
+            },
+            TsJoin::AsOf(_) => match sorted.first() {
+                Some(first) => first.iter().map(|v| v.0).collect(),
+                None => vec![],
+            },
+        };
+
+        let columns = sources
+            .into_iter()
+            .zip(sorted.iter())
+            .enumerate()
+            .map(|(idx, (source, values))| TsFrameColumn {
+                name: source.name,
+                labels: source.labels,
+                values: timestamps
+                    .iter()
+                    .map(|ts| match join {
+                        TsJoin::AsOf(tolerance) if idx > 0 => as_of(values, *ts, tolerance),
+                        _ => exact(values, *ts),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        TsFrame {
+            timestamps,
+            columns,
+        }
+    }
+}
+
+fn exact(values: &[(u64, f64)], ts: u64) -> Option<f64> {
+    values
+        .binary_search_by_key(&ts, |v| v.0)
+        .ok()
+        .map(|idx| values[idx].1)
+}
+
+fn as_of(values: &[(u64, f64)], ts: u64, tolerance: u64) -> Option<f64> {
+    let idx = values.partition_point(|v| v.0 <= ts).checked_sub(1)?;
+    let (prev_ts, value) = values[idx];
+    if ts - prev_ts <= tolerance {
+        Some(value)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,277 @@
+use crate::types::*;
+use redis::{RedisError, RedisResult};
+
+/// A continuous interval of missing samples in a time series. Both start and end
+/// are the timestamps of the first and last missing sample at the resolution
+/// the gap was detected with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TsGap {
+    pub start: u64,
+    pub end: u64,
+    pub missing: u64,
+}
+
+/// Lists all gaps found in a time series result at a given resolution. A sample
+/// is considered missing if the distance to its predecessor exceeds the resolution
+/// or if the server reported it as NaN (eg. for EMPTY aggregation buckets).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TsGapReport {
+    pub resolution: u64,
+    pub gaps: Vec<TsGap>,
+}
+
+impl TsGapReport {
+    /// Returns true if no samples are missing.
+    pub fn is_empty(&self) -> bool {
+        self.gaps.is_empty()
+    }
+
+    /// Returns the total number of missing samples over all gaps.
+    pub fn missing_samples(&self) -> u64 {
+        self.gaps.iter().map(|g| g.missing).sum()
+    }
+}
+
+/// Strategies to fill missing samples of a time series result.
+/// - Previous: Repeats the last known value.
+/// - Linear: Interpolates between the surrounding known values.
+/// - Constant(value): Uses the given value.
+/// - Drop: Removes NaN samples and does not insert any values.
+///
+/// Missing samples without a required neighbour (eg. leading gaps with Previous)
+/// are left out of the result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TsFillStrategy {
+    Previous,
+    Linear,
+    Constant(f64),
+    Drop,
+}
+
+/// A run of count missing timestamps starting at start and spaced by the
+/// resolution. Runs are expanded only when filling.
+#[derive(Clone, Copy)]
+struct Run {
+    start: u64,
+    count: u64,
+}
+
+enum Segment {
+    Present(u64, f64),
+    Missing(Vec<Run>),
+}
+
+fn invalid_resolution() -> RedisError {
+    RedisError::from(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "gap resolution must be greater than 0",
+    ))
+}
+
+/// Appends a run, extending the last one if it continues at the resolution.
+fn push_run(runs: &mut Vec<Run>, run: Run, resolution: u64) {
+    if let Some(last) = runs.last_mut() {
+        if last.start + last.count * resolution == run.start {
+            last.count += run.count;
+            return;
+        }
+    }
+    runs.push(run);
+}
+
+/// Splits ascending values into present samples and runs of missing timestamps.
+/// The resolution must not be 0.
+fn segments(values: &[(u64, f64)], resolution: u64) -> Vec<Segment> {
+    let mut result = vec![];
+    let mut missing: Vec<Run> = vec![];
+    let mut last_ts: Option<u64> = None;
+
+    for &(ts, value) in values {
+        if let Some(prev) = last_ts {
+            let count = ts.saturating_sub(prev).saturating_sub(1) / resolution;
+            if count > 0 {
+                let start = prev + resolution;
+                push_run(&mut missing, Run { start, count }, resolution);
+            }
+        }
+        last_ts = Some(ts);
+
+        if value.is_nan() {
+            push_run(
+                &mut missing,
+                Run {
+                    start: ts,
+                    count: 1,
+                },
+                resolution,
+            );
+            continue;
+        }
+
+        if !missing.is_empty() {
+            result.push(Segment::Missing(std::mem::take(&mut missing)));
+        }
+        result.push(Segment::Present(ts, value));
+    }
+
+    if !missing.is_empty() {
+        result.push(Segment::Missing(missing));
+    }
+    result
+}
+
+/// Runs f on the values in ascending order, restoring a descending order (eg. from
+/// a revrange query) afterwards.
+fn ascending<F: FnOnce(&[(u64, f64)]) -> Vec<(u64, f64)>>(
+    values: &[(u64, f64)],
+    f: F,
+) -> Vec<(u64, f64)> {
+    let descending = values.len() > 1 && values[0].0 > values[values.len() - 1].0;
+    if descending {
+        let mut reversed = values.to_vec();
+        reversed.reverse();
+        let mut result = f(&reversed);
+        result.reverse();
+        result
+    } else {
+        f(values)
+    }
+}
+
+fn detect_gaps(values: &[(u64, f64)], resolution: u64) -> RedisResult<TsGapReport> {
+    if resolution == 0 {
+        return Err(invalid_resolution());
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by_key(|v| v.0);
+    let gaps = segments(&sorted, resolution)
+        .into_iter()
+        .filter_map(|s| match s {
+            Segment::Missing(runs) => {
+                let last = runs[runs.len() - 1];
+                Some(TsGap {
+                    start: runs[0].start,
+                    end: last.start + (last.count - 1) * resolution,
+                    missing: runs.iter().map(|r| r.count).sum(),
+                })
+            }
+            Segment::Present(..) => None,
+        })
+        .collect();
+    Ok(TsGapReport { resolution, gaps })
+}
+
+fn fill_values(
+    values: &[(u64, f64)],
+    resolution: u64,
+    strategy: TsFillStrategy,
+) -> RedisResult<Vec<(u64, f64)>> {
+    if resolution == 0 {
+        return Err(invalid_resolution());
+    }
+    Ok(ascending(values, |values| {
+        let segments = segments(values, resolution);
+        let mut result = Vec::with_capacity(values.len());
+        for (idx, segment) in segments.iter().enumerate() {
+            let runs = match segment {
+                Segment::Present(ts, value) => {
+                    result.push((*ts, *value));
+                    continue;
+                }
+                Segment::Missing(runs) => runs,
+            };
+            let prev = match idx.checked_sub(1).map(|i| &segments[i]) {
+                Some(Segment::Present(ts, value)) => Some((*ts, *value)),
+                _ => None,
+            };
+            let next = match segments.get(idx + 1) {
+                Some(Segment::Present(ts, value)) => Some((*ts, *value)),
+                _ => None,
+            };
+            let missing = runs
+                .iter()
+                .flat_map(|r| (0..r.count).map(move |i| r.start + i * resolution));
+            for ts in missing {
+                let value = match (strategy, prev, next) {
+                    (TsFillStrategy::Previous, Some((_, v)), _) => v,
+                    (TsFillStrategy::Linear, Some((t0, v0)), Some((t1, v1))) => {
+                        v0 + (v1 - v0) * (ts - t0) as f64 / (t1 - t0) as f64
+                    }
+                    (TsFillStrategy::Constant(v), _, _) => v,
+                    _ => continue,
+                };
+                result.push((ts, value));
+            }
+        }
+        result
+    }))
+}
+
+impl TsRange<u64, f64> {
+    /// Detects all gaps in this range at the given resolution in millis. Fails if
+    /// the resolution is 0.
+    pub fn gaps(&self, resolution: u64) -> RedisResult<TsGapReport> {
+        detect_gaps(&self.values, resolution)
+    }
+
+    /// Returns a copy of this range with all gaps at the given resolution in millis
+    /// filled by the given strategy. Fails if the resolution is 0.
+    pub fn fill(
+        &self,
+        resolution: u64,
+        strategy: TsFillStrategy,
+    ) -> RedisResult<TsRange<u64, f64>> {
+        Ok(TsRange {
+            values: fill_values(&self.values, resolution, strategy)?,
+        })
+    }
+}
+
+impl TsMrangeEntry<u64, f64> {
+    /// Detects all gaps in this series at the given resolution in millis. Fails if
+    /// the resolution is 0.
+    pub fn gaps(&self, resolution: u64) -> RedisResult<TsGapReport> {
+        detect_gaps(&self.values, resolution)
+    }
+
+    /// Returns a copy of this series with all gaps at the given resolution in millis
+    /// filled by the given strategy. Fails if the resolution is 0.
+    pub fn fill(
+        &self,
+        resolution: u64,
+        strategy: TsFillStrategy,
+    ) -> RedisResult<TsMrangeEntry<u64, f64>> {
+        Ok(TsMrangeEntry {
+            key: self.key.clone(),
+            labels: self.labels.clone(),
+            values: fill_values(&self.values, resolution, strategy)?,
+        })
+    }
+}
+
+impl TsMrange<u64, f64> {
+    /// Detects the gaps of all series at the given resolution in millis. Returns the
+    /// series key together with its report.
+    pub fn gaps(&self, resolution: u64) -> RedisResult<Vec<(String, TsGapReport)>> {
+        self.values
+            .iter()
+            .map(|e| Ok((e.key.clone(), e.gaps(resolution)?)))
+            .collect()
+    }
+
+    /// Returns a copy of this result with the gaps of all series filled by the
+    /// given strategy.
+    pub fn fill(
+        &self,
+        resolution: u64,
+        strategy: TsFillStrategy,
+    ) -> RedisResult<TsMrange<u64, f64>> {
+        Ok(TsMrange {
+            values: self
+                .values
+                .iter()
+                .map(|e| e.fill(resolution, strategy))
+                .collect::<RedisResult<_>>()?,
+        })
+    }
+}
@@ -0,0 +1,283 @@
+use crate::commands::TsCommands;
+use crate::types::*;
+use redis::RedisResult;
+use std::collections::BTreeMap;
+
+/// The label holding the upper bound of a histogram bucket series.
+const BUCKET_LABEL: &str = "le";
+
+/// The label shared by all bucket series of a histogram.
+const HISTOGRAM_LABEL: &str = "histogram";
+
+/// A histogram stored as one time series per bucket. Every bucket series counts
+/// the observations of a batch that are less than or equal to its upper bound
+/// (`le` label), a `+Inf` bucket counting all observations is always added.
+/// Quantiles are estimated from the bucket counts by linear interpolation
+/// within the bucket that contains the quantile, like the PromQL
+/// histogram_quantile function.
+///
+/// ```rust,no_run
+/// # fn run() -> redis::RedisResult<()> {
+/// use redis_ts::{TsAggregationType, TsHistogram, TsOptions, TsRangeQuery};
+///
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let mut con = client.get_connection()?;
+///
+/// let latency = TsHistogram::new("latency:api", vec![0.01, 0.05, 0.1, 0.5, 1.0])
+///     .metric("latency")
+///     .options(TsOptions::default().retention_time(86400000).label("service", "api"));
+/// latency.create(&mut con)?;
+/// latency.record(&mut con, 1000, &[0.02, 0.07, 0.3])?;
+///
+/// let p99 = latency.quantile(
+///     &mut con,
+///     TsRangeQuery::default().aggregation_type(TsAggregationType::Sum(60000)),
+///     0.99
+/// )?;
+/// # Ok(()) }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct TsHistogram {
+    pub name: String,
+    pub metric: String,
+    pub bounds: Vec<f64>,
+    options: TsOptions,
+}
+
+/// Formats a bucket bound as `le` label value.
+fn bound_label(bound: f64) -> String {
+    if bound == f64::INFINITY {
+        "+Inf".to_string()
+    } else {
+        bound.to_string()
+    }
+}
+
+impl TsHistogram {
+    /// Creates a histogram with the given bucket upper bounds. Bucket series are
+    /// stored under `<name>:le=<bound>` and labeled with `histogram=<name>`.
+    pub fn new(name: &str, bounds: Vec<f64>) -> Self {
+        let mut bounds: Vec<f64> = bounds
+            .into_iter()
+            .filter(|b| !b.is_nan() && *b != f64::INFINITY)
+            .collect();
+        bounds.sort_by(|a, b| a.total_cmp(b));
+        bounds.dedup();
+        bounds.push(f64::INFINITY);
+        TsHistogram {
+            name: name.to_string(),
+            metric: name.to_string(),
+            bounds,
+            options: TsOptions::default().duplicate_policy(TsDuplicatePolicy::Sum),
+        }
+    }
+
+    /// Overrides the value of the `histogram` label. Histograms with the same
+    /// metric, eg. of different hosts, are merged when quantiles are estimated.
+    pub fn metric(mut self, metric: &str) -> Self {
+        self.metric = metric.to_string();
+        self
+    }
+
+    /// The options all bucket series are created with. The histogram and bucket
+    /// labels are added to the labels. Unless the options set a duplicate
+    /// policy the SUM policy is kept, so batches recorded at the same timestamp
+    /// add up.
+    pub fn options(mut self, options: TsOptions) -> Self {
+        self.options = if options.has_duplicate_policy() {
+            options
+        } else {
+            options.duplicate_policy(TsDuplicatePolicy::Sum)
+        };
+        self
+    }
+
+    /// Returns the key of every bucket series with its `le` label value.
+    pub fn keys(&self) -> Vec<(String, String)> {
+        self.bounds
+            .iter()
+            .map(|b| {
+                let le = bound_label(*b);
+                (format!("{}:{}={}", self.name, BUCKET_LABEL, le), le)
+            })
+            .collect()
+    }
+
+    /// Returns the keys and options to create all bucket series with.
+    pub fn series(&self) -> Vec<(String, TsOptions)> {
+        self.keys()
+            .into_iter()
+            .map(|(key, le)| {
+                let options = self
+                    .options
+                    .clone()
+                    .label(HISTOGRAM_LABEL, &self.metric)
+                    .label(BUCKET_LABEL, &le);
+                (key, options)
+            })
+            .collect()
+    }
+
+    /// Returns the samples of a batch of observations to be added with a
+    /// single TS.MADD. NaN observations are ignored.
+    pub fn samples(&self, ts: u64, observations: &[f64]) -> Vec<(String, u64, f64)> {
+        self.keys()
+            .into_iter()
+            .zip(self.bounds.iter())
+            .map(|((key, _), bound)| {
+                let count = observations.iter().filter(|v| *v <= bound).count();
+                (key, ts, count as f64)
+            })
+            .collect()
+    }
+
+    /// Returns the TS.MRANGE query that sums the bucket series of all
+    /// histograms with this metric grouped by their upper bound.
+    pub fn query(&self, query: TsRangeQuery) -> (TsRangeQuery, TsFilterOptions) {
+        let filter = TsFilterOptions::default()
+            .equals(HISTOGRAM_LABEL, &self.metric)
+            .with_labels(true)
+            .group_by(BUCKET_LABEL, TsReducer::Sum);
+        (query, filter)
+    }
+
+    /// Creates all bucket series.
+    pub fn create<C: TsCommands>(&self, con: &mut C) -> RedisResult<()> {
+        for (key, options) in self.series() {
+            let _: () = con.ts_create(key, options)?;
+        }
+        Ok(())
+    }
+
+    /// Records a batch of observations at the given timestamp.
+    pub fn record<C: TsCommands>(
+        &self,
+        con: &mut C,
+        ts: u64,
+        observations: &[f64],
+    ) -> RedisResult<()> {
+        con.ts_madd(&self.samples(ts, observations))
+    }
+
+    /// Queries the bucket counts and estimates the given quantile (0 to 1) at
+    /// every timestamp of the result.
+    pub fn quantile<C: TsCommands>(
+        &self,
+        con: &mut C,
+        query: TsRangeQuery,
+        quantile: f64,
+    ) -> RedisResult<TsRange<u64, f64>> {
+        let (query, filter) = self.query(query);
+        let buckets: TsMrange<u64, f64> = con.ts_mrange(query, filter)?;
+        Ok(histogram_quantile(&buckets, quantile))
+    }
+
+    /// Async version of create.
+    #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+    pub async fn create_async<C: crate::AsyncTsCommands>(&self, con: &mut C) -> RedisResult<()> {
+        for (key, options) in self.series() {
+            let _: () = con.ts_create(key, options).await?;
+        }
+        Ok(())
+    }
+
+    /// Async version of record.
+    #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+    pub async fn record_async<C: crate::AsyncTsCommands>(
+        &self,
+        con: &mut C,
+        ts: u64,
+        observations: &[f64],
+    ) -> RedisResult<()> {
+        con.ts_madd(&self.samples(ts, observations)).await
+    }
+
+    /// Async version of quantile.
+    #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+    pub async fn quantile_async<C: crate::AsyncTsCommands>(
+        &self,
+        con: &mut C,
+        query: TsRangeQuery,
+        quantile: f64,
+    ) -> RedisResult<TsRange<u64, f64>> {
+        let (query, filter) = self.query(query);
+        let buckets: TsMrange<u64, f64> = con.ts_mrange(query, filter).await?;
+        Ok(histogram_quantile(&buckets, quantile))
+    }
+}
+
+/// Returns the upper bound of a grouped bucket series, either from its `le`
+/// label or its `le=<bound>` key.
+fn bucket_bound(entry: &TsMrangeEntry<u64, f64>) -> Option<f64> {
+    let le = entry
+        .labels
+        .iter()
+        .find(|(name, _)| name == BUCKET_LABEL)
+        .map(|(_, value)| value.as_str())
+        .or_else(|| entry.key.strip_prefix("le="))?;
+    match le {
+        "+Inf" | "inf" => Some(f64::INFINITY),
+        le => le.parse().ok(),
+    }
+}
+
+/// Estimates a quantile from cumulative bucket counts sorted by upper bound.
+/// The `+Inf` bucket has to be the last one.
+fn estimate(quantile: f64, buckets: &[(f64, f64)]) -> f64 {
+    if quantile < 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if quantile > 1.0 {
+        return f64::INFINITY;
+    }
+    let total = buckets[buckets.len() - 1].1;
+    if buckets.len() < 2 || total <= 0.0 {
+        return f64::NAN;
+    }
+    let rank = quantile * total;
+    let idx = buckets
+        .iter()
+        .position(|(_, count)| *count >= rank)
+        .unwrap_or(buckets.len() - 1);
+    if idx == buckets.len() - 1 {
+        return buckets[idx - 1].0;
+    }
+    let (end, count) = buckets[idx];
+    let (start, previous) = match idx {
+        0 if end <= 0.0 => return end,
+        0 => (0.0, 0.0),
+        _ => buckets[idx - 1],
+    };
+    if count == previous {
+        return end;
+    }
+    start + (end - start) * (rank - previous) / (count - previous)
+}
+
+/// Estimates the given quantile (0 to 1) at every timestamp of a bucket query
+/// result, eg. of TS.MRANGE with `GROUPBY le REDUCE sum`. Timestamps without a
+/// `+Inf` bucket are skipped, timestamps without observations are NaN.
+pub fn histogram_quantile(buckets: &TsMrange<u64, f64>, quantile: f64) -> TsRange<u64, f64> {
+    let mut samples: BTreeMap<u64, Vec<(f64, f64)>> = BTreeMap::new();
+    for entry in buckets.values.iter() {
+        if let Some(bound) = bucket_bound(entry) {
+            for (ts, count) in entry.values.iter() {
+                samples.entry(*ts).or_default().push((bound, *count));
+            }
+        }
+    }
+    let values = samples
+        .into_iter()
+        .filter_map(|(ts, mut counts)| {
+            counts.sort_by(|a, b| a.0.total_cmp(&b.0));
+            match counts.last() {
+                Some((bound, _)) if *bound == f64::INFINITY => {
+                    Some((ts, estimate(quantile, &counts)))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    TsRange { values }
+}
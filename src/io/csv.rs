@@ -0,0 +1,543 @@
+//! CSV export of range results and CSV import via TS.MADD.
+//!
+//! Results can be exported in long format with the columns key, labels,
+//! timestamp and value, or in wide format with a timestamp column and one
+//! column per series. Labels in long format are written as `name=value`
+//! pairs separated by `;`, with `;`, `=` and `\` in names and values escaped
+//! by a backslash.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! use redis_ts::io::csv::{import, write_mrange, TsCsvFormat, TsCsvImport, TsTimestampFormat};
+//! use redis_ts::{TsCommands, TsFilterOptions, TsMrange, TsOptions, TsRangeQuery};
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/")?;
+//! let mut con = client.get_connection()?;
+//!
+//! let temperatures: TsMrange<u64, f64> = con.ts_mrange(
+//!     TsRangeQuery::default(),
+//!     TsFilterOptions::default().equals("sensor", "temperature").with_labels(true),
+//! )?;
+//! write_mrange(std::io::stdout(), &temperatures, TsCsvFormat::Wide)?;
+//!
+//! let config = TsCsvImport::wide("time")
+//!     .timestamp_format(TsTimestampFormat::Rfc3339)
+//!     .key_prefix("backfill:")
+//!     .options(TsOptions::default().label("source", "backfill"));
+//! let file = std::fs::File::open("history.csv")?;
+//! let report = import(&mut con, file, &config)?;
+//! for rejected in report.rejected.iter() {
+//!     eprintln!("line {}: {}", rejected.line, rejected.reason);
+//! }
+//! # Ok(()) }
+//! ```
+//!
+use crate::commands::TsCommands;
+use crate::frame::{TsFrame, TsJoin};
+use crate::io::{create_if_missing, madd, parse_rfc3339};
+use crate::types::*;
+use redis::{RedisError, RedisResult};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+/// The layout of exported CSV data.
+/// - Long: One row per sample with the columns key, labels, timestamp, value.
+/// - Wide: One row per timestamp with one column per series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsCsvFormat {
+    Long,
+    Wide,
+}
+
+/// The format of timestamps in imported CSV data. All formats are normalized to
+/// unix millis.
+/// - Millis: Integer unix millis.
+/// - Seconds: Unix seconds, fractions are allowed.
+/// - Rfc3339: Date time strings like `2021-03-04T10:00:00.500Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsTimestampFormat {
+    Millis,
+    Seconds,
+    Rfc3339,
+}
+
+fn csv_error(e: csv::Error) -> RedisError {
+    RedisError::from(std::io::Error::other(e))
+}
+
+fn parse_error(msg: String) -> RedisError {
+    RedisError::from(std::io::Error::new(std::io::ErrorKind::InvalidData, msg))
+}
+
+fn escape_label(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, ';' | '=' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn format_labels(labels: &[(String, String)]) -> String {
+    labels
+        .iter()
+        .map(|(name, value)| format!("{}={}", escape_label(name), escape_label(value)))
+        .collect::<Vec<String>>()
+        .join(";")
+}
+
+/// Parses `name=value` pairs separated by `;`, honouring backslash escapes.
+/// Pairs without a `=` are skipped.
+fn parse_labels(labels: &str) -> Vec<(String, String)> {
+    let mut result = vec![];
+    let mut name = String::new();
+    let mut current = String::new();
+    let mut in_value = false;
+    let mut chars = labels.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            '=' if !in_value => {
+                name = std::mem::take(&mut current);
+                in_value = true;
+            }
+            ';' => {
+                if in_value {
+                    let value = std::mem::take(&mut current);
+                    result.push((name.trim().to_string(), value.trim().to_string()));
+                }
+                current.clear();
+                in_value = false;
+            }
+            c => current.push(c),
+        }
+    }
+    if in_value {
+        result.push((name.trim().to_string(), current.trim().to_string()));
+    }
+    result
+}
+
+fn write_long<W: Write>(
+    writer: &mut csv::Writer<W>,
+    key: &str,
+    labels: &[(String, String)],
+    values: &[(u64, f64)],
+) -> RedisResult<()> {
+    let labels = format_labels(labels);
+    for (ts, value) in values {
+        writer
+            .write_record([key, &labels, &ts.to_string(), &value.to_string()])
+            .map_err(csv_error)?;
+    }
+    Ok(())
+}
+
+/// Writes a single TS.RANGE result in long format with empty labels.
+pub fn write_range<W: Write>(writer: W, key: &str, range: &TsRange<u64, f64>) -> RedisResult<()> {
+    let mut w = csv::Writer::from_writer(writer);
+    w.write_record(["key", "labels", "timestamp", "value"])
+        .map_err(csv_error)?;
+    write_long(&mut w, key, &[], &range.values)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Writes a TS.MRANGE result in the given format. The wide format contains all
+/// timestamps of any series, missing values are left empty.
+pub fn write_mrange<W: Write>(
+    writer: W,
+    mrange: &TsMrange<u64, f64>,
+    format: TsCsvFormat,
+) -> RedisResult<()> {
+    let mut w = csv::Writer::from_writer(writer);
+    match format {
+        TsCsvFormat::Long => {
+            w.write_record(["key", "labels", "timestamp", "value"])
+                .map_err(csv_error)?;
+            for entry in mrange.values.iter() {
+                write_long(&mut w, &entry.key, &entry.labels, &entry.values)?;
+            }
+        }
+        TsCsvFormat::Wide => {
+            let frame = TsFrame::from_mrange(mrange, TsJoin::Outer);
+            let mut header = vec!["timestamp".to_string()];
+            header.extend(frame.columns.iter().map(|c| c.name.clone()));
+            w.write_record(&header).map_err(csv_error)?;
+            for (idx, ts) in frame.timestamps.iter().enumerate() {
+                let mut row = vec![ts.to_string()];
+                row.extend(
+                    frame
+                        .columns
+                        .iter()
+                        .map(|c| c.values[idx].map(|v| v.to_string()).unwrap_or_default()),
+                );
+                w.write_record(&row).map_err(csv_error)?;
+            }
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+enum TsCsvLayout {
+    Long {
+        key_column: String,
+        value_column: String,
+        labels_column: Option<String>,
+    },
+    Wide {
+        value_columns: Vec<String>,
+    },
+}
+
+/// Describes how CSV data is mapped to time series samples for an import. Long
+/// imports read the key, timestamp and value of every sample from a column. Wide
+/// imports use a timestamp column and turn every other column into a series named
+/// after its header.
+///
+/// If options are given, every key is created with TS.CREATE on first sight. Keys
+/// that already exist are left untouched. Labels read from a labels column are
+/// added to the options of the created key.
+#[derive(Debug, Clone)]
+pub struct TsCsvImport {
+    layout: TsCsvLayout,
+    timestamp_column: String,
+    timestamp_format: TsTimestampFormat,
+    key_prefix: String,
+    options: Option<TsOptions>,
+    batch_size: usize,
+    delimiter: u8,
+}
+
+impl TsCsvImport {
+    /// Imports data in long format with the given key, timestamp and value columns.
+    pub fn long(key_column: &str, timestamp_column: &str, value_column: &str) -> Self {
+        TsCsvImport::new(
+            TsCsvLayout::Long {
+                key_column: key_column.to_string(),
+                value_column: value_column.to_string(),
+                labels_column: None,
+            },
+            timestamp_column,
+        )
+    }
+
+    /// Imports data in wide format with the given timestamp column.
+    pub fn wide(timestamp_column: &str) -> Self {
+        TsCsvImport::new(
+            TsCsvLayout::Wide {
+                value_columns: vec![],
+            },
+            timestamp_column,
+        )
+    }
+
+    fn new(layout: TsCsvLayout, timestamp_column: &str) -> Self {
+        TsCsvImport {
+            layout,
+            timestamp_column: timestamp_column.to_string(),
+            timestamp_format: TsTimestampFormat::Millis,
+            key_prefix: String::new(),
+            options: None,
+            batch_size: 1000,
+            delimiter: b',',
+        }
+    }
+
+    /// Reads labels for auto created keys from the given column of a long import.
+    pub fn labels_column(mut self, column: &str) -> Self {
+        if let TsCsvLayout::Long {
+            ref mut labels_column,
+            ..
+        } = self.layout
+        {
+            *labels_column = Some(column.to_string());
+        }
+        self
+    }
+
+    /// Restricts a wide import to the given value columns. Defaults to all
+    /// columns except the timestamp column.
+    pub fn value_columns(mut self, columns: Vec<&str>) -> Self {
+        if let TsCsvLayout::Wide {
+            ref mut value_columns,
+        } = self.layout
+        {
+            *value_columns = columns.into_iter().map(|c| c.to_string()).collect();
+        }
+        self
+    }
+
+    /// The format of the timestamp column. Defaults to millis.
+    pub fn timestamp_format(mut self, format: TsTimestampFormat) -> Self {
+        self.timestamp_format = format;
+        self
+    }
+
+    /// Prepends the given prefix to all imported keys.
+    pub fn key_prefix(mut self, prefix: &str) -> Self {
+        self.key_prefix = prefix.to_string();
+        self
+    }
+
+    /// Creates all imported keys with the given options if they do not exist yet.
+    pub fn options(mut self, options: TsOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// The max amount of samples sent in a single TS.MADD. Defaults to 1000.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// The field delimiter of the CSV data. Defaults to `,`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    fn column_index(&self, headers: &csv::StringRecord, name: &str) -> RedisResult<usize> {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| parse_error(format!("missing csv column {name}")))
+    }
+
+    /// Reads the samples of the given CSV data row by row. Every item holds the
+    /// samples of one row, so the data is never read into memory at once.
+    fn records<'a, R: Read + 'a>(
+        &'a self,
+        reader: R,
+    ) -> RedisResult<impl Iterator<Item = RedisResult<Vec<TsCsvRecord>>> + 'a> {
+        let mut r = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .from_reader(reader);
+        let headers = r.headers().map_err(csv_error)?.clone();
+        let ts_idx = self.column_index(&headers, &self.timestamp_column)?;
+
+        let columns = match self.layout {
+            TsCsvLayout::Long {
+                ref key_column,
+                ref value_column,
+                ref labels_column,
+            } => TsCsvColumns::Long {
+                key: self.column_index(&headers, key_column)?,
+                value: self.column_index(&headers, value_column)?,
+                labels: match labels_column {
+                    Some(c) => Some(self.column_index(&headers, c)?),
+                    None => None,
+                },
+            },
+            TsCsvLayout::Wide { ref value_columns } if value_columns.is_empty() => {
+                TsCsvColumns::Wide(
+                    headers
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| *i != ts_idx)
+                        .map(|(i, h)| (i, h.to_string()))
+                        .collect(),
+                )
+            }
+            TsCsvLayout::Wide { ref value_columns } => TsCsvColumns::Wide(
+                value_columns
+                    .iter()
+                    .map(|c| Ok((self.column_index(&headers, c)?, c.clone())))
+                    .collect::<RedisResult<_>>()?,
+            ),
+        };
+
+        Ok(r.into_records().map(move |row| {
+            let row = row.map_err(csv_error)?;
+            let line = row.position().map_or(0, |p| p.line());
+            let ts = parse_timestamp(&row[ts_idx], self.timestamp_format)?;
+            match columns {
+                TsCsvColumns::Long { key, value, labels } => Ok(vec![TsCsvRecord {
+                    key: format!("{}{}", self.key_prefix, &row[key]),
+                    labels: labels.map(|i| parse_labels(&row[i])).unwrap_or_default(),
+                    line,
+                    ts,
+                    value: parse_value(&row[value])?,
+                }]),
+                TsCsvColumns::Wide(ref columns) => columns
+                    .iter()
+                    .filter(|(idx, _)| !row[*idx].trim().is_empty())
+                    .map(|(idx, name)| {
+                        Ok(TsCsvRecord {
+                            key: format!("{}{}", self.key_prefix, name),
+                            labels: vec![],
+                            line,
+                            ts,
+                            value: parse_value(&row[*idx])?,
+                        })
+                    })
+                    .collect(),
+            }
+        }))
+    }
+
+    fn create_options(&self, labels: &[(String, String)]) -> Option<TsOptions> {
+        self.options.clone().map(|o| {
+            labels
+                .iter()
+                .fold(o, |o, (name, value)| o.label(name, value))
+        })
+    }
+}
+
+/// The column indexes of a layout resolved against the CSV headers.
+enum TsCsvColumns {
+    Long {
+        key: usize,
+        value: usize,
+        labels: Option<usize>,
+    },
+    Wide(Vec<(usize, String)>),
+}
+
+struct TsCsvRecord {
+    key: String,
+    labels: Vec<(String, String)>,
+    line: u64,
+    ts: u64,
+    value: f64,
+}
+
+/// A sample of the imported CSV data that was rejected by the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsCsvRejected {
+    /// The line of the sample in the CSV data, the header is line 1.
+    pub line: u64,
+    pub key: String,
+    pub ts: u64,
+    pub value: f64,
+    pub reason: String,
+}
+
+/// The outcome of an import. Samples rejected by the server, eg. duplicates
+/// under a BLOCK policy or samples older than the retention, are listed with
+/// their line and the error message instead of failing the whole import.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TsCsvReport {
+    pub imported: u64,
+    pub rejected: Vec<TsCsvRejected>,
+}
+
+impl TsCsvReport {
+    /// Adds the per sample results of a TS.MADD for the given records.
+    fn add(&mut self, records: &mut Vec<TsCsvRecord>, results: Vec<Result<u64, String>>) {
+        for (record, result) in records.drain(..).zip(results) {
+            match result {
+                Ok(_) => self.imported += 1,
+                Err(reason) => self.rejected.push(TsCsvRejected {
+                    line: record.line,
+                    key: record.key,
+                    ts: record.ts,
+                    value: record.value,
+                    reason,
+                }),
+            }
+        }
+    }
+}
+
+fn batch(records: &[TsCsvRecord]) -> Vec<(&str, u64, f64)> {
+    records
+        .iter()
+        .map(|r| (r.key.as_str(), r.ts, r.value))
+        .collect()
+}
+
+fn parse_value(value: &str) -> RedisResult<f64> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| parse_error(format!("invalid csv value {value}")))
+}
+
+/// Parses a timestamp in the given format into unix millis.
+pub fn parse_timestamp(value: &str, format: TsTimestampFormat) -> RedisResult<u64> {
+    let value = value.trim();
+    let parsed = match format {
+        TsTimestampFormat::Millis => value.parse::<u64>().ok(),
+        TsTimestampFormat::Seconds => value
+            .parse::<f64>()
+            .ok()
+            .filter(|s| *s >= 0.0)
+            .map(|s| (s * 1000.0).round() as u64),
+        TsTimestampFormat::Rfc3339 => parse_rfc3339(value),
+    };
+    parsed.ok_or_else(|| parse_error(format!("invalid csv timestamp {value}")))
+}
+
+/// Imports CSV data with the given configuration and returns the number of
+/// imported samples and the samples rejected by the server.
+pub fn import<C: TsCommands, R: Read>(
+    con: &mut C,
+    reader: R,
+    config: &TsCsvImport,
+) -> RedisResult<TsCsvReport> {
+    let mut created: HashSet<String> = HashSet::new();
+    let mut records: Vec<TsCsvRecord> = Vec::with_capacity(config.batch_size);
+    let mut report = TsCsvReport::default();
+
+    for row in config.records(reader)? {
+        for record in row? {
+            if let Some(options) = config.create_options(&record.labels) {
+                if created.insert(record.key.clone()) {
+                    create_if_missing(con, &record.key, options)?;
+                }
+            }
+            records.push(record);
+            if records.len() >= config.batch_size {
+                let results = madd(con, &batch(&records))?;
+                report.add(&mut records, results);
+            }
+        }
+    }
+
+    if !records.is_empty() {
+        let results = madd(con, &batch(&records))?;
+        report.add(&mut records, results);
+    }
+    Ok(report)
+}
+
+/// Async version of import. The CSV data itself is read synchronously.
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+pub async fn import_async<C: crate::AsyncTsCommands, R: Read>(
+    con: &mut C,
+    reader: R,
+    config: &TsCsvImport,
+) -> RedisResult<TsCsvReport> {
+    let mut created: HashSet<String> = HashSet::new();
+    let mut records: Vec<TsCsvRecord> = Vec::with_capacity(config.batch_size);
+    let mut report = TsCsvReport::default();
+
+    for row in config.records(reader)? {
+        for record in row? {
+            if let Some(options) = config.create_options(&record.labels) {
+                if created.insert(record.key.clone()) {
+                    crate::io::create_if_missing_async(con, &record.key, options).await?;
+                }
+            }
+            records.push(record);
+            if records.len() >= config.batch_size {
+                let results = crate::io::madd_async(con, &batch(&records)).await?;
+                report.add(&mut records, results);
+            }
+        }
+    }
+
+    if !records.is_empty() {
+        let results = crate::io::madd_async(con, &batch(&records)).await?;
+        report.add(&mut records, results);
+    }
+    Ok(report)
+}
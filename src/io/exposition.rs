@@ -0,0 +1,195 @@
+//! Prometheus and OpenMetrics text exposition of the latest values of series.
+//!
+//! Renders a TS.MGET result so that it can be served from a scrape endpoint.
+//! The metric name of a series is derived from its key or one of its labels,
+//! all other labels are exposed as metric labels. Names are sanitized to the
+//! Prometheus naming rules, series without a value are skipped.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! use redis_ts::io::exposition::{TsExposition, TsMetricName};
+//! use redis_ts::{TsCommands, TsFilterOptions, TsMget};
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/")?;
+//! let mut con = client.get_connection()?;
+//!
+//! let latest: TsMget<u64, f64> = con.ts_mget(
+//!     TsFilterOptions::default().equals("sensor", "temperature").with_labels(true),
+//! )?;
+//! let body = TsExposition::new(TsMetricName::Label("metric".to_string()))
+//!     .prefix("redis_")
+//!     .render(&latest);
+//! # Ok(()) }
+//! ```
+//!
+use crate::types::TsMget;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Controls how the metric name of a series is derived.
+/// - Key: The series key.
+/// - Label(name): The value of the given label. The label itself is not exposed
+///   and series without it are skipped.
+/// - Constant(name): The same name for all series, eg. if they only differ by
+///   labels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TsMetricName {
+    Key,
+    Label(String),
+    Constant(String),
+}
+
+/// The text format to render.
+/// - Prometheus: The classic text format with millisecond timestamps.
+/// - OpenMetrics: OpenMetrics text with second timestamps and a trailing `# EOF`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsExpositionFormat {
+    Prometheus,
+    OpenMetrics,
+}
+
+/// Renders TS.MGET results as text exposition.
+#[derive(Debug, Clone)]
+pub struct TsExposition {
+    name: TsMetricName,
+    prefix: String,
+    format: TsExpositionFormat,
+    timestamps: bool,
+    metric_type: String,
+}
+
+/// Replaces all characters that are not allowed in a metric name with `_`.
+/// Colons are only allowed in metric names, not in label names.
+fn sanitize(name: &str, colons: bool) -> String {
+    let mut result: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || (colons && c == ':') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    result
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        format!("{value}")
+    }
+}
+
+impl TsExposition {
+    /// Creates an exposition that derives metric names as given. Defaults to the
+    /// Prometheus format with timestamps and the gauge type.
+    pub fn new(name: TsMetricName) -> Self {
+        TsExposition {
+            name,
+            prefix: String::new(),
+            format: TsExpositionFormat::Prometheus,
+            timestamps: true,
+            metric_type: "gauge".to_string(),
+        }
+    }
+
+    /// A prefix added to all metric names.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// The text format to render. Defaults to Prometheus.
+    pub fn format(mut self, format: TsExpositionFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Adds the sample timestamps to all samples. Enabled by default.
+    pub fn timestamps(mut self, value: bool) -> Self {
+        self.timestamps = value;
+        self
+    }
+
+    /// The type announced for every metric. Defaults to gauge.
+    pub fn metric_type(mut self, metric_type: &str) -> Self {
+        self.metric_type = metric_type.to_string();
+        self
+    }
+
+    /// Returns the sanitized metric name of a series or None if it can not be
+    /// derived.
+    pub fn metric_name(&self, key: &str, labels: &[(String, String)]) -> Option<String> {
+        let name = match &self.name {
+            TsMetricName::Key => key,
+            TsMetricName::Label(label) => labels
+                .iter()
+                .find(|(n, _)| n == label)
+                .map(|(_, v)| v.as_str())?,
+            TsMetricName::Constant(name) => name,
+        };
+        Some(sanitize(&format!("{}{}", self.prefix, name), true))
+    }
+
+    /// Renders all series with a value. Series with the same metric name are
+    /// grouped below a single TYPE line.
+    pub fn render(&self, mget: &TsMget<u64, f64>) -> String {
+        let mut metrics: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for entry in mget.values.iter() {
+            let (ts, value) = match entry.value {
+                Some(v) => v,
+                None => continue,
+            };
+            let name = match self.metric_name(&entry.key, &entry.labels) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let labels: Vec<String> = entry
+                .labels
+                .iter()
+                .filter(|(n, _)| !n.starts_with("__"))
+                .filter(|(n, _)| !matches!(&self.name, TsMetricName::Label(l) if l == n))
+                .map(|(n, v)| format!("{}=\"{}\"", sanitize(n, false), escape_label_value(v)))
+                .collect();
+            let mut line = name.clone();
+            if !labels.is_empty() {
+                let _ = write!(line, "{{{}}}", labels.join(","));
+            }
+            let _ = write!(line, " {}", format_value(value));
+            if self.timestamps {
+                let _ = match self.format {
+                    TsExpositionFormat::Prometheus => write!(line, " {ts}"),
+                    TsExpositionFormat::OpenMetrics => write!(line, " {}", ts as f64 / 1000.0),
+                };
+            }
+            metrics.entry(name).or_default().push(line);
+        }
+
+        let mut body = String::new();
+        for (name, lines) in metrics {
+            let _ = writeln!(body, "# TYPE {name} {}", self.metric_type);
+            for line in lines {
+                let _ = writeln!(body, "{line}");
+            }
+        }
+        if self.format == TsExpositionFormat::OpenMetrics {
+            body.push_str("# EOF\n");
+        }
+        body
+    }
+}
@@ -0,0 +1,493 @@
+//! A handler for the Grafana JSON datasource protocol (SimpleJSON / Infinity).
+//!
+//! The handler is not tied to any HTTP framework. It takes the request path and
+//! JSON body and returns the JSON response body:
+//!
+//! - `/`: Connection test, returns `"OK"`.
+//! - `/search`: The target is a filter expression, returns the matching keys
+//!   via TS.QUERYINDEX. An empty target lists all series matching the base
+//!   filter, or all series if there is none.
+//! - `/query`: Every target is either a series key or a filter expression. The
+//!   series are aggregated into buckets of `intervalMs` (at least the range
+//!   divided by `maxDataPoints`) and returned as timeseries or table.
+//! - `/annotations`: Every sample of the series matching the annotation query
+//!   becomes an annotation tagged with the series labels.
+//!
+//! Filter expressions use the TS.MRANGE filter syntax, eg.
+//! `sensor=temperature room!=(attic,cellar) floor=`. The aggregation can be set
+//! per target with a payload like `{"aggregation": "max"}` (or `"none"` for raw
+//! samples) and defaults to avg. Ad hoc filters with the operators `=` and `!=`
+//! are added to all filter targets.
+//!
+//! ```rust,no_run
+//! # fn run(path: &str, body: &str) -> redis::RedisResult<()> {
+//! use redis_ts::io::grafana::TsGrafanaHandler;
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/")?;
+//! let mut con = client.get_connection()?;
+//!
+//! let handler = TsGrafanaHandler::new().base_filter("app=dashboard");
+//! let response: String = handler.handle(&mut con, path, body)?;
+//! # Ok(()) }
+//! ```
+//!
+use crate::commands::TsCommands;
+use crate::io::parse_rfc3339;
+pub use crate::io::{aggregation, parse_filter};
+use crate::promql::filter_value;
+use crate::types::*;
+use redis::{RedisError, RedisResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+/// The time range of a request as RFC3339 strings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GrafanaRange {
+    pub from: String,
+    pub to: String,
+}
+
+/// A single target of a query request.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrafanaTarget {
+    #[serde(default)]
+    pub target: String,
+    #[serde(default)]
+    pub ref_id: String,
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub payload: Option<Value>,
+}
+
+/// An ad hoc filter that applies to all targets of a query.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GrafanaAdhocFilter {
+    pub key: String,
+    pub operator: String,
+    pub value: String,
+}
+
+/// The body of a `/query` request.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrafanaQueryRequest {
+    pub range: GrafanaRange,
+    #[serde(default)]
+    pub interval_ms: u64,
+    #[serde(default)]
+    pub max_data_points: Option<u64>,
+    #[serde(default)]
+    pub targets: Vec<GrafanaTarget>,
+    #[serde(default)]
+    pub adhoc_filters: Vec<GrafanaAdhocFilter>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GrafanaSearchRequest {
+    #[serde(default)]
+    target: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GrafanaAnnotationRequest {
+    range: GrafanaRange,
+    annotation: Value,
+}
+
+/// A query needed to answer a request.
+/// - Range(key, query): TS.RANGE of a single series.
+/// - Mrange(query, filter): TS.MRANGE of all series matching the filter.
+#[derive(Debug, Clone)]
+pub enum GrafanaQuery {
+    Range(String, TsRangeQuery),
+    Mrange(TsRangeQuery, TsFilterOptions),
+}
+
+fn request_error(reason: String) -> RedisError {
+    RedisError::from(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("invalid grafana request ({reason})"),
+    ))
+}
+
+fn json_error(e: serde_json::Error) -> RedisError {
+    request_error(e.to_string())
+}
+
+fn parse_time(value: &str) -> RedisResult<u64> {
+    parse_rfc3339(value).ok_or_else(|| request_error(format!("invalid time {value}")))
+}
+
+/// Returns true if the target is a filter expression rather than a series key.
+fn is_filter(target: &str) -> bool {
+    target.contains('=')
+}
+
+/// Translates Grafana JSON datasource requests into time series queries.
+#[derive(Debug, Clone)]
+pub struct TsGrafanaHandler {
+    base_filter: String,
+    aggregation: String,
+    name_label: Option<String>,
+}
+
+impl Default for TsGrafanaHandler {
+    fn default() -> Self {
+        TsGrafanaHandler::new()
+    }
+}
+
+impl TsGrafanaHandler {
+    /// Creates a handler without base filter that aggregates with avg.
+    pub fn new() -> Self {
+        TsGrafanaHandler {
+            base_filter: String::new(),
+            aggregation: "avg".to_string(),
+            name_label: None,
+        }
+    }
+
+    /// A filter expression added to all filters, eg. to limit the series that
+    /// are visible to Grafana.
+    pub fn base_filter(mut self, expression: &str) -> Self {
+        self.base_filter = expression.to_string();
+        self
+    }
+
+    /// The aggregation used for targets without aggregation payload. Defaults
+    /// to avg.
+    pub fn aggregation(mut self, name: &str) -> Self {
+        self.aggregation = name.to_string();
+        self
+    }
+
+    /// Names timeseries after the value of this label instead of the key if
+    /// the series has it.
+    pub fn name_label(mut self, label: &str) -> Self {
+        self.name_label = Some(label.to_string());
+        self
+    }
+
+    fn filter(&self, expression: &str) -> RedisResult<TsFilterOptions> {
+        let filter = parse_filter(
+            TsFilterOptions::default().with_labels(true),
+            &self.base_filter,
+        )?;
+        let filter = parse_filter(filter, expression)?;
+        if self.base_filter.trim().is_empty() && expression.trim().is_empty() {
+            return Err(request_error("empty filter".to_string()));
+        }
+        Ok(filter)
+    }
+
+    /// Returns the filter of a search target, or None if the target and the
+    /// base filter are empty and all series are listed.
+    fn search_filter(&self, target: &str) -> RedisResult<Option<TsFilterOptions>> {
+        if self.base_filter.trim().is_empty() && target.trim().is_empty() {
+            return Ok(None);
+        }
+        self.filter(target).map(Some)
+    }
+
+    fn range_query(&self, range: &GrafanaRange) -> RedisResult<TsRangeQuery> {
+        Ok(TsRangeQuery::default()
+            .from(parse_time(&range.from)?)
+            .to(parse_time(&range.to)?))
+    }
+
+    /// Returns the queries needed to answer a query request, one per target.
+    pub fn query_plan(&self, request: &GrafanaQueryRequest) -> RedisResult<Vec<GrafanaQuery>> {
+        let from = parse_time(&request.range.from)?;
+        let to = parse_time(&request.range.to)?;
+        let min_bucket = request
+            .max_data_points
+            .filter(|p| *p > 0)
+            .map(|p| to.saturating_sub(from).div_ceil(p))
+            .unwrap_or(1);
+        let bucket = request.interval_ms.max(min_bucket).max(1);
+        for f in request.adhoc_filters.iter() {
+            if !matches!(f.operator.as_str(), "=" | "!=") {
+                return Err(request_error(format!(
+                    "unsupported operator {}",
+                    f.operator
+                )));
+            }
+            if f.key.is_empty() || f.key.contains(['=', '!']) || !filter_value(&f.value) {
+                return Err(request_error(format!(
+                    "unsupported ad hoc filter {}{}{}",
+                    f.key, f.operator, f.value
+                )));
+            }
+        }
+
+        request
+            .targets
+            .iter()
+            .map(|target| {
+                let name = target
+                    .payload
+                    .as_ref()
+                    .and_then(|p| p.get("aggregation"))
+                    .and_then(|a| a.as_str())
+                    .unwrap_or(&self.aggregation);
+                let mut query = self.range_query(&request.range)?;
+                if name != "none" {
+                    let aggregation = aggregation(name, bucket)
+                        .ok_or_else(|| request_error(format!("unknown aggregation {name}")))?;
+                    query = query.aggregation_type(aggregation).align(TsAlign::Start);
+                }
+                if is_filter(&target.target) {
+                    let filter = request.adhoc_filters.iter().fold(
+                        self.filter(&target.target)?,
+                        |filter, f| match f.operator.as_str() {
+                            "=" => filter.equals(&f.key, &f.value),
+                            _ => filter.not_equals(&f.key, &f.value),
+                        },
+                    );
+                    Ok(GrafanaQuery::Mrange(query, filter))
+                } else if target.target.is_empty() {
+                    Err(request_error(format!("empty target {}", target.ref_id)))
+                } else {
+                    Ok(GrafanaQuery::Range(target.target.clone(), query))
+                }
+            })
+            .collect()
+    }
+
+    fn series_name(&self, entry: &TsMrangeEntry<u64, f64>) -> String {
+        self.name_label
+            .as_ref()
+            .and_then(|label| entry.labels.iter().find(|(n, _)| n == label))
+            .map(|(_, v)| v.clone())
+            .unwrap_or_else(|| entry.key.clone())
+    }
+
+    /// Formats the results of the query_plan queries as Grafana response. Range
+    /// results are expected as TsMrange with a single entry.
+    pub fn query_response(
+        &self,
+        request: &GrafanaQueryRequest,
+        results: Vec<TsMrange<u64, f64>>,
+    ) -> Value {
+        let mut response = vec![];
+        for (target, result) in request.targets.iter().zip(results) {
+            if target.kind.as_deref() == Some("table") {
+                let rows: Vec<Value> = result
+                    .values
+                    .iter()
+                    .flat_map(|e| e.values.iter().map(move |(ts, v)| json!([ts, e.key, v])))
+                    .collect();
+                response.push(json!({
+                    "type": "table",
+                    "refId": target.ref_id,
+                    "columns": [
+                        {"text": "Time", "type": "time"},
+                        {"text": "Series", "type": "string"},
+                        {"text": "Value", "type": "number"}
+                    ],
+                    "rows": rows,
+                }));
+            } else {
+                for entry in result.values.iter() {
+                    let datapoints: Vec<Value> =
+                        entry.values.iter().map(|(ts, v)| json!([v, ts])).collect();
+                    response.push(json!({
+                        "target": self.series_name(entry),
+                        "refId": target.ref_id,
+                        "datapoints": datapoints,
+                    }));
+                }
+            }
+        }
+        Value::Array(response)
+    }
+
+    fn annotation_response(&self, annotation: &Value, results: TsMrange<u64, f64>) -> Value {
+        let mut response = vec![];
+        for entry in results.values.iter() {
+            let tags: Vec<String> = entry
+                .labels
+                .iter()
+                .map(|(n, v)| format!("{n}={v}"))
+                .collect();
+            for (ts, value) in entry.values.iter() {
+                response.push(json!({
+                    "annotation": annotation,
+                    "time": ts,
+                    "title": self.series_name(entry),
+                    "text": format!("{value}"),
+                    "tags": tags,
+                }));
+            }
+        }
+        Value::Array(response)
+    }
+
+    fn annotation_query(&self, request: &GrafanaAnnotationRequest) -> RedisResult<GrafanaQuery> {
+        let query = self.range_query(&request.range)?;
+        let expression = request
+            .annotation
+            .get("query")
+            .and_then(|q| q.as_str())
+            .unwrap_or_default();
+        if is_filter(expression) || expression.is_empty() {
+            Ok(GrafanaQuery::Mrange(query, self.filter(expression)?))
+        } else {
+            Ok(GrafanaQuery::Range(expression.to_string(), query))
+        }
+    }
+
+    /// Answers a request to the given path with a JSON response body.
+    pub fn handle<C: TsCommands>(
+        &self,
+        con: &mut C,
+        path: &str,
+        body: &str,
+    ) -> RedisResult<String> {
+        let response = match path.trim_end_matches('/') {
+            "" => json!("OK"),
+            "/search" => {
+                let request: GrafanaSearchRequest =
+                    serde_json::from_str(body).map_err(json_error)?;
+                let keys: Vec<String> = match self.search_filter(&request.target)? {
+                    Some(filter) => con.ts_queryindex(filter)?,
+                    None => all_keys(con)?,
+                };
+                json!(keys)
+            }
+            "/query" => {
+                let request: GrafanaQueryRequest =
+                    serde_json::from_str(body).map_err(json_error)?;
+                let mut results = vec![];
+                for query in self.query_plan(&request)? {
+                    results.push(execute(con, query)?);
+                }
+                self.query_response(&request, results)
+            }
+            "/annotations" => {
+                let request: GrafanaAnnotationRequest =
+                    serde_json::from_str(body).map_err(json_error)?;
+                let results = execute(con, self.annotation_query(&request)?)?;
+                self.annotation_response(&request.annotation, results)
+            }
+            path => return Err(request_error(format!("unknown path {path}"))),
+        };
+        Ok(response.to_string())
+    }
+
+    /// Async version of handle.
+    #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+    pub async fn handle_async<C: crate::AsyncTsCommands>(
+        &self,
+        con: &mut C,
+        path: &str,
+        body: &str,
+    ) -> RedisResult<String> {
+        let response = match path.trim_end_matches('/') {
+            "" => json!("OK"),
+            "/search" => {
+                let request: GrafanaSearchRequest =
+                    serde_json::from_str(body).map_err(json_error)?;
+                let keys: Vec<String> = match self.search_filter(&request.target)? {
+                    Some(filter) => con.ts_queryindex(filter).await?,
+                    None => all_keys_async(con).await?,
+                };
+                json!(keys)
+            }
+            "/query" => {
+                let request: GrafanaQueryRequest =
+                    serde_json::from_str(body).map_err(json_error)?;
+                let mut results = vec![];
+                for query in self.query_plan(&request)? {
+                    results.push(execute_async(con, query).await?);
+                }
+                self.query_response(&request, results)
+            }
+            "/annotations" => {
+                let request: GrafanaAnnotationRequest =
+                    serde_json::from_str(body).map_err(json_error)?;
+                let results = execute_async(con, self.annotation_query(&request)?).await?;
+                self.annotation_response(&request.annotation, results)
+            }
+            path => return Err(request_error(format!("unknown path {path}"))),
+        };
+        Ok(response.to_string())
+    }
+}
+
+fn scan_cmd(cursor: u64) -> redis::Cmd {
+    let mut cmd = redis::cmd("SCAN");
+    cmd.arg(cursor)
+        .arg("TYPE")
+        .arg("TSDB-TYPE")
+        .arg("COUNT")
+        .arg(1000);
+    cmd
+}
+
+/// Returns the sorted keys of all time series, TS.QUERYINDEX needs at least one
+/// label to match.
+fn all_keys<C: TsCommands>(con: &mut C) -> RedisResult<Vec<String>> {
+    let mut keys = BTreeSet::new();
+    let mut cursor = 0;
+    loop {
+        let (next, batch): (u64, Vec<String>) = scan_cmd(cursor).query(con)?;
+        keys.extend(batch);
+        if next == 0 {
+            return Ok(keys.into_iter().collect());
+        }
+        cursor = next;
+    }
+}
+
+/// Async version of all_keys.
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+async fn all_keys_async<C: crate::AsyncTsCommands>(con: &mut C) -> RedisResult<Vec<String>> {
+    let mut keys = BTreeSet::new();
+    let mut cursor = 0;
+    loop {
+        let (next, batch): (u64, Vec<String>) = scan_cmd(cursor).query_async(con).await?;
+        keys.extend(batch);
+        if next == 0 {
+            return Ok(keys.into_iter().collect());
+        }
+        cursor = next;
+    }
+}
+
+fn range_to_mrange(key: String, range: TsRange<u64, f64>) -> TsMrange<u64, f64> {
+    TsMrange {
+        values: vec![TsMrangeEntry {
+            key,
+            labels: vec![],
+            values: range.values,
+        }],
+    }
+}
+
+fn execute<C: TsCommands>(con: &mut C, query: GrafanaQuery) -> RedisResult<TsMrange<u64, f64>> {
+    match query {
+        GrafanaQuery::Range(key, query) => {
+            let range: TsRange<u64, f64> = con.ts_range(key.as_str(), query)?;
+            Ok(range_to_mrange(key, range))
+        }
+        GrafanaQuery::Mrange(query, filter) => con.ts_mrange(query, filter),
+    }
+}
+
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+async fn execute_async<C: crate::AsyncTsCommands>(
+    con: &mut C,
+    query: GrafanaQuery,
+) -> RedisResult<TsMrange<u64, f64>> {
+    match query {
+        GrafanaQuery::Range(key, query) => {
+            let range: TsRange<u64, f64> = con.ts_range(key.as_str(), query).await?;
+            Ok(range_to_mrange(key, range))
+        }
+        GrafanaQuery::Mrange(query, filter) => con.ts_mrange(query, filter).await,
+    }
+}
@@ -0,0 +1,391 @@
+//! Ingestion of InfluxDB line protocol data.
+//!
+//! Every numeric field of a line becomes a sample of its own series. Series keys
+//! are derived from a template, tags become labels of the series. Keys are
+//! created with TS.CREATE the first time a sink sees them and samples are
+//! written in TS.MADD batches.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! use redis_ts::io::influx::{TsInfluxSink, TsPrecision};
+//! use redis_ts::TsOptions;
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/")?;
+//! let mut con = client.get_connection()?;
+//!
+//! let mut sink = TsInfluxSink::new("telegraf:{measurement}:{host}:{field}")
+//!     .precision(TsPrecision::Seconds)
+//!     .options(TsOptions::default().retention_time(86400000));
+//!
+//! let report = sink.write(&mut con, "cpu,host=web01 usage_idle=97.5,usage_user=1.2 1700000000")?;
+//! for (sample, reason) in report.rejected.iter() {
+//!     eprintln!("rejected {} at {}: {}", sample.key, sample.ts, reason);
+//! }
+//! # Ok(()) }
+//! ```
+//!
+use crate::commands::TsCommands;
+use crate::io::{create_if_missing, madd};
+use crate::types::TsOptions;
+use redis::{RedisError, RedisResult};
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The precision of line protocol timestamps. Timestamps are normalized to
+/// millis before they are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsPrecision {
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+    Seconds,
+}
+
+impl TsPrecision {
+    /// Converts a timestamp of this precision into millis.
+    pub fn to_millis(&self, ts: u64) -> u64 {
+        match self {
+            TsPrecision::Nanoseconds => ts / 1_000_000,
+            TsPrecision::Microseconds => ts / 1_000,
+            TsPrecision::Milliseconds => ts,
+            TsPrecision::Seconds => ts.saturating_mul(1_000),
+        }
+    }
+}
+
+/// A single parsed line of line protocol. Only numeric and boolean fields are
+/// kept, booleans are represented as 1.0 and 0.0. The timestamp is in the
+/// precision of the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsLinePoint {
+    pub measurement: String,
+    pub tags: Vec<(String, String)>,
+    pub fields: Vec<(String, f64)>,
+    pub timestamp: Option<u64>,
+}
+
+fn parse_error(line: &str, reason: &str) -> RedisError {
+    RedisError::from(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("invalid line protocol ({reason}): {line}"),
+    ))
+}
+
+/// Splits at every unescaped separator. Separators inside double quotes are
+/// ignored if quoted is set.
+fn split_unescaped(s: &str, sep: char, quoted: bool) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    let mut in_quotes = false;
+    for (idx, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' if quoted => in_quotes = !in_quotes,
+            c if c == sep && !in_quotes => {
+                parts.push(&s[start..idx]);
+                start = idx + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next @ (',' | '=' | ' ' | '"' | '\\')) => result.push(next),
+                Some(next) => {
+                    result.push('\\');
+                    result.push(next);
+                }
+                None => result.push('\\'),
+            },
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn parse_field_value(value: &str) -> Option<f64> {
+    match value {
+        "t" | "T" | "true" | "True" | "TRUE" => Some(1.0),
+        "f" | "F" | "false" | "False" | "FALSE" => Some(0.0),
+        v if v.starts_with('"') => None,
+        v if v.ends_with('i') => v[..v.len() - 1].parse::<i64>().ok().map(|i| i as f64),
+        v if v.ends_with('u') => v[..v.len() - 1].parse::<u64>().ok().map(|u| u as f64),
+        v => v.parse::<f64>().ok(),
+    }
+}
+
+/// Parses a single line. Returns None for empty lines and comments.
+pub fn parse_line(line: &str) -> RedisResult<Option<TsLinePoint>> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return Ok(None);
+    }
+
+    let parts: Vec<&str> = split_unescaped(trimmed, ' ', true)
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return Err(parse_error(line, "expected series, fields and timestamp"));
+    }
+
+    let mut series = split_unescaped(parts[0], ',', false).into_iter();
+    let measurement = unescape(series.next().unwrap_or_default());
+    if measurement.is_empty() {
+        return Err(parse_error(line, "missing measurement"));
+    }
+    let tags = series
+        .map(|tag| match split_unescaped(tag, '=', false).as_slice() {
+            [name, value] => Ok((unescape(name), unescape(value))),
+            _ => Err(parse_error(line, "invalid tag")),
+        })
+        .collect::<RedisResult<Vec<(String, String)>>>()?;
+
+    let mut fields = vec![];
+    for field in split_unescaped(parts[1], ',', true) {
+        match split_unescaped(field, '=', true).as_slice() {
+            [name, value] => {
+                if let Some(v) = parse_field_value(value) {
+                    fields.push((unescape(name), v));
+                }
+            }
+            _ => return Err(parse_error(line, "invalid field")),
+        }
+    }
+
+    let timestamp = match parts.get(2) {
+        Some(ts) => Some(
+            ts.parse::<u64>()
+                .map_err(|_| parse_error(line, "invalid timestamp"))?,
+        ),
+        None => None,
+    };
+
+    Ok(Some(TsLinePoint {
+        measurement,
+        tags,
+        fields,
+        timestamp,
+    }))
+}
+
+/// Parses all lines of the given text.
+pub fn parse_lines(text: &str) -> RedisResult<Vec<TsLinePoint>> {
+    let mut points = vec![];
+    for line in text.lines() {
+        if let Some(point) = parse_line(line)? {
+            points.push(point);
+        }
+    }
+    Ok(points)
+}
+
+/// A single time series sample derived from a line protocol field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsInfluxSample {
+    pub key: String,
+    pub labels: Vec<(String, String)>,
+    pub ts: u64,
+    pub value: f64,
+}
+
+/// The outcome of a write. Samples rejected by the server, eg. duplicates under
+/// a BLOCK policy or samples older than the retention, are listed together with
+/// the error message instead of failing the whole write.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TsInfluxReport {
+    pub written: u64,
+    pub rejected: Vec<(TsInfluxSample, String)>,
+}
+
+impl TsInfluxReport {
+    /// Adds the per sample results of a TS.MADD for the given samples.
+    fn add(&mut self, samples: &[TsInfluxSample], results: Vec<Result<u64, String>>) {
+        for (sample, result) in samples.iter().zip(results) {
+            match result {
+                Ok(_) => self.written += 1,
+                Err(reason) => self.rejected.push((sample.clone(), reason)),
+            }
+        }
+    }
+}
+
+fn batch(samples: &[TsInfluxSample]) -> Vec<(&str, u64, f64)> {
+    samples
+        .iter()
+        .map(|s| (s.key.as_str(), s.ts, s.value))
+        .collect()
+}
+
+/// Writes line protocol data into redis time series. The key template may contain
+/// the placeholders `{measurement}` and `{field}`; every other `{name}` is replaced
+/// by the value of the tag with that name (or an empty string if it is missing).
+/// The sink remembers created keys so that TS.CREATE is only sent once per key.
+#[derive(Debug, Clone)]
+pub struct TsInfluxSink {
+    template: String,
+    precision: TsPrecision,
+    options: TsOptions,
+    batch_size: usize,
+    measurement_labels: bool,
+    created: HashSet<String>,
+}
+
+impl TsInfluxSink {
+    /// Creates a new sink with the given key template.
+    pub fn new(template: &str) -> Self {
+        TsInfluxSink {
+            template: template.to_string(),
+            precision: TsPrecision::Nanoseconds,
+            options: TsOptions::default(),
+            batch_size: 1000,
+            measurement_labels: true,
+            created: HashSet::new(),
+        }
+    }
+
+    /// The precision of the incoming timestamps. Defaults to nanoseconds.
+    pub fn precision(mut self, precision: TsPrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// The options used to create new keys. Tags are added as labels.
+    pub fn options(mut self, options: TsOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The max amount of samples sent in a single TS.MADD. Defaults to 1000.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// Adds the labels `measurement` and `field` to created keys unless a tag
+    /// with the same name exists. Enabled by default.
+    pub fn measurement_labels(mut self, value: bool) -> Self {
+        self.measurement_labels = value;
+        self
+    }
+
+    /// Renders the key for the given point and field.
+    pub fn key(&self, point: &TsLinePoint, field: &str) -> String {
+        let mut key = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+            key.push_str(&rest[..start]);
+            match &rest[start + 1..end] {
+                "measurement" => key.push_str(&point.measurement),
+                "field" => key.push_str(field),
+                tag => {
+                    if let Some((_, v)) = point.tags.iter().find(|(name, _)| name == tag) {
+                        key.push_str(v);
+                    }
+                }
+            }
+            rest = &rest[end + 1..];
+        }
+        key.push_str(rest);
+        key
+    }
+
+    /// Converts parsed points into samples. Points without timestamp use the
+    /// current system time.
+    pub fn samples(&self, points: &[TsLinePoint]) -> Vec<TsInfluxSample> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        points
+            .iter()
+            .flat_map(|point| {
+                let ts = point
+                    .timestamp
+                    .map(|ts| self.precision.to_millis(ts))
+                    .unwrap_or(now);
+                point.fields.iter().map(move |(field, value)| {
+                    let mut labels = point.tags.clone();
+                    if self.measurement_labels {
+                        for (name, v) in [("measurement", &point.measurement), ("field", field)] {
+                            if !labels.iter().any(|(n, _)| n == name) {
+                                labels.push((name.to_string(), v.to_string()));
+                            }
+                        }
+                    }
+                    TsInfluxSample {
+                        key: self.key(point, field),
+                        labels,
+                        ts,
+                        value: *value,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn create_options(&self, labels: &[(String, String)]) -> TsOptions {
+        labels
+            .iter()
+            .fold(self.options.clone(), |o, (name, value)| {
+                o.label(name, value)
+            })
+    }
+
+    /// Parses the given line protocol text and writes all samples. Returns the
+    /// number of written samples and the samples rejected by the server.
+    pub fn write<C: TsCommands>(
+        &mut self,
+        con: &mut C,
+        lines: &str,
+    ) -> RedisResult<TsInfluxReport> {
+        let samples = self.samples(&parse_lines(lines)?);
+        let mut report = TsInfluxReport::default();
+        for chunk in samples.chunks(self.batch_size) {
+            for sample in chunk {
+                if !self.created.contains(&sample.key) {
+                    create_if_missing(con, &sample.key, self.create_options(&sample.labels))?;
+                    self.created.insert(sample.key.clone());
+                }
+            }
+            report.add(chunk, madd(con, &batch(chunk))?);
+        }
+        Ok(report)
+    }
+
+    /// Async version of write.
+    #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+    pub async fn write_async<C: crate::AsyncTsCommands>(
+        &mut self,
+        con: &mut C,
+        lines: &str,
+    ) -> RedisResult<TsInfluxReport> {
+        let samples = self.samples(&parse_lines(lines)?);
+        let mut report = TsInfluxReport::default();
+        for chunk in samples.chunks(self.batch_size) {
+            for sample in chunk {
+                if !self.created.contains(&sample.key) {
+                    let options = self.create_options(&sample.labels);
+                    crate::io::create_if_missing_async(con, &sample.key, options).await?;
+                    self.created.insert(sample.key.clone());
+                }
+            }
+            report.add(chunk, crate::io::madd_async(con, &batch(chunk)).await?);
+        }
+        Ok(report)
+    }
+}
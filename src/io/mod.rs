@@ -0,0 +1,227 @@
+//! Adapters to import time series data into redis and export query results
+//! into other formats.
+use crate::commands::TsCommands;
+use crate::types::{TsAggregationType, TsFilterOptions, TsOptions};
+use redis::{ErrorKind, FromRedisValue, RedisError, RedisResult, ToRedisArgs, Value};
+
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod exposition;
+#[cfg(feature = "grafana")]
+pub mod grafana;
+pub mod influx;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+
+/// Returns a stable 64 bit FNV-1a hash of a label set. Labels are sorted by name
+/// and value first, so the order of the given labels does not matter.
+pub fn label_set_hash(labels: &[(String, String)]) -> u64 {
+    let mut sorted: Vec<&(String, String)> = labels.iter().collect();
+    sorted.sort();
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (name, value) in sorted {
+        for byte in name.bytes().chain([0]).chain(value.bytes()).chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+/// Returns the aggregation with the given name, eg. `avg` or `std.p`.
+pub fn aggregation(name: &str, bucket: u64) -> Option<TsAggregationType> {
+    Some(match name {
+        "avg" => TsAggregationType::Avg(bucket),
+        "sum" => TsAggregationType::Sum(bucket),
+        "min" => TsAggregationType::Min(bucket),
+        "max" => TsAggregationType::Max(bucket),
+        "range" => TsAggregationType::Range(bucket),
+        "count" => TsAggregationType::Count(bucket),
+        "first" => TsAggregationType::First(bucket),
+        "last" => TsAggregationType::Last(bucket),
+        "std.p" => TsAggregationType::StdP(bucket),
+        "std.s" => TsAggregationType::StdS(bucket),
+        "var.p" => TsAggregationType::VarP(bucket),
+        "var.s" => TsAggregationType::VarS(bucket),
+        "twa" => TsAggregationType::Twa(bucket),
+        _ => return None,
+    })
+}
+
+fn set_values(value: &str) -> Option<Vec<&str>> {
+    value
+        .strip_prefix('(')
+        .and_then(|v| v.strip_suffix(')'))
+        .map(|v| v.split(',').map(|s| s.trim()).collect())
+}
+
+/// Adds all filters of a whitespace separated filter expression in TS.MRANGE
+/// syntax to the options, eg. `sensor=temperature room!=(attic,cellar) floor=`.
+pub fn parse_filter(options: TsFilterOptions, expression: &str) -> RedisResult<TsFilterOptions> {
+    let mut options = options;
+    for token in expression.split_whitespace() {
+        let invalid = || {
+            RedisError::from(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid filter {token}"),
+            ))
+        };
+        let (name, value, negate) = match token.split_once("!=") {
+            Some((name, value)) => (name, value, true),
+            None => token
+                .split_once('=')
+                .map(|(name, value)| (name, value, false))
+                .ok_or_else(invalid)?,
+        };
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        options = match (negate, value, set_values(value)) {
+            (false, "", _) => options.not_has_label(name),
+            (true, "", _) => options.has_label(name),
+            (false, _, Some(set)) => options.in_set(name, set),
+            (true, _, Some(set)) => options.not_in_set(name, set),
+            (false, value, None) => options.equals(name, value),
+            (true, value, None) => options.not_equals(name, value),
+        };
+    }
+    Ok(options)
+}
+
+/// Returns true if the error was caused by creating a key that already exists.
+fn is_existing_key(e: &RedisError) -> bool {
+    e.kind() == ErrorKind::ResponseError && e.to_string().contains("already exists")
+}
+
+/// Creates the given key with options unless it already exists.
+pub(crate) fn create_if_missing<C: TsCommands>(
+    con: &mut C,
+    key: &str,
+    options: TsOptions,
+) -> RedisResult<()> {
+    match con.ts_create::<_, ()>(key, options) {
+        Err(e) if !is_existing_key(&e) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Async version of create_if_missing.
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+pub(crate) async fn create_if_missing_async<C: crate::AsyncTsCommands>(
+    con: &mut C,
+    key: &str,
+    options: TsOptions,
+) -> RedisResult<()> {
+    match con.ts_create::<_, ()>(key, options).await {
+        Err(e) if !is_existing_key(&e) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn madd_cmd<K: ToRedisArgs>(samples: &[(K, u64, f64)]) -> redis::Cmd {
+    let mut cmd = redis::cmd("TS.MADD");
+    cmd.arg(samples);
+    cmd
+}
+
+/// Returns the result of every sample of a TS.MADD reply, the timestamp of a
+/// written sample or the error message of a rejected one.
+fn madd_results(reply: Value, samples: usize) -> RedisResult<Vec<Result<u64, String>>> {
+    let items = match reply {
+        Value::Array(items) if items.len() == samples => items,
+        Value::ServerError(e) => return Err(e.into()),
+        _ => {
+            return Err(RedisError::from(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "unexpected TS.MADD reply",
+            )))
+        }
+    };
+    items
+        .into_iter()
+        .map(|item| match item {
+            Value::ServerError(e) => Ok(Err(e.details().unwrap_or(e.code()).to_string())),
+            item => Ok(Ok(u64::from_owned_redis_value(item)?)),
+        })
+        .collect()
+}
+
+/// Writes the samples with TS.MADD and returns the result of every sample.
+/// TS.MADD replies an error per rejected sample, which query would turn into
+/// an error of the whole batch.
+pub(crate) fn madd<C: TsCommands, K: ToRedisArgs>(
+    con: &mut C,
+    samples: &[(K, u64, f64)],
+) -> RedisResult<Vec<Result<u64, String>>> {
+    madd_results(con.req_command(&madd_cmd(samples))?, samples.len())
+}
+
+/// Async version of madd.
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+pub(crate) async fn madd_async<C: crate::AsyncTsCommands, K: ToRedisArgs>(
+    con: &mut C,
+    samples: &[(K, u64, f64)],
+) -> RedisResult<Vec<Result<u64, String>>> {
+    let reply = con.req_packed_command(&madd_cmd(samples)).await?;
+    madd_results(reply, samples.len())
+}
+
+#[cfg(any(feature = "csv", feature = "grafana"))]
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(any(feature = "csv", feature = "grafana"))]
+/// Parses date time strings like `2021-03-04T10:00:00.500Z` or with a numeric
+/// offset into unix millis.
+pub(crate) fn parse_rfc3339(value: &str) -> Option<u64> {
+    let b = value.as_bytes();
+    if b.len() < 19 || b[4] != b'-' || b[7] != b'-' || b[13] != b':' || b[16] != b':' {
+        return None;
+    }
+    if !matches!(b[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| value.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+
+    let mut rest = &value[19..];
+    let mut millis = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        let padded = format!("{:0<3}", &fraction[..digits.min(3)]);
+        millis = padded.parse::<i64>().ok()?;
+        rest = &fraction[digits..];
+    }
+
+    let offset = match rest {
+        "Z" | "z" | "" => 0,
+        _ => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let (h, m) = rest[1..].split_once(':')?;
+            sign * (h.parse::<i64>().ok()? * 60 + m.parse::<i64>().ok()?)
+        }
+    };
+
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second
+        - offset * 60;
+    let ts = seconds * 1000 + millis;
+    if ts < 0 {
+        None
+    } else {
+        Some(ts as u64)
+    }
+}
@@ -0,0 +1,453 @@
+//! Prometheus remote storage backed by redis time series.
+//!
+//! Remote-write requests (snappy compressed protobuf) are decoded into TS.MADD
+//! calls. Every label set becomes a series whose key is derived from a hash of
+//! the sorted labels; the labels themselves are stored as series labels.
+//! Remote-read requests are answered by translating their label matchers into
+//! TsFilterOptions and running TS.MRANGE. Matchers that can not be expressed
+//! as a label filter, like most regexes or values containing `,`, `(` or `)`,
+//! are applied client side.
+//!
+//! All series written by a storage carry the label `__redis_ts_storage__` with
+//! the storage prefix as value. It scopes all reads and is removed from read
+//! responses.
+//!
+//! Prometheus retries failed writes by resending the whole request, so series
+//! should be created with a duplicate policy that accepts samples already
+//! stored, like LAST. Samples rejected by the server are counted in the write
+//! report and do not fail the request.
+//!
+//! ```rust,no_run
+//! # fn run(write_body: &[u8], read_body: &[u8]) -> redis::RedisResult<()> {
+//! use redis_ts::io::prometheus::TsPrometheusStorage;
+//! use redis_ts::{TsDuplicatePolicy, TsOptions};
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/")?;
+//! let mut con = client.get_connection()?;
+//!
+//! let mut storage = TsPrometheusStorage::new("prom").options(
+//!     TsOptions::default()
+//!         .retention_time(30 * 86400000)
+//!         .duplicate_policy(TsDuplicatePolicy::Last),
+//! );
+//!
+//! let report = storage.write(&mut con, write_body)?;
+//! let response: Vec<u8> = storage.read(&mut con, read_body)?;
+//! # Ok(()) }
+//! ```
+//!
+use crate::commands::TsCommands;
+use crate::io::{create_if_missing, label_set_hash, madd};
+use crate::promql::{exact_set, filter_value};
+use crate::types::*;
+use prost::Message;
+use redis::{RedisError, RedisResult};
+use regex::Regex;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+/// The label that marks all series written by a storage.
+pub const STORAGE_LABEL: &str = "__redis_ts_storage__";
+
+/// A remote-write request.
+#[derive(Clone, PartialEq, Message)]
+pub struct WriteRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub timeseries: Vec<TimeSeries>,
+}
+
+/// A series with its labels and samples.
+#[derive(Clone, PartialEq, Message)]
+pub struct TimeSeries {
+    #[prost(message, repeated, tag = "1")]
+    pub labels: Vec<Label>,
+    #[prost(message, repeated, tag = "2")]
+    pub samples: Vec<Sample>,
+}
+
+/// A single label of a series.
+#[derive(Clone, PartialEq, Message)]
+pub struct Label {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub value: String,
+}
+
+/// A single sample of a series with a timestamp in millis.
+#[derive(Clone, PartialEq, Message)]
+pub struct Sample {
+    #[prost(double, tag = "1")]
+    pub value: f64,
+    #[prost(int64, tag = "2")]
+    pub timestamp: i64,
+}
+
+/// A remote-read request.
+#[derive(Clone, PartialEq, Message)]
+pub struct ReadRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub queries: Vec<Query>,
+}
+
+/// A single query of a remote-read request.
+#[derive(Clone, PartialEq, Message)]
+pub struct Query {
+    #[prost(int64, tag = "1")]
+    pub start_timestamp_ms: i64,
+    #[prost(int64, tag = "2")]
+    pub end_timestamp_ms: i64,
+    #[prost(message, repeated, tag = "3")]
+    pub matchers: Vec<LabelMatcher>,
+}
+
+/// The comparison of a label matcher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum MatcherType {
+    Eq = 0,
+    Neq = 1,
+    Re = 2,
+    Nre = 3,
+}
+
+/// A label matcher of a remote-read query.
+#[derive(Clone, PartialEq, Message)]
+pub struct LabelMatcher {
+    #[prost(enumeration = "MatcherType", tag = "1")]
+    pub r#type: i32,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, tag = "3")]
+    pub value: String,
+}
+
+/// A remote-read response with one result per query.
+#[derive(Clone, PartialEq, Message)]
+pub struct ReadResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub results: Vec<QueryResult>,
+}
+
+/// The series matching a single remote-read query.
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryResult {
+    #[prost(message, repeated, tag = "1")]
+    pub timeseries: Vec<TimeSeries>,
+}
+
+fn payload_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> RedisError {
+    RedisError::from(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+fn decompress(body: &[u8]) -> RedisResult<Vec<u8>> {
+    snap::raw::Decoder::new()
+        .decompress_vec(body)
+        .map_err(payload_error)
+}
+
+/// Decodes a snappy compressed remote-write request.
+pub fn decode_write_request(body: &[u8]) -> RedisResult<WriteRequest> {
+    WriteRequest::decode(decompress(body)?.as_slice()).map_err(payload_error)
+}
+
+/// Decodes a snappy compressed remote-read request.
+pub fn decode_read_request(body: &[u8]) -> RedisResult<ReadRequest> {
+    ReadRequest::decode(decompress(body)?.as_slice()).map_err(payload_error)
+}
+
+/// Encodes and snappy compresses a remote-read response.
+pub fn encode_read_response(response: &ReadResponse) -> RedisResult<Vec<u8>> {
+    snap::raw::Encoder::new()
+        .compress_vec(&response.encode_to_vec())
+        .map_err(payload_error)
+}
+
+/// A matcher that has to be applied to the labels of the returned series.
+#[derive(Debug, Clone)]
+pub struct TsClientMatcher {
+    name: String,
+    regex: Regex,
+    negate: bool,
+}
+
+impl TsClientMatcher {
+    /// Returns true if the given labels satisfy this matcher. Missing labels are
+    /// treated as empty values.
+    pub fn matches(&self, labels: &[(String, String)]) -> bool {
+        let value = labels
+            .iter()
+            .find(|(n, _)| *n == self.name)
+            .map(|(_, v)| v.as_str())
+            .unwrap_or_default();
+        self.regex.is_match(value) != self.negate
+    }
+}
+
+/// Translates remote-read label matchers into a filter. Matchers that can not
+/// be expressed as a filter are returned as client side matchers.
+pub fn translate_matchers(
+    prefix: &str,
+    matchers: &[LabelMatcher],
+) -> RedisResult<(TsFilterOptions, Vec<TsClientMatcher>)> {
+    let mut filter = TsFilterOptions::default()
+        .with_labels(true)
+        .equals(STORAGE_LABEL, prefix);
+    let mut client = vec![];
+
+    for m in matchers {
+        let matcher_type = MatcherType::try_from(m.r#type).map_err(payload_error)?;
+        let name = m.name.as_str();
+        let value = m.value.as_str();
+        filter = match (matcher_type, value) {
+            (MatcherType::Eq, "") => filter.not_has_label(name),
+            (MatcherType::Eq, v) if filter_value(v) => filter.equals(name, v),
+            (MatcherType::Neq, "") => filter.has_label(name),
+            (MatcherType::Neq, v) if filter_value(v) => filter.not_equals(name, v),
+            (MatcherType::Re, v) if exact_set(v).is_some() => {
+                filter.in_set(name, exact_set(v).unwrap_or_default())
+            }
+            (MatcherType::Nre, v) if exact_set(v).is_some() => {
+                filter.not_in_set(name, exact_set(v).unwrap_or_default())
+            }
+            (t, v) => {
+                let pattern = match t {
+                    MatcherType::Eq | MatcherType::Neq => regex::escape(v),
+                    _ => v.to_string(),
+                };
+                client.push(TsClientMatcher {
+                    name: name.to_string(),
+                    regex: Regex::new(&format!("^(?:{pattern})$")).map_err(payload_error)?,
+                    negate: matches!(t, MatcherType::Neq | MatcherType::Nre),
+                });
+                filter
+            }
+        };
+    }
+    Ok((filter, client))
+}
+
+fn to_labels(labels: &[(String, String)]) -> Vec<Label> {
+    let mut result: Vec<Label> = labels
+        .iter()
+        .filter(|(name, _)| name != STORAGE_LABEL)
+        .map(|(name, value)| Label {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect();
+    result.sort_by(|a, b| a.name.cmp(&b.name));
+    result
+}
+
+fn query_result(mrange: TsMrange<u64, f64>, client: &[TsClientMatcher]) -> QueryResult {
+    QueryResult {
+        timeseries: mrange
+            .values
+            .into_iter()
+            .filter(|e| client.iter().all(|m| m.matches(&e.labels)))
+            .map(|e| TimeSeries {
+                labels: to_labels(&e.labels),
+                samples: e
+                    .values
+                    .into_iter()
+                    .map(|(ts, value)| Sample {
+                        value,
+                        timestamp: ts as i64,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+/// A series key with its create options and samples.
+type SeriesSamples = (String, TsOptions, Vec<(u64, f64)>);
+
+fn range_query(query: &Query) -> TsRangeQuery {
+    TsRangeQuery::default()
+        .from(query.start_timestamp_ms.max(0))
+        .to(query.end_timestamp_ms.max(0))
+}
+
+/// The outcome of a remote-write. Samples rejected by the server, eg. duplicates
+/// under a BLOCK policy or samples older than the retention, are counted
+/// instead of failing the whole request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TsPrometheusWriteReport {
+    pub written: u64,
+    pub rejected: u64,
+}
+
+impl TsPrometheusWriteReport {
+    fn add(&mut self, results: Vec<Result<u64, String>>) {
+        for result in results {
+            match result {
+                Ok(_) => self.written += 1,
+                Err(_) => self.rejected += 1,
+            }
+        }
+    }
+}
+
+/// Stores Prometheus series in redis time series. Keys are created with TS.CREATE
+/// the first time the storage sees them.
+///
+/// Prometheus resends a whole request when it retries a write, so samples that
+/// are already stored arrive again. Keys should be created with a duplicate
+/// policy like LAST or FIRST, which accepts these samples. Under the BLOCK
+/// policy (the server default) they are reported as rejected.
+#[derive(Debug, Clone)]
+pub struct TsPrometheusStorage {
+    prefix: String,
+    options: TsOptions,
+    batch_size: usize,
+    created: HashSet<String>,
+}
+
+impl TsPrometheusStorage {
+    /// Creates a new storage. The prefix is used for all keys and scopes reads.
+    pub fn new(prefix: &str) -> Self {
+        TsPrometheusStorage {
+            prefix: prefix.to_string(),
+            options: TsOptions::default(),
+            batch_size: 1000,
+            created: HashSet::new(),
+        }
+    }
+
+    /// The options used to create new keys. Series labels are added. Set a
+    /// duplicate policy that accepts resent samples, eg. LAST.
+    pub fn options(mut self, options: TsOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The max amount of samples sent in a single TS.MADD. Defaults to 1000.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// Returns the key of the series with the given labels.
+    pub fn series_key(&self, labels: &[(String, String)]) -> String {
+        format!("{}:{:016x}", self.prefix, label_set_hash(labels))
+    }
+
+    /// Flattens a write request into keys with create options and samples.
+    /// Samples with negative timestamps and NaN values (eg. staleness markers)
+    /// are skipped.
+    fn samples(&self, request: WriteRequest) -> Vec<SeriesSamples> {
+        request
+            .timeseries
+            .into_iter()
+            .map(|series| {
+                let labels: Vec<(String, String)> = series
+                    .labels
+                    .into_iter()
+                    .map(|l| (l.name, l.value))
+                    .collect();
+                let options = labels
+                    .iter()
+                    .fold(self.options.clone(), |o, (name, value)| {
+                        o.label(name, value)
+                    })
+                    .label(STORAGE_LABEL, &self.prefix);
+                let samples = series
+                    .samples
+                    .into_iter()
+                    .filter(|s| s.timestamp >= 0 && !s.value.is_nan())
+                    .map(|s| (s.timestamp as u64, s.value))
+                    .collect();
+                (self.series_key(&labels), options, samples)
+            })
+            .collect()
+    }
+
+    /// Writes a snappy compressed remote-write request. Returns the number of
+    /// written samples and of the samples rejected by the server. Rejected
+    /// samples do not fail the request, so Prometheus does not retry it.
+    pub fn write<C: TsCommands>(
+        &mut self,
+        con: &mut C,
+        body: &[u8],
+    ) -> RedisResult<TsPrometheusWriteReport> {
+        let mut batch: Vec<(String, u64, f64)> = vec![];
+        let mut report = TsPrometheusWriteReport::default();
+        for (key, options, samples) in self.samples(decode_write_request(body)?) {
+            if !self.created.contains(&key) {
+                create_if_missing(con, &key, options)?;
+                self.created.insert(key.clone());
+            }
+            for (ts, value) in samples {
+                batch.push((key.clone(), ts, value));
+                if batch.len() >= self.batch_size {
+                    report.add(madd(con, &batch)?);
+                    batch.clear();
+                }
+            }
+        }
+        if !batch.is_empty() {
+            report.add(madd(con, &batch)?);
+        }
+        Ok(report)
+    }
+
+    /// Answers a snappy compressed remote-read request with a snappy compressed
+    /// response.
+    pub fn read<C: TsCommands>(&self, con: &mut C, body: &[u8]) -> RedisResult<Vec<u8>> {
+        let request = decode_read_request(body)?;
+        let mut response = ReadResponse { results: vec![] };
+        for query in request.queries.iter() {
+            let (filter, client) = translate_matchers(&self.prefix, &query.matchers)?;
+            let mrange: TsMrange<u64, f64> = con.ts_mrange(range_query(query), filter)?;
+            response.results.push(query_result(mrange, &client));
+        }
+        encode_read_response(&response)
+    }
+
+    /// Async version of write.
+    #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+    pub async fn write_async<C: crate::AsyncTsCommands>(
+        &mut self,
+        con: &mut C,
+        body: &[u8],
+    ) -> RedisResult<TsPrometheusWriteReport> {
+        let mut batch: Vec<(String, u64, f64)> = vec![];
+        let mut report = TsPrometheusWriteReport::default();
+        for (key, options, samples) in self.samples(decode_write_request(body)?) {
+            if !self.created.contains(&key) {
+                crate::io::create_if_missing_async(con, &key, options).await?;
+                self.created.insert(key.clone());
+            }
+            for (ts, value) in samples {
+                batch.push((key.clone(), ts, value));
+                if batch.len() >= self.batch_size {
+                    report.add(crate::io::madd_async(con, &batch).await?);
+                    batch.clear();
+                }
+            }
+        }
+        if !batch.is_empty() {
+            report.add(crate::io::madd_async(con, &batch).await?);
+        }
+        Ok(report)
+    }
+
+    /// Async version of read.
+    #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+    pub async fn read_async<C: crate::AsyncTsCommands>(
+        &self,
+        con: &mut C,
+        body: &[u8],
+    ) -> RedisResult<Vec<u8>> {
+        let request = decode_read_request(body)?;
+        let mut response = ReadResponse { results: vec![] };
+        for query in request.queries.iter() {
+            let (filter, client) = translate_matchers(&self.prefix, &query.matchers)?;
+            let mrange: TsMrange<u64, f64> = con.ts_mrange(range_query(query), filter).await?;
+            response.results.push(query_result(mrange, &client));
+        }
+        encode_read_response(&response)
+    }
+}
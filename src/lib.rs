@@ -5,12 +5,12 @@
 //! series commands are available as synchronous and asynchronous versions.
 //!
 //! The crate is called `redis_ts` and you can depend on it via cargo. You will
-//! also need redis in your dependencies. It has been tested against redis 0.25.2
-//! but should work with versions higher than that.
+//! also need redis in your dependencies. It has been tested against redis 0.32
+//! but should work with all 0.x versions higher than that.
 //!
 //! ```ini
 //! [dependencies]
-//! redis = "0.25.2"
+//! redis = "0.32"
 //! redis_ts = "0.5.4"
 //! ```
 //!
@@ -25,7 +25,7 @@
 //! crate (either: 'async-std-comp' or 'tokio-comp):
 //! ```ini
 //! [dependencies]
-//! redis = "0.25.2"
+//! redis = "0.32"
 //! redis_ts = { version = "0.5.4", features = ['tokio-comp'] }
 //! ```
 //!
@@ -62,12 +62,76 @@
 //! use redis_ts::{AsyncTsCommands, TsOptions};
 //!
 //! let client = redis::Client::open("redis://127.0.0.1/")?;
-//! let mut con = client.get_async_connection().await?;
+//! let mut con = client.get_multiplexed_async_connection().await?;
 //!
 //! let _:() = con.ts_create("my_ts", TsOptions::default()).await?;
 //! # Ok(()) }
 //! ```
 //!
+//! The async commands are available on every async ConnectionLike, eg. a
+//! MultiplexedConnection or, with the 'connection-manager' feature, a
+//! reconnecting ConnectionManager that can be cloned into many tasks.
+//!
+//! To react on new samples, [ts_follow] and [ts_mfollow] poll one or many
+//! series and return a stream that yields every new sample once. Without
+//! polling, [ts_changes] turns keyspace notifications into a stream of changes.
+//!
+//! # Import and export
+//!
+//! The [io] module contains adapters to get data in and out of redis time
+//! series, eg. [io::influx] to ingest InfluxDB line protocol as sent by
+//! Telegraf style agents or [io::exposition] to render the latest values in
+//! Prometheus text format for scraping.
+//!
+//! # PromQL
+//!
+//! The [promql] module evaluates a subset of PromQL (selectors, `rate`,
+//! `avg_over_time`, `max_over_time` and `sum by`) with TS.MRANGE, pushing
+//! aggregations and grouping down to redis where possible.
+//!
+//! # Alerting
+//!
+//! The [alert] module evaluates threshold rules over aggregated windows of all
+//! series matching a filter and reports pending, firing and resolved series to
+//! a notifier.
+//!
+//! # Schemas
+//!
+//! The [schema] module defines key templates, allowed labels, retention and
+//! compaction tiers of series families and validates writes and queries
+//! against them.
+//!
+//! # RESP3
+//!
+//! All replies are decoded from RESP2 and RESP3, so connections may negotiate
+//! RESP3, eg. with `redis://127.0.0.1/?protocol=resp3`. Under RESP3 TS.INFO,
+//! TS.MGET and TS.MRANGE reply maps and values are native doubles.
+//!
+//! # Optional features
+//!
+//! - 'arrow': Converts range, multi range and mget results into Arrow record
+//!   batches via the [arrow] module.
+//! - 'csv': Exports range results to CSV and imports CSV data via the
+//!   [io::csv] module.
+//! - 'prometheus': Prometheus remote-write and remote-read support via the
+//!   [io::prometheus] module.
+//! - 'opentelemetry': An OpenTelemetry metrics exporter writing into redis time
+//!   series through an async connection via the [otel] module (implies
+//!   'tokio-comp').
+//! - 'grafana': A handler for the Grafana JSON datasource protocol via the
+//!   [io::grafana] module.
+//! - 'cli': The `redis-ts` command line tool to inspect series, manage rules and
+//!   import or export data (`cargo install redis_ts --features cli`).
+//! - 'connection-manager': Enables the redis ConnectionManager (implies
+//!   'tokio-comp').
+//! - 'derive': `#[derive(TsSeries)]` to derive a [schema::TsSeries] from a
+//!   struct holding label values and `#[derive(TsRecord)]` to write structs
+//!   as samples and read them back, see [TsRecord].
+//! ```ini
+//! [dependencies]
+//! redis_ts = { version = "0.5.4", features = ['arrow', 'csv'] }
+//! ```
+//!
 //! # Supported commands
 //!
 //! The following examples work with the synchronous and asynchronous
@@ -239,7 +303,7 @@
 //! ```rust,no_run
 //! fn run() -> redis::RedisResult<()> {
 //! # use redis::Commands;
-//! # use redis_ts::{TsCommands, TsRange, TsAggregationType, TsRangeQuery};
+//! # use redis_ts::{TsCommands, TsRange, TsAggregationType, TsRangeQuery, TsValue};
 //! # let client = redis::Client::open("redis://127.0.0.1/")?;
 //! # let mut con = client.get_connection()?;
 //! let first_three_avg:TsRange<u64,f64> = con.ts_range(
@@ -258,9 +322,21 @@
 //!     "my_engine",
 //!     TsRangeQuery::default().from(1234).to(5678)
 //! )?;
+//!
+//! // decodes nan and inf of empty buckets or std aggregations
+//! let stddev:TsRange<u64,TsValue> = con.ts_range(
+//!     "my_engine",
+//!     TsRangeQuery::default().aggregation_type(TsAggregationType::StdS(5000))
+//! )?;
 //! # Ok(()) }
 //! ```
 //!
+//! Values are not required to be `Copy`, eg. `TsRange<u64,String>` returns
+//! the values as replied by the server.
+//!
+//! Large ranges can be decoded straight into a timestamp and a value column
+//! with `ts_range_columns` and `ts_mrange_columns`, see TsColumns.
+//!
 //! ## TS.MRANGE/TS.MREVRANGE
 //! Batch query multiple ranges of time series data.
 //!
@@ -287,6 +363,129 @@
 //! # Ok(()) }
 //! ```
 //!
+//! ## Candles
+//! Query open/high/low/close candles of one or multiple time series. Candles are
+//! built from server side FIRST, MAX, MIN, LAST and COUNT aggregations with a
+//! shared bucket alignment.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! # use redis::Commands;
+//! # use redis_ts::{TsCommands, TsCandle, TsMcandles, TsFilterOptions};
+//! # let client = redis::Client::open("redis://127.0.0.1/")?;
+//! # let mut con = client.get_connection()?;
+//! let minute_bars:Vec<TsCandle> = con.ts_candles("my_price", 0, 3600000, 60000)?;
+//!
+//! let all_bars:TsMcandles = con.ts_mcandles(
+//!     0,
+//!     3600000,
+//!     60000,
+//!     TsFilterOptions::default().equals("asset", "stock")
+//! )?;
+//! # Ok(()) }
+//! ```
+//!
+//! ## Rates of counters
+//! Counters (eg. written with TS.INCRBY) can be turned into rates client side.
+//! rate, irate, increase and delta follow the PromQL semantics including counter
+//! reset detection and extrapolation to the window boundaries.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! # use redis::Commands;
+//! # use redis_ts::{TsCommands, TsRange, TsRangeQuery};
+//! # let client = redis::Client::open("redis://127.0.0.1/")?;
+//! # let mut con = client.get_connection()?;
+//! let requests_per_second:TsRange<u64,f64> = con.ts_rate(
+//!     "my_requests",
+//!     TsRangeQuery::default(),
+//!     60000
+//! )?;
+//!
+//! let range:TsRange<u64,f64> = con.ts_range("my_requests", TsRangeQuery::default())?;
+//! let hourly_increase = range.increase(3600000);
+//! # Ok(()) }
+//! ```
+//!
+//! ## Histograms and quantiles
+//! A TsHistogram stores bucketed observation counts as one series per upper
+//! bound (`le` label). Quantiles like p50 or p99 are estimated from a single
+//! TS.MRANGE with `GROUPBY le REDUCE sum`, so histograms of multiple hosts can
+//! share a metric.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! # use redis_ts::{TsAggregationType, TsHistogram, TsRange, TsRangeQuery};
+//! # let client = redis::Client::open("redis://127.0.0.1/")?;
+//! # let mut con = client.get_connection()?;
+//! let latency = TsHistogram::new("latency", vec![0.01, 0.1, 1.0]);
+//! latency.create(&mut con)?;
+//! latency.record(&mut con, 1000, &[0.02, 0.07, 0.3])?;
+//! let p99:TsRange<u64,f64> = latency.quantile(
+//!     &mut con,
+//!     TsRangeQuery::default().aggregation_type(TsAggregationType::Sum(60000)),
+//!     0.99
+//! )?;
+//! # Ok(()) }
+//! ```
+//!
+//! ## Gap detection and filling
+//! Range results can report missing samples at a given resolution and fill them
+//! client side. NaN values as returned for EMPTY buckets count as missing.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! # use redis::Commands;
+//! # use redis_ts::{TsCommands, TsRange, TsRangeQuery, TsFillStrategy};
+//! # let client = redis::Client::open("redis://127.0.0.1/")?;
+//! # let mut con = client.get_connection()?;
+//! let range:TsRange<u64,f64> = con.ts_range("my_engine", TsRangeQuery::default())?;
+//! let report = range.gaps(1000)?;
+//! if !report.is_empty() {
+//!     println!("{} samples missing", report.missing_samples());
+//! }
+//! let filled = range.fill(1000, TsFillStrategy::Linear)?;
+//! # Ok(()) }
+//! ```
+//!
+//! ## Anomaly detection
+//! Range results can score their samples against a rolling z-score, median
+//! absolute deviation, EWMA or seasonal (eg. same time last week) baseline and
+//! report annotated samples or intervals of consecutive anomalies.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! # use redis::Commands;
+//! # use redis_ts::{TsCommands, TsRange, TsRangeQuery, TsAnomalyDetector};
+//! # let client = redis::Client::open("redis://127.0.0.1/")?;
+//! # let mut con = client.get_connection()?;
+//! let range:TsRange<u64,f64> = con.ts_range("my_engine", TsRangeQuery::default())?;
+//! let detector = TsAnomalyDetector::Mad { window: 60, threshold: 3.5 };
+//! for interval in range.anomalies(detector) {
+//!     println!("anomaly from {} to {}", interval.start, interval.end);
+//! }
+//! let weekly = range.anomaly_scores(TsAnomalyDetector::weekly(4, 60000, 0.5));
+//! # Ok(()) }
+//! ```
+//!
+//! ## Joining series into frames
+//! Multiple range results can be joined on their timestamps into a columnar
+//! TsFrame with outer, inner or as-of (nearest previous) semantics.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! # use redis::Commands;
+//! # use redis_ts::{TsCommands, TsFrame, TsJoin, TsMrange, TsFilterOptions, TsRangeQuery};
+//! # let client = redis::Client::open("redis://127.0.0.1/")?;
+//! # let mut con = client.get_connection()?;
+//! let temperatures:TsMrange<u64,f64> = con.ts_mrange(
+//!     TsRangeQuery::default(),
+//!     TsFilterOptions::default().equals("sensor", "temperature").with_labels(true)
+//! )?;
+//! let frame = TsFrame::from_mrange(&temperatures, TsJoin::AsOf(5000));
+//! # Ok(()) }
+//! ```
+//!
 //! ## TS.GET
 //! Get the most recent value of a time series.
 //!
@@ -348,15 +547,53 @@ extern crate core;
 #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
 pub use crate::async_commands::AsyncTsCommands;
 
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+pub use crate::changes::{ts_changes, TsChangeEvent, TsChangeKind, TsChangeOptions};
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+pub use crate::follow::{ts_follow, ts_mfollow, TsFollowOptions, TsFollowSample};
+
+pub use crate::anomaly::{TsAnomalyDetector, TsAnomalyInterval, TsAnomalySample};
+pub use crate::candles::{TsCandle, TsMcandles, TsMcandlesEntry};
+pub use crate::columns::{TsColumns, TsColumnsIter, TsColumnsSlice, TsMcolumns, TsMcolumnsEntry};
 pub use crate::commands::TsCommands;
+pub use crate::frame::{TsFrame, TsFrameColumn, TsJoin};
+pub use crate::gaps::{TsFillStrategy, TsGap, TsGapReport};
+pub use crate::histogram::{histogram_quantile, TsHistogram};
+pub use crate::record::TsRecord;
+
+#[cfg(feature = "derive")]
+pub use redis_ts_derive::{TsRecord, TsSeries};
 
 pub use crate::types::{
     TsAggregationType, TsAlign, TsBucketTimestamp, TsDuplicatePolicy, TsFilterOptions, TsInfo,
-    TsMget, TsMrange, TsMrangeEntry, TsOptions, TsRange, TsRangeQuery,
+    TsMget, TsMgetEntry, TsMrange, TsMrangeEntry, TsOptions, TsRange, TsRangeQuery, TsReducer,
+    TsValue,
 };
 
+pub mod alert;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+
 #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
 mod async_commands;
 
+pub mod io;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
+pub mod promql;
+pub mod schema;
+
+mod anomaly;
+mod candles;
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+mod changes;
+mod columns;
 mod commands;
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+mod follow;
+mod frame;
+mod gaps;
+mod histogram;
+mod rate;
+mod record;
 mod types;
@@ -0,0 +1,407 @@
+//! An OpenTelemetry metrics exporter that writes into redis time series.
+//!
+//! Every data point becomes a sample of its own series. Resource and data point
+//! attributes are stored as labels together with the metric name in the
+//! `__name__` label, so exported metrics can be queried with the [crate::promql]
+//! module. Keys are derived from the metric name and a hash of the label set
+//! and are created with TS.CREATE the first time the exporter sees them.
+//!
+//! The exporter writes through an async connection, eg. a MultiplexedConnection,
+//! so exports never block the thread of the caller. The connection is cloned for
+//! every export.
+//!
+//! - Gauges and sums are written as is with the temporality of the exporter.
+//! - Histograms are written as cumulative `{name}_bucket` series with an `le`
+//!   label per bucket bound, plus `{name}_sum` and `{name}_count`.
+//! - Exponential histograms only write `{name}_sum` and `{name}_count`.
+//!
+//! ```rust,no_run
+//! # async fn run() -> redis::RedisResult<()> {
+//! use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+//! use redis_ts::otel::TsMetricExporter;
+//! use redis_ts::{TsAggregationType, TsOptions};
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/")?;
+//! let exporter = TsMetricExporter::new(client.get_multiplexed_async_connection().await?, "otel")
+//!     .options(TsOptions::default().retention_time(86400000))
+//!     .instrument_options("http.server.duration", TsOptions::default().retention_time(3600000))
+//!     .compaction("http.server.active_requests", "1m", TsAggregationType::Avg(60000), TsOptions::default());
+//!
+//! let provider = SdkMeterProvider::builder()
+//!     .with_reader(PeriodicReader::builder(exporter).build())
+//!     .build();
+//! # Ok(()) }
+//! ```
+//!
+use crate::async_commands::AsyncTsCommands;
+use crate::io::{create_if_missing_async, label_set_hash};
+use crate::types::*;
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData, ResourceMetrics};
+use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
+use opentelemetry_sdk::metrics::Temporality;
+use redis::RedisResult;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The label holding the metric name of a series.
+pub const METRIC_LABEL: &str = "__name__";
+
+/// A single sample derived from an OpenTelemetry data point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsOtelSample {
+    pub name: String,
+    pub key: String,
+    pub labels: Vec<(String, String)>,
+    pub ts: u64,
+    pub value: f64,
+}
+
+trait AsF64: Copy {
+    fn as_f64(self) -> f64;
+}
+
+impl AsF64 for f64 {
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
+
+impl AsF64 for u64 {
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl AsF64 for i64 {
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Builds samples of a single metric and derives their keys.
+struct SampleBuilder<'a> {
+    prefix: &'a str,
+    resource: &'a [(String, String)],
+    samples: Vec<TsOtelSample>,
+}
+
+impl SampleBuilder<'_> {
+    fn push<'k, I>(
+        &mut self,
+        name: &str,
+        attributes: I,
+        extra: Option<(&str, String)>,
+        ts: u64,
+        value: f64,
+    ) where
+        I: Iterator<Item = &'k opentelemetry::KeyValue>,
+    {
+        let mut labels = self.resource.to_vec();
+        labels.extend(attributes.map(|kv| (kv.key.to_string(), kv.value.to_string())));
+        if let Some((label, value)) = extra {
+            labels.push((label.to_string(), value));
+        }
+        labels.push((METRIC_LABEL.to_string(), name.to_string()));
+        self.samples.push(TsOtelSample {
+            name: name.to_string(),
+            key: format!("{}:{}:{:016x}", self.prefix, name, label_set_hash(&labels)),
+            labels,
+            ts,
+            value,
+        });
+    }
+
+    fn metric<T: AsF64>(&mut self, name: &str, data: &MetricData<T>) {
+        match data {
+            MetricData::Gauge(gauge) => {
+                let ts = millis(gauge.time());
+                for point in gauge.data_points() {
+                    self.push(name, point.attributes(), None, ts, point.value().as_f64());
+                }
+            }
+            MetricData::Sum(sum) => {
+                let ts = millis(sum.time());
+                for point in sum.data_points() {
+                    self.push(name, point.attributes(), None, ts, point.value().as_f64());
+                }
+            }
+            MetricData::Histogram(histogram) => {
+                let ts = millis(histogram.time());
+                let bucket = format!("{name}_bucket");
+                for point in histogram.data_points() {
+                    let mut cumulative = 0;
+                    let bounds = point.bounds().map(|b| format!("{b}"));
+                    let bounds = bounds.chain(std::iter::once("+Inf".to_string()));
+                    for (le, count) in bounds.zip(point.bucket_counts()) {
+                        cumulative += count;
+                        self.push(
+                            &bucket,
+                            point.attributes(),
+                            Some(("le", le)),
+                            ts,
+                            cumulative as f64,
+                        );
+                    }
+                    let sum = point.sum().as_f64();
+                    self.push(&format!("{name}_sum"), point.attributes(), None, ts, sum);
+                    let count = point.count() as f64;
+                    self.push(
+                        &format!("{name}_count"),
+                        point.attributes(),
+                        None,
+                        ts,
+                        count,
+                    );
+                }
+            }
+            MetricData::ExponentialHistogram(histogram) => {
+                let ts = millis(histogram.time());
+                for point in histogram.data_points() {
+                    let sum = point.sum().as_f64();
+                    self.push(&format!("{name}_sum"), point.attributes(), None, ts, sum);
+                    let count = point.count() as f64;
+                    self.push(
+                        &format!("{name}_count"),
+                        point.attributes(),
+                        None,
+                        ts,
+                        count,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Converts collected metrics into samples. Keys are `{prefix}:{name}:{hash}`
+/// where hash is the label_set_hash of all labels of the series.
+pub fn metric_samples(prefix: &str, metrics: &ResourceMetrics) -> Vec<TsOtelSample> {
+    let resource: Vec<(String, String)> = metrics
+        .resource()
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let mut builder = SampleBuilder {
+        prefix,
+        resource: &resource,
+        samples: vec![],
+    };
+    for scope in metrics.scope_metrics() {
+        for metric in scope.metrics() {
+            match metric.data() {
+                AggregatedMetrics::F64(data) => builder.metric(metric.name(), data),
+                AggregatedMetrics::U64(data) => builder.metric(metric.name(), data),
+                AggregatedMetrics::I64(data) => builder.metric(metric.name(), data),
+            }
+        }
+    }
+    builder.samples
+}
+
+/// A compaction rule created for every series of an instrument.
+#[derive(Debug, Clone)]
+struct TsCompaction {
+    suffix: String,
+    aggregation: TsAggregationType,
+    options: TsOptions,
+}
+
+/// A push metric exporter that writes all data points with TS.MADD. Use it with
+/// a PeriodicReader of the OpenTelemetry SDK.
+pub struct TsMetricExporter<C: AsyncTsCommands + Clone + Sync + 'static> {
+    con: C,
+    created: Mutex<HashSet<String>>,
+    prefix: String,
+    options: TsOptions,
+    instruments: HashMap<String, TsOptions>,
+    compactions: HashMap<String, Vec<TsCompaction>>,
+    temporality: Temporality,
+    batch_size: usize,
+    shutdown: AtomicBool,
+}
+
+impl<C: AsyncTsCommands + Clone + Sync + 'static> TsMetricExporter<C> {
+    /// Creates an exporter writing to the given connection. The prefix is used
+    /// for all keys.
+    pub fn new(con: C, prefix: &str) -> Self {
+        TsMetricExporter {
+            con,
+            created: Mutex::new(HashSet::new()),
+            prefix: prefix.to_string(),
+            options: TsOptions::default(),
+            instruments: HashMap::new(),
+            compactions: HashMap::new(),
+            temporality: Temporality::Cumulative,
+            batch_size: 1000,
+            shutdown: AtomicBool::new(false),
+        }
+    }
+
+    /// The options used to create new keys. Series labels are added.
+    pub fn options(mut self, options: TsOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The options used to create keys of the instrument with the given name
+    /// instead of the default options. Histogram series use the histogram name.
+    pub fn instrument_options(mut self, instrument: &str, options: TsOptions) -> Self {
+        self.instruments.insert(instrument.to_string(), options);
+        self
+    }
+
+    /// Creates a compaction rule with TS.CREATERULE for every series of the given
+    /// instrument. The destination key is `{key}:{suffix}` and is created with the
+    /// given options and the series labels, its `__name__` label is
+    /// `{name}:{suffix}`.
+    pub fn compaction(
+        mut self,
+        instrument: &str,
+        suffix: &str,
+        aggregation: TsAggregationType,
+        options: TsOptions,
+    ) -> Self {
+        self.compactions
+            .entry(instrument.to_string())
+            .or_default()
+            .push(TsCompaction {
+                suffix: suffix.to_string(),
+                aggregation,
+                options,
+            });
+        self
+    }
+
+    /// The temporality requested from the SDK. Defaults to cumulative.
+    pub fn temporality(mut self, temporality: Temporality) -> Self {
+        self.temporality = temporality;
+        self
+    }
+
+    /// The max amount of samples sent in a single TS.MADD. Defaults to 1000.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// Returns the instrument name of a sample, histogram series are mapped to
+    /// their histogram.
+    fn instrument<'a>(&self, sample: &'a TsOtelSample) -> &'a str {
+        let name = sample.name.as_str();
+        ["_bucket", "_sum", "_count"]
+            .iter()
+            .filter_map(|suffix| name.strip_suffix(suffix))
+            .find(|base| {
+                self.instruments.contains_key(*base) || self.compactions.contains_key(*base)
+            })
+            .unwrap_or(name)
+    }
+
+    fn with_labels(options: &TsOptions, labels: &[(String, String)]) -> TsOptions {
+        labels
+            .iter()
+            .fold(options.clone(), |o, (name, value)| o.label(name, value))
+    }
+
+    async fn create(&self, con: &mut C, sample: &TsOtelSample) -> RedisResult<()> {
+        let instrument = self.instrument(sample);
+        let options = self.instruments.get(instrument).unwrap_or(&self.options);
+        let options = Self::with_labels(options, &sample.labels);
+        create_if_missing_async(con, &sample.key, options).await?;
+        let compactions = match self.compactions.get(instrument) {
+            Some(compactions) => compactions,
+            None => return Ok(()),
+        };
+        let rules = con.ts_info(sample.key.as_str()).await?.rules;
+        for compaction in compactions {
+            let dest = format!("{}:{}", sample.key, compaction.suffix);
+            let labels: Vec<(String, String)> = sample
+                .labels
+                .iter()
+                .map(|(n, v)| match n.as_str() {
+                    METRIC_LABEL => (n.clone(), format!("{v}:{}", compaction.suffix)),
+                    _ => (n.clone(), v.clone()),
+                })
+                .collect();
+            let options = Self::with_labels(&compaction.options, &labels);
+            create_if_missing_async(con, &dest, options).await?;
+            if !rules.iter().any(|(key, _, _)| *key == dest) {
+                con.ts_createrule::<_, ()>(
+                    sample.key.as_str(),
+                    dest.as_str(),
+                    compaction.aggregation,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_created(&self, key: &str) -> bool {
+        self.created
+            .lock()
+            .is_ok_and(|created| created.contains(key))
+    }
+
+    /// Writes all samples of the given metrics. Returns the number of written
+    /// samples. Keys and compaction rules are created once per exporter, rules
+    /// that already exist on a key are kept.
+    pub async fn write(&self, metrics: &ResourceMetrics) -> RedisResult<u64> {
+        let samples = metric_samples(&self.prefix, metrics);
+        let mut con = self.con.clone();
+        let mut count = 0;
+        for chunk in samples.chunks(self.batch_size) {
+            for sample in chunk {
+                if !self.is_created(&sample.key) {
+                    self.create(&mut con, sample).await?;
+                    if let Ok(mut created) = self.created.lock() {
+                        created.insert(sample.key.clone());
+                    }
+                }
+            }
+            let batch: Vec<(&str, u64, f64)> = chunk
+                .iter()
+                .map(|s| (s.key.as_str(), s.ts, s.value))
+                .collect();
+            let _: Vec<u64> = con.ts_madd(&batch).await?;
+            count += batch.len() as u64;
+        }
+        Ok(count)
+    }
+}
+
+impl<C: AsyncTsCommands + Clone + Sync + 'static> PushMetricExporter for TsMetricExporter<C> {
+    async fn export(&self, metrics: &ResourceMetrics) -> OTelSdkResult {
+        if self.shutdown.load(Ordering::SeqCst) {
+            return Err(OTelSdkError::AlreadyShutdown);
+        }
+        self.write(metrics)
+            .await
+            .map(|_| ())
+            .map_err(|e| OTelSdkError::InternalFailure(e.to_string()))
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+        if self.shutdown.swap(true, Ordering::SeqCst) {
+            return Err(OTelSdkError::AlreadyShutdown);
+        }
+        Ok(())
+    }
+
+    fn temporality(&self) -> Temporality {
+        self.temporality
+    }
+}
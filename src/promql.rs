@@ -0,0 +1,639 @@
+//! A practical subset of PromQL evaluated with TS.MRANGE.
+//!
+//! Supported are instant and range vector selectors with `=`, `!=`, `=~` and
+//! `!~` matchers (regex matchers only on an exact set like `"a|b"`), the
+//! functions `rate`, `avg_over_time` and `max_over_time` on range vectors and
+//! `sum` with an optional `by` clause. The metric name is matched against the
+//! `__name__` label.
+//!
+//! Queries are evaluated like a Prometheus range query: the result holds one
+//! sample every step millis between start and end. The value at timestamp t is
+//! computed from the samples in `[t - range, t)`, instant selectors use the last
+//! sample in `[t - step, t)`. `rate` corrects counter resets and extrapolates
+//! to the window boundaries like Prometheus and [TsRange::rate](crate::TsRange::rate).
+//! Whatever can be expressed as an aggregation of a single TS.MRANGE call
+//! (instant selectors, `*_over_time` with a range equal to the step and
+//! `sum by` a single label of those) is pushed down to redis, all other parts
+//! are computed client side from the raw samples.
+//!
+//! ```rust,no_run
+//! # fn run() -> redis::RedisResult<()> {
+//! use redis_ts::promql::query_range;
+//! use redis_ts::TsMrange;
+//!
+//! let client = redis::Client::open("redis://127.0.0.1/")?;
+//! let mut con = client.get_connection()?;
+//!
+//! let result: TsMrange<u64, f64> = query_range(
+//!     &mut con,
+//!     r#"sum by (job) (rate(http_requests_total{env="prod"}[5m]))"#,
+//!     1700000000000,
+//!     1700003600000,
+//!     60000,
+//! )?;
+//! # Ok(()) }
+//! ```
+//!
+use crate::commands::TsCommands;
+use crate::rate::rate_at;
+use crate::types::*;
+use redis::{RedisError, RedisResult};
+use std::collections::BTreeMap;
+
+/// The label holding the metric name of a series.
+pub const METRIC_LABEL: &str = "__name__";
+
+/// The comparison of a label matcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromMatchOp {
+    Eq,
+    Neq,
+    Re,
+    Nre,
+}
+
+/// A single label matcher of a selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromMatcher {
+    pub name: String,
+    pub op: PromMatchOp,
+    pub value: String,
+}
+
+/// A series selector. Selectors with a range (in millis) are range vectors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromSelector {
+    pub matchers: Vec<PromMatcher>,
+    pub range: Option<u64>,
+}
+
+/// The supported functions on range vectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromFunction {
+    Rate,
+    AvgOverTime,
+    MaxOverTime,
+}
+
+/// A parsed query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromExpr {
+    Selector(PromSelector),
+    Call(PromFunction, PromSelector),
+    Sum {
+        by: Vec<String>,
+        expr: Box<PromExpr>,
+    },
+}
+
+fn query_error(query: &str, reason: &str) -> RedisError {
+    RedisError::from(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("invalid promql ({reason}): {query}"),
+    ))
+}
+
+/// Returns true if the value can be written into a TS.MRANGE filter as is.
+/// Commas and parentheses would be read as set syntax by the server.
+pub(crate) fn filter_value(value: &str) -> bool {
+    !value.contains([',', '(', ')'])
+}
+
+/// Returns the values of a regex that is a plain alternation of non empty
+/// literals like `a|b|c`, which can be expressed as a label set filter.
+pub(crate) fn exact_set(value: &str) -> Option<Vec<&str>> {
+    let values: Vec<&str> = value.split('|').collect();
+    if values.iter().all(|v| {
+        !v.is_empty()
+            && !v
+                .chars()
+                .any(|c| "\\.+*?()[]{}^$#&~,".contains(c) || c.is_whitespace())
+    }) {
+        Some(values)
+    } else {
+        None
+    }
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.query[self.pos..]
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_ws();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> RedisResult<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{token}' at {}", self.pos)))
+        }
+    }
+
+    fn error(&self, reason: &str) -> RedisError {
+        query_error(self.query, reason)
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        self.skip_ws();
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|(idx, c)| {
+                !(c.is_ascii_alphabetic()
+                    || *c == '_'
+                    || *c == ':'
+                    || (*idx > 0 && c.is_ascii_digit()))
+            })
+            .map(|(idx, _)| idx)
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn string(&mut self) -> RedisResult<String> {
+        self.skip_ws();
+        let quote = match self.rest().chars().next() {
+            Some(q @ ('"' | '\'')) => q,
+            _ => return Err(self.error(&format!("expected string at {}", self.pos))),
+        };
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices().skip(1);
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, next)) => value.push(next),
+                    None => break,
+                },
+                c if c == quote => {
+                    self.pos += idx + 1;
+                    return Ok(value);
+                }
+                c => value.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn duration(&mut self) -> RedisResult<u64> {
+        self.skip_ws();
+        let mut total = 0;
+        loop {
+            let rest = self.rest();
+            let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+            if digits == 0 {
+                break;
+            }
+            let value: u64 = rest[..digits]
+                .parse()
+                .map_err(|_| self.error("invalid duration"))?;
+            let unit = &rest[digits..];
+            let (len, millis) = if unit.starts_with("ms") {
+                (2, 1)
+            } else {
+                match unit.chars().next() {
+                    Some('s') => (1, 1_000),
+                    Some('m') => (1, 60_000),
+                    Some('h') => (1, 3_600_000),
+                    Some('d') => (1, 86_400_000),
+                    Some('w') => (1, 604_800_000),
+                    Some('y') => (1, 31_536_000_000),
+                    _ => return Err(self.error("missing duration unit")),
+                }
+            };
+            total += value.saturating_mul(millis);
+            self.pos += digits + len;
+        }
+        if total == 0 {
+            return Err(self.error("invalid duration"));
+        }
+        Ok(total)
+    }
+
+    fn label_list(&mut self) -> RedisResult<Vec<String>> {
+        self.expect("(")?;
+        let mut labels = vec![];
+        while !self.eat(")") {
+            match self.ident() {
+                Some(label) => labels.push(label.to_string()),
+                None => return Err(self.error("expected label name")),
+            }
+            if !self.eat(",") {
+                self.expect(")")?;
+                break;
+            }
+        }
+        Ok(labels)
+    }
+
+    fn by_clause(&mut self) -> RedisResult<Option<Vec<String>>> {
+        let start = self.pos;
+        match self.ident() {
+            Some("by") => self.label_list().map(Some),
+            Some("without") => Err(self.error("sum without is not supported")),
+            _ => {
+                self.pos = start;
+                Ok(None)
+            }
+        }
+    }
+
+    fn selector(&mut self, metric: Option<&str>) -> RedisResult<PromSelector> {
+        let mut matchers = vec![];
+        if let Some(name) = metric {
+            matchers.push(PromMatcher {
+                name: METRIC_LABEL.to_string(),
+                op: PromMatchOp::Eq,
+                value: name.to_string(),
+            });
+        }
+        if self.eat("{") {
+            while !self.eat("}") {
+                let name = match self.ident() {
+                    Some(name) => name.to_string(),
+                    None => return Err(self.error("expected label name")),
+                };
+                let op = if self.eat("=~") {
+                    PromMatchOp::Re
+                } else if self.eat("!~") {
+                    PromMatchOp::Nre
+                } else if self.eat("!=") {
+                    PromMatchOp::Neq
+                } else if self.eat("=") {
+                    PromMatchOp::Eq
+                } else {
+                    return Err(self.error("expected label matcher"));
+                };
+                let value = self.string()?;
+                if matches!(op, PromMatchOp::Re | PromMatchOp::Nre) && exact_set(&value).is_none() {
+                    return Err(self.error("regex matchers only support exact sets like \"a|b\""));
+                }
+                if !filter_value(&value) {
+                    return Err(self.error("label values must not contain ',', '(' or ')'"));
+                }
+                matchers.push(PromMatcher { name, op, value });
+                if !self.eat(",") {
+                    self.expect("}")?;
+                    break;
+                }
+            }
+        }
+        if matchers.is_empty() {
+            return Err(self.error("empty selector"));
+        }
+        let range = if self.eat("[") {
+            let range = self.duration()?;
+            self.expect("]")?;
+            Some(range)
+        } else {
+            None
+        };
+        Ok(PromSelector { matchers, range })
+    }
+
+    fn expr(&mut self) -> RedisResult<PromExpr> {
+        let start = self.pos;
+        let name = self.ident();
+        let function = match name {
+            Some("rate") => Some(PromFunction::Rate),
+            Some("avg_over_time") => Some(PromFunction::AvgOverTime),
+            Some("max_over_time") => Some(PromFunction::MaxOverTime),
+            _ => None,
+        };
+
+        if name == Some("sum") && matches!(self.peek(), Some('(') | Some('b') | Some('w')) {
+            let by = self.by_clause()?;
+            self.expect("(")?;
+            let expr = self.expr()?;
+            self.expect(")")?;
+            let by = match by {
+                Some(by) => by,
+                None => self.by_clause()?.unwrap_or_default(),
+            };
+            if let PromExpr::Selector(PromSelector { range: Some(_), .. }) = expr {
+                return Err(self.error("sum expects an instant vector"));
+            }
+            return Ok(PromExpr::Sum {
+                by,
+                expr: Box::new(expr),
+            });
+        }
+
+        if let Some(function) = function {
+            if self.peek() == Some('(') {
+                self.expect("(")?;
+                let inner = self.ident();
+                let selector = self.selector(inner)?;
+                self.expect(")")?;
+                if selector.range.is_none() {
+                    return Err(self.error("functions expect a range vector selector"));
+                }
+                return Ok(PromExpr::Call(function, selector));
+            }
+        }
+
+        if self.peek() == Some('(') {
+            self.pos = start;
+            return Err(self.error(&format!("unsupported function at {start}")));
+        }
+        self.selector(name).map(PromExpr::Selector)
+    }
+}
+
+/// Parses a query. Returns an error for anything outside of the supported subset.
+pub fn parse(query: &str) -> RedisResult<PromExpr> {
+    let mut parser = Parser { query, pos: 0 };
+    let expr = parser.expr()?;
+    if parser.peek().is_some() {
+        return Err(parser.error(&format!("unexpected input at {}", parser.pos)));
+    }
+    if let PromExpr::Selector(PromSelector { range: Some(_), .. }) = expr {
+        return Err(parser.error("range vectors can not be evaluated as range query"));
+    }
+    Ok(expr)
+}
+
+/// How the samples of a leaf expression are computed.
+enum Leaf {
+    Pushed(TsAggregationType),
+    Window(PromFunction, u64),
+}
+
+fn leaf(expr: &PromExpr, step: u64) -> Option<(Leaf, &PromSelector)> {
+    match expr {
+        PromExpr::Selector(selector) => {
+            Some((Leaf::Pushed(TsAggregationType::Last(step)), selector))
+        }
+        PromExpr::Call(function, selector) => {
+            let range = selector.range.unwrap_or(step);
+            let leaf = match function {
+                PromFunction::AvgOverTime if range == step => {
+                    Leaf::Pushed(TsAggregationType::Avg(step))
+                }
+                PromFunction::MaxOverTime if range == step => {
+                    Leaf::Pushed(TsAggregationType::Max(step))
+                }
+                function => Leaf::Window(*function, range),
+            };
+            Some((leaf, selector))
+        }
+        PromExpr::Sum { .. } => None,
+    }
+}
+
+/// Returns the label a sum can be grouped by on the server.
+fn grouped_by<'a>(by: &'a [String], expr: &PromExpr, step: u64) -> Option<&'a str> {
+    match (by, leaf(expr, step)) {
+        ([label], Some((Leaf::Pushed(_), _))) => Some(label.as_str()),
+        _ => None,
+    }
+}
+
+fn filter(selector: &PromSelector) -> TsFilterOptions {
+    selector
+        .matchers
+        .iter()
+        .fold(TsFilterOptions::default().with_labels(true), |f, m| {
+            let name = m.name.as_str();
+            match (m.op, m.value.as_str()) {
+                (PromMatchOp::Eq, "") => f.not_has_label(name),
+                (PromMatchOp::Eq, v) => f.equals(name, v),
+                (PromMatchOp::Neq, "") => f.has_label(name),
+                (PromMatchOp::Neq, v) => f.not_equals(name, v),
+                (PromMatchOp::Re, v) => f.in_set(name, exact_set(v).unwrap_or_default()),
+                (PromMatchOp::Nre, v) => f.not_in_set(name, exact_set(v).unwrap_or_default()),
+            }
+        })
+}
+
+fn leaf_query(leaf: &Leaf, start: u64, end: u64, step: u64) -> TsRangeQuery {
+    match leaf {
+        Leaf::Pushed(aggregation) => TsRangeQuery::default()
+            .from(start.saturating_sub(step))
+            .to(end.saturating_sub(1))
+            .align(TsAlign::Ts(start))
+            .aggregation_type(*aggregation)
+            .bucket_timestamp(TsBucketTimestamp::High),
+        Leaf::Window(_, range) => TsRangeQuery::default()
+            .from(start.saturating_sub(*range))
+            .to(end.saturating_sub(1)),
+    }
+}
+
+fn window_value(
+    function: PromFunction,
+    range: u64,
+    end: u64,
+    window: &[(u64, f64)],
+) -> Option<f64> {
+    match function {
+        PromFunction::AvgOverTime if !window.is_empty() => {
+            Some(window.iter().map(|v| v.1).sum::<f64>() / window.len() as f64)
+        }
+        PromFunction::MaxOverTime => window.iter().map(|v| v.1).reduce(f64::max),
+        PromFunction::Rate => rate_at(window, range, end),
+        _ => None,
+    }
+}
+
+fn without_metric(labels: Vec<(String, String)>) -> Vec<(String, String)> {
+    labels
+        .into_iter()
+        .filter(|(n, _)| n != METRIC_LABEL)
+        .collect()
+}
+
+impl PromExpr {
+    /// Returns the TS.MRANGE queries needed to evaluate this expression between
+    /// start and end with the given step, in the order their results are
+    /// expected by evaluate.
+    pub fn queries(&self, start: u64, end: u64, step: u64) -> Vec<(TsRangeQuery, TsFilterOptions)> {
+        let mut queries = vec![];
+        self.collect_queries(start, end, step.max(1), &mut queries);
+        queries
+    }
+
+    fn collect_queries(
+        &self,
+        start: u64,
+        end: u64,
+        step: u64,
+        queries: &mut Vec<(TsRangeQuery, TsFilterOptions)>,
+    ) {
+        if let Some((leaf, selector)) = leaf(self, step) {
+            queries.push((leaf_query(&leaf, start, end, step), filter(selector)));
+        } else if let PromExpr::Sum { by, expr } = self {
+            match (grouped_by(by, expr, step), leaf(expr, step)) {
+                (Some(label), Some((leaf, selector))) => queries.push((
+                    leaf_query(&leaf, start, end, step),
+                    filter(selector).group_by(label, TsReducer::Sum),
+                )),
+                _ => expr.collect_queries(start, end, step, queries),
+            }
+        }
+    }
+
+    /// Evaluates this expression from the results of the queries returned by
+    /// queries. Grouped series are keyed by their `name=value` label pairs.
+    pub fn evaluate(
+        &self,
+        results: Vec<TsMrange<u64, f64>>,
+        start: u64,
+        end: u64,
+        step: u64,
+    ) -> RedisResult<TsMrange<u64, f64>> {
+        let mut results = results.into_iter();
+        let values = self.combine(&mut results, start, end, step.max(1))?;
+        Ok(TsMrange { values })
+    }
+
+    fn combine<I: Iterator<Item = TsMrange<u64, f64>>>(
+        &self,
+        results: &mut I,
+        start: u64,
+        end: u64,
+        step: u64,
+    ) -> RedisResult<Vec<TsMrangeEntry<u64, f64>>> {
+        Ok(match (self, leaf(self, step)) {
+            (PromExpr::Selector(_), _) => next_result(results)?,
+            (PromExpr::Call(..), Some((Leaf::Pushed(_), _))) => next_result(results)?
+                .into_iter()
+                .map(|e| TsMrangeEntry {
+                    key: e.key,
+                    labels: without_metric(e.labels),
+                    values: e.values,
+                })
+                .collect(),
+            (PromExpr::Call(..), Some((Leaf::Window(function, range), _))) => next_result(results)?
+                .into_iter()
+                .map(|e| {
+                    let mut values = e.values;
+                    values.sort_by_key(|v| v.0);
+                    let samples = (start..=end)
+                        .step_by(step as usize)
+                        .filter_map(|t| {
+                            let lo = values.partition_point(|v| v.0 < t.saturating_sub(range));
+                            let hi = values.partition_point(|v| v.0 < t);
+                            window_value(function, range, t, &values[lo..hi]).map(|v| (t, v))
+                        })
+                        .collect();
+                    TsMrangeEntry {
+                        key: e.key,
+                        labels: without_metric(e.labels),
+                        values: samples,
+                    }
+                })
+                .filter(|e| !e.values.is_empty())
+                .collect(),
+            (PromExpr::Sum { by, expr }, _) => match grouped_by(by, expr, step) {
+                Some(label) => next_result(results)?
+                    .into_iter()
+                    .map(|e| TsMrangeEntry {
+                        key: e.key,
+                        labels: e.labels.into_iter().filter(|(n, _)| n == label).collect(),
+                        values: e.values,
+                    })
+                    .collect(),
+                None => sum_by(by, expr.combine(results, start, end, step)?),
+            },
+            (PromExpr::Call(..), None) => vec![],
+        })
+    }
+}
+
+fn next_result<I: Iterator<Item = TsMrange<u64, f64>>>(
+    results: &mut I,
+) -> RedisResult<Vec<TsMrangeEntry<u64, f64>>> {
+    results
+        .next()
+        .map(|r| r.values)
+        .ok_or_else(|| RedisError::from(std::io::Error::other("missing query result")))
+}
+
+fn sum_by(by: &[String], entries: Vec<TsMrangeEntry<u64, f64>>) -> Vec<TsMrangeEntry<u64, f64>> {
+    let mut groups: BTreeMap<Vec<(String, String)>, BTreeMap<u64, f64>> = BTreeMap::new();
+    for entry in entries {
+        let labels = by
+            .iter()
+            .filter_map(|name| entry.labels.iter().find(|(n, _)| n == name).cloned())
+            .collect();
+        let group = groups.entry(labels).or_default();
+        for (ts, value) in entry.values {
+            *group.entry(ts).or_insert(0.0) += value;
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(labels, values)| TsMrangeEntry {
+            key: labels
+                .iter()
+                .map(|(n, v)| format!("{n}={v}"))
+                .collect::<Vec<String>>()
+                .join(","),
+            labels,
+            values: values.into_iter().collect(),
+        })
+        .collect()
+}
+
+/// Parses and evaluates a query between start and end with the given step in
+/// millis.
+pub fn query_range<C: TsCommands>(
+    con: &mut C,
+    query: &str,
+    start: u64,
+    end: u64,
+    step: u64,
+) -> RedisResult<TsMrange<u64, f64>> {
+    let expr = parse(query)?;
+    let mut results = vec![];
+    for (range_query, filter) in expr.queries(start, end, step) {
+        results.push(con.ts_mrange(range_query, filter)?);
+    }
+    expr.evaluate(results, start, end, step)
+}
+
+/// Async version of query_range.
+#[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+pub async fn query_range_async<C: crate::AsyncTsCommands>(
+    con: &mut C,
+    query: &str,
+    start: u64,
+    end: u64,
+    step: u64,
+) -> RedisResult<TsMrange<u64, f64>> {
+    let expr = parse(query)?;
+    let mut results = vec![];
+    for (range_query, filter) in expr.queries(start, end, step) {
+        results.push(con.ts_mrange(range_query, filter).await?);
+    }
+    expr.evaluate(results, start, end, step)
+}
@@ -0,0 +1,135 @@
+use crate::types::*;
+
+/// Returns the non NaN samples in ascending order.
+fn ascending(values: &[(u64, f64)]) -> Vec<(u64, f64)> {
+    let mut sorted: Vec<(u64, f64)> = values.iter().filter(|v| !v.1.is_nan()).copied().collect();
+    sorted.sort_by_key(|v| v.0);
+    sorted
+}
+
+/// Evaluates a function on the sliding window (ts - window, ts] ending at every
+/// sample. Windows without a result are skipped.
+fn sliding<F>(values: &[(u64, f64)], window: u64, f: F) -> TsRange<u64, f64>
+where
+    F: Fn(&[(u64, f64)], u64) -> Option<f64>,
+{
+    let values = ascending(values);
+    let mut start = 0;
+    let mut result = vec![];
+    for (end, &(ts, _)) in values.iter().enumerate() {
+        while start < end && values[start].0 + window <= ts {
+            start += 1;
+        }
+        if let Some(value) = f(&values[start..=end], ts) {
+            result.push((ts, value));
+        }
+    }
+    TsRange { values: result }
+}
+
+/// The difference between the last and first sample, extrapolated to the window
+/// boundaries the same way Prometheus does. Counter resets are corrected for
+/// counters and the extrapolation stops where a counter would hit zero.
+fn extrapolated(
+    samples: &[(u64, f64)],
+    window: u64,
+    end: u64,
+    counter: bool,
+    per_second: bool,
+) -> Option<f64> {
+    if samples.len() < 2 {
+        return None;
+    }
+    let (first_ts, first) = samples[0];
+    let (last_ts, last) = samples[samples.len() - 1];
+    let mut result = last - first;
+    if counter {
+        result += samples
+            .windows(2)
+            .filter(|pair| pair[1].1 < pair[0].1)
+            .map(|pair| pair[0].1)
+            .sum::<f64>();
+    }
+
+    let sampled = (last_ts - first_ts) as f64 / 1000.0;
+    let average = sampled / (samples.len() - 1) as f64;
+    let mut to_start = (first_ts + window - end) as f64 / 1000.0;
+    let to_end = (end - last_ts) as f64 / 1000.0;
+    if counter && result > 0.0 && first >= 0.0 {
+        to_start = to_start.min(sampled * (first / result));
+    }
+
+    let threshold = average * 1.1;
+    let mut interval = sampled;
+    interval += if to_start < threshold {
+        to_start
+    } else {
+        average / 2.0
+    };
+    interval += if to_end < threshold {
+        to_end
+    } else {
+        average / 2.0
+    };
+    result *= interval / sampled;
+
+    if per_second {
+        result /= window as f64 / 1000.0;
+    }
+    Some(result)
+}
+
+/// The per second rate of a counter over the window (in millis) ending at end,
+/// for the samples inside that window. Shared with the PromQL rate function.
+pub(crate) fn rate_at(samples: &[(u64, f64)], window: u64, end: u64) -> Option<f64> {
+    extrapolated(&ascending(samples), window, end, true, true)
+}
+
+/// The per second rate between the last two samples, treating a decrease as a
+/// counter reset.
+fn instant(samples: &[(u64, f64)]) -> Option<f64> {
+    match samples {
+        [.., (prev_ts, prev), (last_ts, last)] if last_ts > prev_ts => {
+            let increase = if last < prev { *last } else { last - prev };
+            Some(increase / ((last_ts - prev_ts) as f64 / 1000.0))
+        }
+        _ => None,
+    }
+}
+
+impl TsRange<u64, f64> {
+    /// Returns the per second rate of increase of a counter over the window
+    /// (in millis) ending at each sample, like the PromQL rate function. Counter
+    /// resets are detected and the increase is extrapolated to the window
+    /// boundaries. Windows with less than two samples yield no value.
+    pub fn rate(&self, window: u64) -> TsRange<u64, f64> {
+        sliding(&self.values, window, |samples, end| {
+            extrapolated(samples, window, end, true, true)
+        })
+    }
+
+    /// Returns the per second rate of increase of a counter between the last
+    /// two samples of the window ending at each sample, like the PromQL irate
+    /// function.
+    pub fn irate(&self, window: u64) -> TsRange<u64, f64> {
+        sliding(&self.values, window, |samples, _| instant(samples))
+    }
+
+    /// Returns the increase of a counter over the window ending at each sample,
+    /// like the PromQL increase function. This is the rate multiplied by the
+    /// window in seconds.
+    pub fn increase(&self, window: u64) -> TsRange<u64, f64> {
+        sliding(&self.values, window, |samples, end| {
+            extrapolated(samples, window, end, true, false)
+        })
+    }
+
+    /// Returns the difference between the first and last value of a gauge over
+    /// the window ending at each sample, extrapolated like the PromQL delta
+    /// function. Decreases are not treated as resets.
+    pub fn delta(&self, window: u64) -> TsRange<u64, f64> {
+        sliding(&self.values, window, |samples, end| {
+            extrapolated(samples, window, end, false, false)
+        })
+    }
+}
@@ -0,0 +1,189 @@
+use crate::types::*;
+use redis::{RedisError, RedisResult};
+use std::collections::BTreeMap;
+
+/// The label holding the record name of every series written by a TsRecord.
+pub const RECORD_LABEL: &str = "__record__";
+
+/// The label holding the value field name of every series written by a
+/// TsRecord.
+pub const FIELD_LABEL: &str = "__field__";
+
+fn invalid(message: String) -> RedisError {
+    RedisError::from(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message,
+    ))
+}
+
+/// A struct written as one sample per value field at the timestamp of the
+/// record. Every value field is stored in its own series, labeled with the
+/// label fields of the record as well as `__record__` and `__field__`, which
+/// allows reading mget and mrange results back into records.
+///
+/// With the `derive` feature it can be derived with `#[derive(TsRecord)]`:
+///
+/// ```rust
+/// # #[cfg(feature = "derive")]
+/// # fn run() -> redis::RedisResult<()> {
+/// use redis_ts::{TsCommands, TsMrange, TsOptions, TsRangeQuery, TsRecord};
+///
+/// #[derive(TsRecord)]
+/// #[ts(name = "cpu", key = "cpu:{host}:{field}")]
+/// struct Cpu {
+///     #[ts(timestamp)]
+///     ts: u64,
+///     #[ts(label)]
+///     host: String,
+///     #[ts(value)]
+///     user: f64,
+///     #[ts(value, rename = "sys")]
+///     system: Option<f64>,
+/// }
+///
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let mut con = client.get_connection()?;
+///
+/// let cpu = Cpu { ts: 1000, host: "a".to_string(), user: 0.5, system: Some(0.1) };
+/// for (key, options) in cpu.series_options(TsOptions::default().retention_time(60000)) {
+///     let _: () = con.ts_create(key, options)?;
+/// }
+/// let _: Vec<u64> = con.ts_madd(&Cpu::samples(&[cpu]))?;
+///
+/// let mrange: TsMrange<u64, f64> = con.ts_mrange(TsRangeQuery::default(), Cpu::filter())?;
+/// let records: Vec<Cpu> = Cpu::from_mrange(&mrange)?;
+/// # Ok(()) }
+/// ```
+///
+/// Value fields have to be numbers or options of numbers, label fields have to
+/// implement Display and FromStr. A custom key has to contain `{field}` unless
+/// there is a single value field, otherwise all values share one series:
+///
+/// ```compile_fail
+/// use redis_ts::TsRecord;
+///
+/// #[derive(TsRecord)]
+/// #[ts(name = "cpu", key = "cpu:{host}")]
+/// struct Cpu {
+///     #[ts(timestamp)]
+///     ts: u64,
+///     #[ts(label)]
+///     host: String,
+///     #[ts(value)]
+///     user: f64,
+///     #[ts(value)]
+///     system: f64,
+/// }
+/// ```
+pub trait TsRecord: Sized {
+    /// The name of the record, stored in the `__record__` label.
+    fn record_name() -> &'static str;
+
+    /// The timestamp of the record.
+    fn timestamp(&self) -> u64;
+
+    /// The label values of the record.
+    fn labels(&self) -> Vec<(String, String)>;
+
+    /// The values of all value fields that are set.
+    fn values(&self) -> Vec<(&'static str, f64)>;
+
+    /// The key of the series of the given value field.
+    fn key(&self, field: &str) -> String;
+
+    /// Builds a record from a timestamp, its labels and field values.
+    fn from_parts(
+        ts: u64,
+        labels: &[(String, String)],
+        values: &[(String, f64)],
+    ) -> Result<Self, String>;
+
+    /// Returns the TS.MADD samples of all value fields of the given records.
+    fn samples(records: &[Self]) -> Vec<(String, u64, f64)> {
+        records
+            .iter()
+            .flat_map(|r| {
+                r.values()
+                    .into_iter()
+                    .map(move |(field, value)| (r.key(field), r.timestamp(), value))
+            })
+            .collect()
+    }
+
+    /// Adds the record labels of the given value field to the options.
+    fn options(&self, field: &str, options: TsOptions) -> TsOptions {
+        self.labels().iter().fold(
+            options
+                .label(RECORD_LABEL, Self::record_name())
+                .label(FIELD_LABEL, field),
+            |o, (name, value)| o.label(name, value),
+        )
+    }
+
+    /// Returns the keys and options to create the series of all value fields
+    /// that are set.
+    fn series_options(&self, options: TsOptions) -> Vec<(String, TsOptions)> {
+        self.values()
+            .into_iter()
+            .map(|(field, _)| (self.key(field), self.options(field, options.clone())))
+            .collect()
+    }
+
+    /// Returns a filter matching all series of this record type with labels.
+    fn filter() -> TsFilterOptions {
+        TsFilterOptions::default()
+            .equals(RECORD_LABEL, Self::record_name())
+            .with_labels(true)
+    }
+
+    /// Reads the latest values of an mget with labels into records. Values are
+    /// merged into one record if their labels and timestamp are equal.
+    fn from_mget(mget: &TsMget<u64, f64>) -> RedisResult<Vec<Self>> {
+        let samples = mget.values.iter().flat_map(|e| {
+            e.value
+                .iter()
+                .map(move |(ts, value)| (&e.labels, *ts, *value))
+        });
+        records(samples)
+    }
+
+    /// Reads an mrange with labels into records, ordered by timestamp.
+    fn from_mrange(mrange: &TsMrange<u64, f64>) -> RedisResult<Vec<Self>> {
+        let samples = mrange.values.iter().flat_map(|e| {
+            e.values
+                .iter()
+                .map(move |(ts, value)| (&e.labels, *ts, *value))
+        });
+        records(samples)
+    }
+}
+
+type RecordKey = (u64, Vec<(String, String)>);
+
+fn records<'a, T: TsRecord, I>(samples: I) -> RedisResult<Vec<T>>
+where
+    I: Iterator<Item = (&'a Vec<(String, String)>, u64, f64)>,
+{
+    let mut grouped: BTreeMap<RecordKey, Vec<(String, f64)>> = BTreeMap::new();
+    for (labels, ts, value) in samples {
+        let field = labels
+            .iter()
+            .find(|(name, _)| name == FIELD_LABEL)
+            .map(|(_, field)| field.clone())
+            .ok_or_else(|| invalid(format!("missing {FIELD_LABEL} label")))?;
+        let mut record_labels: Vec<(String, String)> = labels
+            .iter()
+            .filter(|(name, _)| name != FIELD_LABEL && name != RECORD_LABEL)
+            .cloned()
+            .collect();
+        record_labels.sort();
+        grouped
+            .entry((ts, record_labels))
+            .or_default()
+            .push((field, value));
+    }
+    grouped
+        .into_iter()
+        .map(|((ts, labels), values)| T::from_parts(ts, &labels, &values).map_err(invalid))
+        .collect()
+}
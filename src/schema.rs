@@ -0,0 +1,567 @@
+//! Typed series schemas and a registry to validate writes and queries.
+//!
+//! A [TsSchema] defines the key template of a family of series, eg.
+//! `sensor:{site}:{kind}`, the labels every series has with their allowed
+//! values, the retention, duplicate policy and compaction tiers. Keys, create
+//! options and filters are generated from label values and rejected if they do
+//! not fit the schema. Every series is labeled with `__schema__=<name>` so
+//! filters only match series of the schema.
+//!
+//! ```rust
+//! use redis_ts::schema::{TsSchema, TsSchemaRegistry};
+//! use redis_ts::{TsAggregationType, TsDuplicatePolicy};
+//!
+//! let sensor = TsSchema::new("sensor", "sensor:{site}:{kind}")
+//!     .label("site")
+//!     .label_values("kind", &["temperature", "humidity"])
+//!     .optional_label("firmware")
+//!     .retention(86400000)
+//!     .duplicate_policy(TsDuplicatePolicy::Last)
+//!     .compaction("1h", TsAggregationType::Avg(3600000), 2592000000);
+//!
+//! let key = sensor.key(&[("site", "berlin"), ("kind", "temperature")]).unwrap();
+//! assert_eq!(key, "sensor:berlin:temperature");
+//! assert!(sensor.key(&[("site", "berlin"), ("kind", "pressure")]).is_err());
+//!
+//! let registry = TsSchemaRegistry::new().register(sensor);
+//! assert!(registry.validate_key("sensor:berlin:humidity").is_ok());
+//! assert!(registry.validate_key("sensors:berlin").is_err());
+//! ```
+//!
+//! With the `derive` feature the schema can be derived from a struct holding
+//! the label values, see [TsSeries].
+
+use crate::commands::TsCommands;
+use crate::types::*;
+use redis::{RedisError, RedisResult, ToRedisArgs};
+
+/// The label holding the schema name of every series.
+pub const SCHEMA_LABEL: &str = "__schema__";
+
+fn invalid(message: String) -> RedisError {
+    RedisError::from(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        message,
+    ))
+}
+
+fn owned<L: AsRef<str>, V: AsRef<str>>(labels: &[(L, V)]) -> Vec<(String, String)> {
+    labels
+        .iter()
+        .map(|(n, v)| (n.as_ref().to_string(), v.as_ref().to_string()))
+        .collect()
+}
+
+/// A label of a schema. Any value is allowed if values is empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsLabelSpec {
+    pub name: String,
+    pub values: Vec<String>,
+    pub required: bool,
+}
+
+/// A compaction tier stored in `{key}:{suffix}` with its own retention.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsCompactionTier {
+    pub suffix: String,
+    pub aggregation: TsAggregationType,
+    pub retention: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Label(String),
+}
+
+fn segments(template: &str) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        match rest[start..].find('}') {
+            Some(end) => {
+                if start > 0 {
+                    segments.push(Segment::Literal(rest[..start].to_string()));
+                }
+                segments.push(Segment::Label(rest[start + 1..start + end].to_string()));
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+    segments
+}
+
+/// The schema of a family of series. See the [module docs](self) for an
+/// example.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsSchema {
+    pub name: String,
+    pub key: String,
+    pub labels: Vec<TsLabelSpec>,
+    pub retention: Option<u64>,
+    pub duplicate_policy: Option<TsDuplicatePolicy>,
+    pub compactions: Vec<TsCompactionTier>,
+}
+
+impl TsSchema {
+    /// Creates a schema with the given name and key template. Placeholders like
+    /// `{site}` in the template are replaced with the value of the label.
+    pub fn new(name: &str, key: &str) -> Self {
+        TsSchema {
+            name: name.to_string(),
+            key: key.to_string(),
+            labels: vec![],
+            retention: None,
+            duplicate_policy: None,
+            compactions: vec![],
+        }
+    }
+
+    fn add_label(mut self, name: &str, values: &[&str], required: bool) -> Self {
+        self.labels.retain(|l| l.name != name);
+        self.labels.push(TsLabelSpec {
+            name: name.to_string(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+            required,
+        });
+        self
+    }
+
+    /// Adds a required label that allows any value.
+    pub fn label(self, name: &str) -> Self {
+        self.add_label(name, &[], true)
+    }
+
+    /// Adds a required label that only allows the given values.
+    pub fn label_values(self, name: &str, values: &[&str]) -> Self {
+        self.add_label(name, values, true)
+    }
+
+    /// Adds an optional label that allows any value.
+    pub fn optional_label(self, name: &str) -> Self {
+        self.add_label(name, &[], false)
+    }
+
+    /// Adds an optional label that only allows the given values.
+    pub fn optional_label_values(self, name: &str, values: &[&str]) -> Self {
+        self.add_label(name, values, false)
+    }
+
+    /// The retention of the series in millis.
+    pub fn retention(mut self, millis: u64) -> Self {
+        self.retention = Some(millis);
+        self
+    }
+
+    /// The duplicate policy of the series.
+    pub fn duplicate_policy(mut self, policy: TsDuplicatePolicy) -> Self {
+        self.duplicate_policy = Some(policy);
+        self
+    }
+
+    /// Adds a compaction tier. Its series are stored in `{key}:{suffix}` with
+    /// the given retention and are part of the `{name}:{suffix}` schema.
+    pub fn compaction(
+        mut self,
+        suffix: &str,
+        aggregation: TsAggregationType,
+        retention: u64,
+    ) -> Self {
+        self.compactions.push(TsCompactionTier {
+            suffix: suffix.to_string(),
+            aggregation,
+            retention,
+        });
+        self
+    }
+
+    /// Returns the schema of the compaction tier with the given suffix.
+    pub fn tier(&self, suffix: &str) -> Option<TsSchema> {
+        self.compactions
+            .iter()
+            .find(|c| c.suffix == suffix)
+            .map(|c| TsSchema {
+                name: format!("{}:{}", self.name, c.suffix),
+                key: format!("{}:{}", self.key, c.suffix),
+                labels: self.labels.clone(),
+                retention: Some(c.retention),
+                duplicate_policy: self.duplicate_policy.clone(),
+                compactions: vec![],
+            })
+    }
+
+    fn spec(&self, name: &str) -> RedisResult<&TsLabelSpec> {
+        self.labels
+            .iter()
+            .find(|l| l.name == name)
+            .ok_or_else(|| invalid(format!("unknown label {name} in schema {}", self.name)))
+    }
+
+    fn check_value(&self, name: &str, value: &str) -> RedisResult<()> {
+        let spec = self.spec(name)?;
+        if !spec.values.is_empty() && !spec.values.iter().any(|v| v == value) {
+            return Err(invalid(format!(
+                "invalid value {value} of label {name} in schema {}",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that all required labels are set and all labels are known and
+    /// have allowed values.
+    pub fn validate_labels<L: AsRef<str>, V: AsRef<str>>(
+        &self,
+        labels: &[(L, V)],
+    ) -> RedisResult<()> {
+        for (name, value) in labels {
+            self.check_value(name.as_ref(), value.as_ref())?;
+        }
+        match self
+            .labels
+            .iter()
+            .find(|l| l.required && !labels.iter().any(|(n, _)| n.as_ref() == l.name))
+        {
+            Some(missing) => Err(invalid(format!(
+                "missing label {} in schema {}",
+                missing.name, self.name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the key of the series with the given labels.
+    pub fn key<L: AsRef<str>, V: AsRef<str>>(&self, labels: &[(L, V)]) -> RedisResult<String> {
+        self.validate_labels(labels)?;
+        let mut key = String::new();
+        for segment in segments(&self.key) {
+            match segment {
+                Segment::Literal(literal) => key.push_str(&literal),
+                Segment::Label(name) => match labels.iter().find(|(n, _)| n.as_ref() == name) {
+                    Some((_, value)) if !value.as_ref().is_empty() => key.push_str(value.as_ref()),
+                    _ => {
+                        return Err(invalid(format!(
+                            "missing key label {name} in schema {}",
+                            self.name
+                        )))
+                    }
+                },
+            }
+        }
+        Ok(key)
+    }
+
+    /// Returns the label values of the key template if the key matches it.
+    pub fn parse_key(&self, key: &str) -> Option<Vec<(String, String)>> {
+        let segments = segments(&self.key);
+        let mut labels = vec![];
+        let mut rest = key;
+        for (idx, segment) in segments.iter().enumerate() {
+            match segment {
+                Segment::Literal(literal) => rest = rest.strip_prefix(literal.as_str())?,
+                Segment::Label(name) => {
+                    let end = match segments.get(idx + 1) {
+                        Some(Segment::Literal(next)) => rest.find(next.as_str())?,
+                        _ => rest.len(),
+                    };
+                    if end == 0 {
+                        return None;
+                    }
+                    labels.push((name.clone(), rest[..end].to_string()));
+                    rest = &rest[end..];
+                }
+            }
+        }
+        match rest.is_empty() {
+            true => Some(labels),
+            false => None,
+        }
+    }
+
+    /// Checks that the key matches the template and its label values match
+    /// the given labels, which have to be valid.
+    pub fn validate_write<L: AsRef<str>, V: AsRef<str>>(
+        &self,
+        key: &str,
+        labels: &[(L, V)],
+    ) -> RedisResult<()> {
+        self.validate_labels(labels)?;
+        let key_labels = self
+            .parse_key(key)
+            .ok_or_else(|| invalid(format!("key {key} does not match schema {}", self.name)))?;
+        let labels = owned(labels);
+        match key_labels.iter().find(|l| !labels.contains(l)) {
+            Some((name, value)) => Err(invalid(format!(
+                "label {name} does not match key value {value} in schema {}",
+                self.name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the options to create the series with the given labels.
+    pub fn options<L: AsRef<str>, V: AsRef<str>>(
+        &self,
+        labels: &[(L, V)],
+    ) -> RedisResult<TsOptions> {
+        self.validate_labels(labels)?;
+        let mut options = TsOptions::default().label(SCHEMA_LABEL, &self.name);
+        for (name, value) in labels {
+            options = options.label(name.as_ref(), value.as_ref());
+        }
+        if let Some(retention) = self.retention {
+            options = options.retention_time(retention);
+        }
+        if let Some(policy) = &self.duplicate_policy {
+            options = options.duplicate_policy(policy.clone());
+        }
+        Ok(options)
+    }
+
+    /// Returns a filter matching all series of this schema with the given
+    /// label values. Labels that are not given are not filtered.
+    pub fn filter<L: AsRef<str>, V: AsRef<str>>(
+        &self,
+        labels: &[(L, V)],
+    ) -> RedisResult<TsFilterOptions> {
+        let mut filter = TsFilterOptions::default().equals(SCHEMA_LABEL, &self.name);
+        for (name, value) in labels {
+            self.check_value(name.as_ref(), value.as_ref())?;
+            filter = filter.equals(name.as_ref(), value.as_ref());
+        }
+        Ok(filter)
+    }
+
+    /// Checks that a filter only uses labels of this schema and allowed values.
+    pub fn validate_filter(&self, filter: &TsFilterOptions) -> RedisResult<()> {
+        for arg in filter.clone().get_filters().to_redis_args() {
+            let arg = String::from_utf8_lossy(&arg).to_string();
+            let (name, value) = match arg.split_once("!=") {
+                Some(pair) => pair,
+                None => arg.split_once('=').unwrap_or((arg.as_str(), "")),
+            };
+            if name == SCHEMA_LABEL {
+                continue;
+            }
+            let values: Vec<&str> = match value.strip_prefix('(').and_then(|v| v.strip_suffix(')'))
+            {
+                Some(set) => set.split(',').collect(),
+                None if value.is_empty() => vec![],
+                None => vec![value],
+            };
+            self.spec(name)?;
+            for value in values {
+                self.check_value(name, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the series with the given labels as well as its compaction
+    /// tiers and rules. Returns the key of the series.
+    pub fn create<C: TsCommands, L: AsRef<str>, V: AsRef<str>>(
+        &self,
+        con: &mut C,
+        labels: &[(L, V)],
+    ) -> RedisResult<String> {
+        let key = self.key(labels)?;
+        let _: () = con.ts_create(key.as_str(), self.options(labels)?)?;
+        for compaction in self.compactions.iter() {
+            if let Some(tier) = self.tier(&compaction.suffix) {
+                let dest = tier.key(labels)?;
+                let _: () = con.ts_create(dest.as_str(), tier.options(labels)?)?;
+                let _: () =
+                    con.ts_createrule(key.as_str(), dest.as_str(), compaction.aggregation)?;
+            }
+        }
+        Ok(key)
+    }
+
+    /// Async version of create.
+    #[cfg(any(feature = "tokio-comp", feature = "async-std-comp"))]
+    pub async fn create_async<C: crate::AsyncTsCommands, L: AsRef<str>, V: AsRef<str>>(
+        &self,
+        con: &mut C,
+        labels: &[(L, V)],
+    ) -> RedisResult<String> {
+        let key = self.key(labels)?;
+        let _: () = con.ts_create(key.clone(), self.options(labels)?).await?;
+        for compaction in self.compactions.iter() {
+            if let Some(tier) = self.tier(&compaction.suffix) {
+                let dest = tier.key(labels)?;
+                let _: () = con.ts_create(dest.clone(), tier.options(labels)?).await?;
+                let _: () = con
+                    .ts_createrule(key.clone(), dest, compaction.aggregation)
+                    .await?;
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// A set of schemas to validate writes and queries against.
+#[derive(Debug, Default, Clone)]
+pub struct TsSchemaRegistry {
+    schemas: Vec<TsSchema>,
+}
+
+impl TsSchemaRegistry {
+    pub fn new() -> Self {
+        TsSchemaRegistry::default()
+    }
+
+    /// Adds a schema and the schemas of its compaction tiers. A schema with
+    /// the same name is replaced.
+    pub fn register(mut self, schema: TsSchema) -> Self {
+        let tiers: Vec<TsSchema> = schema
+            .compactions
+            .iter()
+            .filter_map(|c| schema.tier(&c.suffix))
+            .collect();
+        for schema in std::iter::once(schema).chain(tiers) {
+            self.schemas.retain(|s| s.name != schema.name);
+            self.schemas.push(schema);
+        }
+        self
+    }
+
+    /// Adds the schema of a typed series.
+    pub fn register_series<T: TsSeries>(self) -> Self {
+        self.register(T::schema())
+    }
+
+    /// Returns the schema with the given name.
+    pub fn get(&self, name: &str) -> Option<&TsSchema> {
+        self.schemas.iter().find(|s| s.name == name)
+    }
+
+    /// Returns all registered schemas.
+    pub fn schemas(&self) -> &[TsSchema] {
+        &self.schemas
+    }
+
+    /// Returns the schema whose key template matches the key with allowed label
+    /// values. Compaction tiers are preferred over the schema they belong to.
+    pub fn validate_key(&self, key: &str) -> RedisResult<&TsSchema> {
+        let mut error = None;
+        let schema = self
+            .schemas
+            .iter()
+            .filter(|s| {
+                let labels = match s.parse_key(key) {
+                    Some(labels) => labels,
+                    None => return false,
+                };
+                match labels.iter().try_for_each(|(n, v)| s.check_value(n, v)) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        error.get_or_insert(e);
+                        false
+                    }
+                }
+            })
+            .max_by_key(|s| s.key.len());
+        schema.ok_or_else(|| {
+            error.unwrap_or_else(|| invalid(format!("key {key} does not match any schema")))
+        })
+    }
+
+    /// Checks that all keys of a TS.MADD match a schema.
+    pub fn validate_samples<K: AsRef<str>, TS, V>(
+        &self,
+        samples: &[(K, TS, V)],
+    ) -> RedisResult<()> {
+        for (key, _, _) in samples {
+            self.validate_key(key.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Checks a key and the labels it is created with against its schema.
+    pub fn validate_write<L: AsRef<str>, V: AsRef<str>>(
+        &self,
+        key: &str,
+        labels: &[(L, V)],
+    ) -> RedisResult<()> {
+        self.validate_key(key)?.validate_write(key, labels)
+    }
+
+    /// Checks a filter against the schema named in its `__schema__` label.
+    pub fn validate_query(&self, filter: &TsFilterOptions) -> RedisResult<&TsSchema> {
+        let prefix = format!("{SCHEMA_LABEL}=");
+        let schema = filter
+            .clone()
+            .get_filters()
+            .to_redis_args()
+            .into_iter()
+            .find_map(|arg| {
+                String::from_utf8_lossy(&arg)
+                    .strip_prefix(prefix.as_str())
+                    .and_then(|name| self.get(name))
+            })
+            .ok_or_else(|| invalid("filter does not select a schema".to_string()))?;
+        schema.validate_filter(filter)?;
+        Ok(schema)
+    }
+}
+
+/// A struct holding the label values of a series of a schema. With the
+/// `derive` feature it can be derived with `#[derive(TsSeries)]`.
+///
+/// ```rust
+/// use redis_ts::schema::{TsSchema, TsSeries};
+///
+/// struct Sensor {
+///     site: String,
+///     kind: String,
+/// }
+///
+/// impl TsSeries for Sensor {
+///     fn schema() -> TsSchema {
+///         TsSchema::new("sensor", "sensor:{site}:{kind}")
+///             .label("site")
+///             .label_values("kind", &["temperature", "humidity"])
+///     }
+///
+///     fn labels(&self) -> Vec<(String, String)> {
+///         vec![
+///             ("site".to_string(), self.site.clone()),
+///             ("kind".to_string(), self.kind.clone()),
+///         ]
+///     }
+/// }
+///
+/// let sensor = Sensor { site: "berlin".to_string(), kind: "humidity".to_string() };
+/// assert_eq!(sensor.key().unwrap(), "sensor:berlin:humidity");
+/// ```
+pub trait TsSeries {
+    /// The schema of all series of this type.
+    fn schema() -> TsSchema;
+
+    /// The label values of this series.
+    fn labels(&self) -> Vec<(String, String)>;
+
+    /// The key of this series.
+    fn key(&self) -> RedisResult<String> {
+        Self::schema().key(&self.labels())
+    }
+
+    /// The options to create this series with.
+    fn options(&self) -> RedisResult<TsOptions> {
+        Self::schema().options(&self.labels())
+    }
+
+    /// A filter matching the series with the label values of this one.
+    fn filter(&self) -> RedisResult<TsFilterOptions> {
+        Self::schema().filter(&self.labels())
+    }
+
+    /// Creates this series and its compaction tiers.
+    fn create<C: TsCommands>(&self, con: &mut C) -> RedisResult<String> {
+        Self::schema().create(con, &self.labels())
+    }
+}
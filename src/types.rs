@@ -24,6 +24,27 @@ pub enum TsAggregationType {
     Twa(u64),
 }
 
+impl TsAggregationType {
+    /// Returns the bucket size in millis.
+    pub(crate) fn bucket(&self) -> u64 {
+        match *self {
+            TsAggregationType::Avg(v)
+            | TsAggregationType::Sum(v)
+            | TsAggregationType::Min(v)
+            | TsAggregationType::Max(v)
+            | TsAggregationType::Range(v)
+            | TsAggregationType::Count(v)
+            | TsAggregationType::First(v)
+            | TsAggregationType::Last(v)
+            | TsAggregationType::StdP(v)
+            | TsAggregationType::StdS(v)
+            | TsAggregationType::VarP(v)
+            | TsAggregationType::VarS(v)
+            | TsAggregationType::Twa(v) => v,
+        }
+    }
+}
+
 impl ToRedisArgs for TsAggregationType {
     fn write_redis_args<W>(&self, out: &mut W)
     where
@@ -51,6 +72,45 @@ impl ToRedisArgs for TsAggregationType {
     }
 }
 
+/// The reducer used to combine the series of a group when grouping multi
+/// range results by a label.
+#[derive(PartialEq, Eq, Clone, Debug, Copy)]
+pub enum TsReducer {
+    Avg,
+    Sum,
+    Min,
+    Max,
+    Range,
+    Count,
+    StdP,
+    StdS,
+    VarP,
+    VarS,
+}
+
+impl ToRedisArgs for TsReducer {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        let r = match *self {
+            TsReducer::Avg => "avg",
+            TsReducer::Sum => "sum",
+            TsReducer::Min => "min",
+            TsReducer::Max => "max",
+            TsReducer::Range => "range",
+            TsReducer::Count => "count",
+            TsReducer::StdP => "std.p",
+            TsReducer::StdS => "std.s",
+            TsReducer::VarP => "var.p",
+            TsReducer::VarS => "var.s",
+        };
+
+        out.write_arg(b"REDUCE");
+        out.write_arg(r.as_bytes());
+    }
+}
+
 ///A time bucket alignment control for AGGREGATION. It controls the time bucket
 /// timestamps by changing the reference timestamp on which a bucket is defined.
 /// - Start: The reference timestamp will be the query start interval time.
@@ -289,6 +349,18 @@ impl TsRangeQuery {
         self.empty = empty;
         self
     }
+
+    /// Returns the max amount of returned samples if set.
+    #[cfg(feature = "arrow")]
+    pub(crate) fn get_count(&self) -> Option<u64> {
+        self.count
+    }
+
+    /// Returns the aggregation of the query if set.
+    #[cfg(feature = "arrow")]
+    pub(crate) fn get_aggregation_type(&self) -> Option<TsAggregationType> {
+        self.aggregation_type
+    }
 }
 
 impl ToRedisArgs for TsRangeQuery {
@@ -353,6 +425,7 @@ impl ToRedisArgs for TsRangeQuery {
 /// inserts of values older or equal to latest value in series. Fist
 /// will simply ignore the new value (as opposed to returning an error),
 /// Last will use the new value, Min the lower and Max the higher value.
+/// Sum will add the new value to the existing one.
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub enum TsDuplicatePolicy {
     Block,
@@ -360,6 +433,7 @@ pub enum TsDuplicatePolicy {
     Last,
     Min,
     Max,
+    Sum,
     Other(String),
 }
 
@@ -374,6 +448,7 @@ impl ToRedisArgs for TsDuplicatePolicy {
             TsDuplicatePolicy::Last => "LAST",
             TsDuplicatePolicy::Min => "MIN",
             TsDuplicatePolicy::Max => "MAX",
+            TsDuplicatePolicy::Sum => "SUM",
             TsDuplicatePolicy::Other(v) => v.as_str(),
         };
         out.write_arg(b"DUPLICATE_POLICY");
@@ -390,6 +465,7 @@ impl FromRedisValue for TsDuplicatePolicy {
             "last" => TsDuplicatePolicy::Last,
             "min" => TsDuplicatePolicy::Min,
             "max" => TsDuplicatePolicy::Max,
+            "sum" => TsDuplicatePolicy::Sum,
             v => TsDuplicatePolicy::Other(v.to_string()),
         };
         Ok(res)
@@ -474,6 +550,11 @@ impl TsOptions {
         self.chunk_size = Some(size);
         self
     }
+
+    /// Returns true if a duplicate policy is set.
+    pub(crate) fn has_duplicate_policy(&self) -> bool {
+        self.duplicate_policy.is_some()
+    }
 }
 
 impl ToRedisArgs for TsOptions {
@@ -514,6 +595,7 @@ impl ToRedisArgs for TsOptions {
 pub struct TsFilterOptions {
     with_labels: bool,
     filters: Vec<TsFilter>,
+    group_by: Option<(String, TsReducer)>,
 }
 
 /// TsFilterOptions allows you to build up your redis time series filter query. It
@@ -632,6 +714,14 @@ impl TsFilterOptions {
         self
     }
 
+    /// Groups the resulting series by the given label and reduces every group
+    /// into a single series with the given reducer. Only supported by TS.MRANGE
+    /// and TS.MREVRANGE, other commands will return an error if this is set.
+    pub fn group_by<L: Display>(mut self, label: L, reducer: TsReducer) -> Self {
+        self.group_by = Some((format!("{label}"), reducer));
+        self
+    }
+
     pub fn get_filters(self) -> Vec<TsFilter> {
         self.filters
     }
@@ -650,6 +740,12 @@ impl ToRedisArgs for TsFilterOptions {
         for f in self.filters.iter() {
             f.write_redis_args(out)
         }
+
+        if let Some((ref label, ref reducer)) = self.group_by {
+            out.write_arg(b"GROUPBY");
+            out.write_arg(label.as_bytes());
+            reducer.write_redis_args(out);
+        }
     }
 }
 
@@ -672,92 +768,189 @@ pub struct TsInfo {
 
 impl FromRedisValue for TsInfo {
     fn from_redis_value(v: &Value) -> RedisResult<Self> {
-        match *v {
-            Value::Bulk(ref values) => {
-                let mut result = TsInfo::default();
-                let mut map: HashMap<String, Value> = HashMap::new();
+        let fields: Vec<(&Value, &Value)> = match *v {
+            Value::Array(ref values) => values
+                .chunks(2)
+                .filter_map(|pair| match pair {
+                    [name, value] => Some((name, value)),
+                    _ => None,
+                })
+                .collect(),
+            Value::Map(ref values) => values.iter().map(|(name, value)| (name, value)).collect(),
+            _ => return Err(RedisError::from(std::io::Error::other("no_ts_info_data"))),
+        };
 
-                for pair in values.chunks(2) {
-                    map.insert(from_redis_value(&pair[0])?, pair[1].clone());
-                }
+        let mut result = TsInfo::default();
+        let mut map: HashMap<&str, &Value> = HashMap::with_capacity(fields.len());
+        for (name, value) in fields {
+            if let Some(name) = text(name) {
+                map.insert(name, value);
+            }
+        }
 
-                //println!("{:?}", map);
+        if let Some(v) = map.get("totalSamples") {
+            result.total_samples = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("totalSamples") {
-                    result.total_samples = from_redis_value(v)?;
-                }
+        if let Some(v) = map.get("memoryUsage") {
+            result.memory_usage = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("memoryUsage") {
-                    result.memory_usage = from_redis_value(v)?;
-                }
+        if let Some(v) = map.get("firstTimestamp") {
+            result.first_timestamp = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("firstTimestamp") {
-                    result.first_timestamp = from_redis_value(v)?;
-                }
+        if let Some(v) = map.get("lastTimestamp") {
+            result.last_timestamp = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("lastTimestamp") {
-                    result.last_timestamp = from_redis_value(v)?;
-                }
+        if let Some(v) = map.get("retentionTime") {
+            result.retention_time = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("retentionTime") {
-                    result.retention_time = from_redis_value(v)?;
-                }
+        if let Some(v) = map.get("chunkCount") {
+            result.chunk_count = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("chunkCount") {
-                    result.chunk_count = from_redis_value(v)?;
-                }
+        if let Some(v) = map.get("maxSamplesPerChunk") {
+            result.max_samples_per_chunk = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("maxSamplesPerChunk") {
-                    result.max_samples_per_chunk = from_redis_value(v)?;
-                }
+        if let Some(v) = map.get("chunkSize") {
+            result.chunk_size = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("chunkSize") {
-                    result.chunk_size = from_redis_value(v)?;
-                }
+        if let Some(v) = map.get("sourceKey") {
+            result.source_key = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("sourceKey") {
-                    result.source_key = from_redis_value(v)?;
-                }
+        if let Some(v) = map.get("duplicatePolicy") {
+            result.duplicate_policy = from_redis_value(v)?;
+        }
 
-                if let Some(v) = map.get("duplicatePolicy") {
-                    result.duplicate_policy = from_redis_value(v)?;
-                }
+        // RESP2 replies an array of [dest, bucket, aggregation, ...] rules,
+        // RESP3 a map of dest to [bucket, aggregation, ...].
+        result.rules = match map.get("rules") {
+            Some(Value::Array(ref values)) => values
+                .iter()
+                .filter_map(|value| match value {
+                    Value::Array(ref vs) if vs.len() >= 3 => Some(rule(&vs[0], &vs[1], &vs[2])),
+                    _ => None,
+                })
+                .collect::<RedisResult<_>>()?,
+            Some(Value::Map(ref values)) => values
+                .iter()
+                .filter_map(|(dest, value)| match value {
+                    Value::Array(ref vs) if vs.len() >= 2 => Some(rule(dest, &vs[0], &vs[1])),
+                    _ => None,
+                })
+                .collect::<RedisResult<_>>()?,
+            _ => vec![],
+        };
 
-                result.rules = match map.get("rules") {
-                    Some(Value::Bulk(ref values)) => values
-                        .iter()
-                        .flat_map(|value| match value {
-                            Value::Bulk(ref vs) => Some((
-                                from_redis_value(&vs[0]).unwrap(),
-                                from_redis_value(&vs[1]).unwrap(),
-                                from_redis_value(&vs[2]).unwrap(),
-                            )),
-                            _ => None,
-                        })
-                        .collect(),
-                    _ => vec![],
-                };
-
-                result.labels = match map.get("labels") {
-                    Some(Value::Bulk(ref values)) => values
-                        .iter()
-                        .flat_map(|value| match value {
-                            Value::Bulk(ref vs) => Some((
-                                from_redis_value(&vs[0]).unwrap(),
-                                from_redis_value(&vs[1]).unwrap(),
-                            )),
-                            _ => None,
-                        })
-                        .collect(),
-                    _ => vec![],
-                };
-
-                Ok(result)
-            }
-            _ => Err(RedisError::from(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "no_ts_info_data",
-            ))),
+        result.labels = match map.get("labels") {
+            Some(v) => labels(v)?,
+            None => vec![],
+        };
+
+        Ok(result)
+    }
+}
+
+/// Returns the text of a string reply.
+fn text(v: &Value) -> Option<&str> {
+    match *v {
+        Value::BulkString(ref bytes) => str::from_utf8(bytes).ok(),
+        Value::SimpleString(ref text) => Some(text),
+        Value::VerbatimString { ref text, .. } => Some(text),
+        _ => None,
+    }
+}
+
+fn rule(dest: &Value, bucket: &Value, aggregation: &Value) -> RedisResult<(String, u64, String)> {
+    Ok((
+        from_redis_value(dest)?,
+        from_redis_value(bucket)?,
+        from_redis_value(aggregation)?,
+    ))
+}
+
+/// A time series sample value that also decodes the special values `nan`,
+/// `inf` and `-inf`, which the server replies eg. for EMPTY buckets or a
+/// `std.s` aggregation of a single sample. Can be used as value type of range
+/// and mget results, eg. `TsRange<u64, TsValue>`, and as value to write.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct TsValue(pub f64);
+
+impl TsValue {
+    /// Returns the value as f64.
+    pub fn value(self) -> f64 {
+        self.0
+    }
+
+    /// Returns true if the value is a number, neither NaN nor infinite.
+    pub fn is_finite(self) -> bool {
+        self.0.is_finite()
+    }
+}
+
+impl From<f64> for TsValue {
+    fn from(value: f64) -> Self {
+        TsValue(value)
+    }
+}
+
+impl From<TsValue> for f64 {
+    fn from(value: TsValue) -> Self {
+        value.0
+    }
+}
+
+impl Display for TsValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// Parses a sample value, including `nan`, `inf` and `-inf` in any case and
+/// with an optional sign.
+pub(crate) fn parse_value(text: &str) -> Option<f64> {
+    text.trim().parse().ok()
+}
+
+impl FromRedisValue for TsValue {
+    fn from_redis_value(v: &Value) -> RedisResult<Self> {
+        let value = match *v {
+            Value::Int(i) => Some(i as f64),
+            Value::BulkString(ref bytes) => str::from_utf8(bytes).ok().and_then(parse_value),
+            Value::SimpleString(ref text) => parse_value(text),
+            Value::Double(d) => Some(d),
+            Value::VerbatimString { ref text, .. } => parse_value(text),
+            _ => None,
+        };
+        value.map(TsValue).ok_or_else(|| {
+            RedisError::from(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid time series value {:?}", v),
+            ))
+        })
+    }
+}
+
+impl ToRedisArgs for TsValue {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        let value = self.0;
+        if value.is_nan() {
+            out.write_arg(b"nan")
+        } else if value == f64::INFINITY {
+            out.write_arg(b"inf")
+        } else if value == f64::NEG_INFINITY {
+            out.write_arg(b"-inf")
+        } else {
+            value.write_redis_args(out)
         }
     }
 }
@@ -771,13 +964,15 @@ pub struct TsMget<TS: FromRedisValue, V: FromRedisValue> {
 
 impl<TS: Default + FromRedisValue, V: Default + FromRedisValue> FromRedisValue for TsMget<TS, V> {
     fn from_redis_value(v: &Value) -> RedisResult<Self> {
-        let res = match *v {
-            Value::Bulk(ref values) => TsMget {
-                values: FromRedisValue::from_redis_values(values)?,
-            },
-            _ => TsMget { values: vec![] },
-        };
-        Ok(res)
+        Ok(TsMget {
+            values: entries(v, TsMgetEntry::from_parts)?,
+        })
+    }
+
+    fn from_owned_redis_value(v: Value) -> RedisResult<Self> {
+        Ok(TsMget {
+            values: owned_entries(v, TsMgetEntry::from_owned_parts)?,
+        })
     }
 }
 
@@ -790,42 +985,49 @@ pub struct TsMgetEntry<TS: FromRedisValue, V: FromRedisValue> {
     pub value: Option<(TS, V)>,
 }
 
+impl<TS: Default + FromRedisValue, V: Default + FromRedisValue> TsMgetEntry<TS, V> {
+    fn from_parts(key: &Value, labels: &Value, sample: &Value) -> RedisResult<Self> {
+        Ok(TsMgetEntry {
+            key: from_redis_value(key)?,
+            labels: self::labels(labels)?,
+            value: match *sample {
+                Value::Array(ref vs) if !vs.is_empty() => Some(self::sample(vs)?),
+                _ => None,
+            },
+        })
+    }
+
+    fn from_owned_parts(key: Value, labels: Value, sample: Value) -> RedisResult<Self> {
+        Ok(TsMgetEntry {
+            key: String::from_owned_redis_value(key)?,
+            labels: owned_labels(labels)?,
+            value: match sample {
+                Value::Array(vs) if !vs.is_empty() => Some(owned_sample(vs)?),
+                _ => None,
+            },
+        })
+    }
+}
+
 impl<TS: Default + FromRedisValue, V: Default + FromRedisValue> FromRedisValue
     for TsMgetEntry<TS, V>
 {
     fn from_redis_value(v: &Value) -> RedisResult<Self> {
         match *v {
-            Value::Bulk(ref values) => {
-                let result = TsMgetEntry::<TS, V> {
-                    key: from_redis_value(&values[0])?,
-                    labels: match values[1] {
-                        Value::Bulk(ref vs) => vs
-                            .iter()
-                            .flat_map(|value| match value {
-                                Value::Bulk(ref v) => Some((
-                                    from_redis_value(&v[0]).unwrap(),
-                                    from_redis_value(&v[1]).unwrap(),
-                                )),
-                                _ => None,
-                            })
-                            .collect(),
-                        _ => vec![],
-                    },
-                    value: match values[2] {
-                        Value::Bulk(ref vs) if !vs.is_empty() => Some((
-                            from_redis_value(&vs[0]).unwrap(),
-                            from_redis_value(&vs[1]).unwrap(),
-                        )),
-                        _ => None,
-                    },
-                };
-
-                Ok(result)
+            Value::Array(ref values) if values.len() >= 3 => {
+                TsMgetEntry::from_parts(&values[0], &values[1], &values[values.len() - 1])
             }
-            _ => Err(RedisError::from(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "no_mget_data",
-            ))),
+            _ => Err(RedisError::from(std::io::Error::other("no_mget_data"))),
+        }
+    }
+
+    fn from_owned_redis_value(v: Value) -> RedisResult<Self> {
+        match v {
+            Value::Array(values) if values.len() >= 3 => {
+                let (key, labels, sample) = owned_parts(values);
+                TsMgetEntry::from_owned_parts(key, labels, sample)
+            }
+            _ => Err(RedisError::from(std::io::Error::other("no_mget_data"))),
         }
     }
 }
@@ -833,23 +1035,26 @@ impl<TS: Default + FromRedisValue, V: Default + FromRedisValue> FromRedisValue
 /// Represents a TS.RANGE redis time series result. The concrete types for timestamp
 /// and value eg <u64,f64> can be provided from the call site.
 #[derive(Debug)]
-pub struct TsRange<TS: FromRedisValue + Copy, V: FromRedisValue + Copy> {
+pub struct TsRange<TS: FromRedisValue, V: FromRedisValue> {
     pub values: Vec<(TS, V)>,
 }
 
-impl<TS: FromRedisValue + Copy, V: FromRedisValue + Copy> FromRedisValue for TsRange<TS, V> {
+impl<TS: FromRedisValue, V: FromRedisValue> FromRedisValue for TsRange<TS, V> {
     fn from_redis_value(v: &Value) -> RedisResult<Self> {
         match *v {
-            Value::Bulk(ref values) => {
-                let items: Vec<TsValueReply<TS, V>> = FromRedisValue::from_redis_values(values)?;
-                Ok(TsRange {
-                    values: items.iter().map(|i| (i.ts, i.value)).collect(),
-                })
-            }
-            _ => Err(RedisError::from(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "no_range_data",
-            ))),
+            Value::Array(ref values) => Ok(TsRange {
+                values: samples(values)?,
+            }),
+            _ => Err(RedisError::from(std::io::Error::other("no_range_data"))),
+        }
+    }
+
+    fn from_owned_redis_value(v: Value) -> RedisResult<Self> {
+        match v {
+            Value::Array(values) => Ok(TsRange {
+                values: owned_samples(values)?,
+            }),
+            _ => Err(RedisError::from(std::io::Error::other("no_range_data"))),
         }
     }
 }
@@ -857,93 +1062,273 @@ impl<TS: FromRedisValue + Copy, V: FromRedisValue + Copy> FromRedisValue for TsR
 /// Represents a TS.MRANGE redis time series result with multiple entries. The concrete types for timestamp
 /// and value eg <u64,f64> can be provided from the call site.
 #[derive(Debug)]
-pub struct TsMrange<TS: FromRedisValue + Copy, V: FromRedisValue + Copy> {
+pub struct TsMrange<TS: FromRedisValue, V: FromRedisValue> {
     pub values: Vec<TsMrangeEntry<TS, V>>,
 }
 
-impl<TS: Default + FromRedisValue + Copy, V: Default + FromRedisValue + Copy> FromRedisValue
-    for TsMrange<TS, V>
-{
+impl<TS: Default + FromRedisValue, V: Default + FromRedisValue> FromRedisValue for TsMrange<TS, V> {
     fn from_redis_value(v: &Value) -> RedisResult<Self> {
-        let res = match *v {
-            Value::Bulk(ref values) => TsMrange {
-                values: FromRedisValue::from_redis_values(values)?,
-            },
-            _ => TsMrange { values: vec![] },
-        };
-        Ok(res)
+        Ok(TsMrange {
+            values: entries(v, TsMrangeEntry::from_parts)?,
+        })
+    }
+
+    fn from_owned_redis_value(v: Value) -> RedisResult<Self> {
+        Ok(TsMrange {
+            values: owned_entries(v, TsMrangeEntry::from_owned_parts)?,
+        })
     }
 }
 
 /// Represents a TS.MRANGE redis time series value. The concrete types for timestamp
 /// and value eg <u64,f64> can be provided from the call site.
 #[derive(Debug, Default)]
-pub struct TsMrangeEntry<TS: FromRedisValue + Copy, V: FromRedisValue + Copy> {
+pub struct TsMrangeEntry<TS: FromRedisValue, V: FromRedisValue> {
     pub key: String,
     pub labels: Vec<(String, String)>,
     pub values: Vec<(TS, V)>,
 }
 
-impl<TS: Default + FromRedisValue + Copy, V: Default + FromRedisValue + Copy> FromRedisValue
+impl<TS: Default + FromRedisValue, V: Default + FromRedisValue> TsMrangeEntry<TS, V> {
+    fn from_parts(key: &Value, labels: &Value, values: &Value) -> RedisResult<Self> {
+        Ok(TsMrangeEntry {
+            key: from_redis_value(key)?,
+            labels: self::labels(labels)?,
+            values: match *values {
+                Value::Array(ref vs) => samples(vs)?,
+                _ => vec![],
+            },
+        })
+    }
+
+    fn from_owned_parts(key: Value, labels: Value, values: Value) -> RedisResult<Self> {
+        Ok(TsMrangeEntry {
+            key: String::from_owned_redis_value(key)?,
+            labels: owned_labels(labels)?,
+            values: match values {
+                Value::Array(vs) => owned_samples(vs)?,
+                _ => vec![],
+            },
+        })
+    }
+}
+
+impl<TS: Default + FromRedisValue, V: Default + FromRedisValue> FromRedisValue
     for TsMrangeEntry<TS, V>
 {
     fn from_redis_value(v: &Value) -> RedisResult<Self> {
         match *v {
-            Value::Bulk(ref values) => {
-                let result = TsMrangeEntry::<TS, V> {
-                    key: from_redis_value(&values[0]).unwrap(),
-                    labels: match values[1] {
-                        Value::Bulk(ref vs) => vs
-                            .iter()
-                            .flat_map(|value| match value {
-                                Value::Bulk(ref v) => Some((
-                                    from_redis_value(&v[0]).unwrap(),
-                                    from_redis_value(&v[1]).unwrap(),
-                                )),
-                                _ => None,
-                            })
-                            .collect(),
-                        _ => vec![],
-                    },
-                    values: match values[2] {
-                        Value::Bulk(ref vs) => {
-                            let items: Vec<TsValueReply<TS, V>> =
-                                FromRedisValue::from_redis_values(vs)?;
-                            items.iter().map(|i| (i.ts, i.value)).collect()
-                        }
-                        _ => vec![],
-                    },
-                };
-
-                Ok(result)
+            Value::Array(ref values) if values.len() >= 3 => {
+                TsMrangeEntry::from_parts(&values[0], &values[1], &values[values.len() - 1])
             }
-            _ => Err(RedisError::from(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "no_mget_data",
-            ))),
+            _ => Err(RedisError::from(std::io::Error::other("no_mget_data"))),
+        }
+    }
+
+    fn from_owned_redis_value(v: Value) -> RedisResult<Self> {
+        match v {
+            Value::Array(values) if values.len() >= 3 => {
+                let (key, labels, samples) = owned_parts(values);
+                TsMrangeEntry::from_owned_parts(key, labels, samples)
+            }
+            _ => Err(RedisError::from(std::io::Error::other("no_mget_data"))),
         }
     }
 }
 
-#[derive(Debug)]
-struct TsValueReply<TS: FromRedisValue, V: FromRedisValue> {
-    pub ts: TS,
-    pub value: V,
+fn no_value_data() -> RedisError {
+    RedisError::from(std::io::Error::other("no_value_data"))
 }
 
-impl<TS: FromRedisValue, V: FromRedisValue> FromRedisValue for TsValueReply<TS, V> {
-    fn from_redis_value(v: &Value) -> RedisResult<Self> {
-        match *v {
-            Value::Bulk(ref values) => Ok(TsValueReply {
-                ts: from_redis_value(&values[0]).unwrap(),
-                value: from_redis_value(&values[1]).unwrap(),
-            }),
-            _ => Err(RedisError::from(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "no_value_data",
+fn no_entry_data() -> RedisError {
+    RedisError::from(std::io::Error::other("no_entry_data"))
+}
+
+/// Splits an owned multi series entry into its first, second and last
+/// element. The entry must have at least three elements.
+fn owned_parts(values: Vec<Value>) -> (Value, Value, Value) {
+    let mut values = values.into_iter();
+    let first = values.next().unwrap_or(Value::Nil);
+    let second = values.next().unwrap_or(Value::Nil);
+    let last = values.next_back().unwrap_or(Value::Nil);
+    (first, second, last)
+}
+
+/// Decodes the entries of a TS.MGET or TS.MRANGE reply. Under RESP2 these
+/// are arrays of `[key, labels, samples]`, under RESP3 a map of key to
+/// `[labels, metadata..., samples]`. Any other reply has no entries.
+pub(crate) fn entries<T, F>(v: &Value, entry: F) -> RedisResult<Vec<T>>
+where
+    F: Fn(&Value, &Value, &Value) -> RedisResult<T>,
+{
+    match *v {
+        Value::Array(ref values) => values
+            .iter()
+            .map(|value| match *value {
+                Value::Array(ref vs) if vs.len() >= 3 => entry(&vs[0], &vs[1], &vs[vs.len() - 1]),
+                _ => Err(no_entry_data()),
+            })
+            .collect(),
+        Value::Map(ref values) => values
+            .iter()
+            .map(|(key, value)| match *value {
+                Value::Array(ref vs) if vs.len() >= 2 => entry(key, &vs[0], &vs[vs.len() - 1]),
+                _ => Err(no_entry_data()),
+            })
+            .collect(),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Decodes the entries of a TS.MGET or TS.MRANGE reply like entries, taking
+/// ownership of the values.
+pub(crate) fn owned_entries<T, F>(v: Value, entry: F) -> RedisResult<Vec<T>>
+where
+    F: Fn(Value, Value, Value) -> RedisResult<T>,
+{
+    match v {
+        Value::Array(values) => values
+            .into_iter()
+            .map(|value| match value {
+                Value::Array(vs) if vs.len() >= 3 => {
+                    let (key, labels, samples) = owned_parts(vs);
+                    entry(key, labels, samples)
+                }
+                _ => Err(no_entry_data()),
+            })
+            .collect(),
+        Value::Map(values) => values
+            .into_iter()
+            .map(|(key, value)| match value {
+                Value::Array(vs) if vs.len() >= 2 => {
+                    let mut vs = vs.into_iter();
+                    let labels = vs.next().unwrap_or(Value::Nil);
+                    let samples = vs.next_back().unwrap_or(Value::Nil);
+                    entry(key, labels, samples)
+                }
+                _ => Err(no_entry_data()),
+            })
+            .collect(),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Decodes the `[timestamp, value]` pair of a single sample.
+fn sample<TS: FromRedisValue, V: FromRedisValue>(pair: &[Value]) -> RedisResult<(TS, V)> {
+    match pair {
+        [ts, value] => Ok((from_redis_value(ts)?, from_redis_value(value)?)),
+        _ => Err(no_value_data()),
+    }
+}
+
+/// Decodes the `[timestamp, value]` pair of a single sample, taking ownership
+/// of the value.
+fn owned_sample<TS: FromRedisValue, V: FromRedisValue>(pair: Vec<Value>) -> RedisResult<(TS, V)> {
+    let mut pair = pair.into_iter();
+    match (pair.next(), pair.next(), pair.next()) {
+        (Some(ts), Some(value), None) => Ok((
+            TS::from_owned_redis_value(ts)?,
+            V::from_owned_redis_value(value)?,
+        )),
+        _ => Err(no_value_data()),
+    }
+}
+
+/// Decodes a list of samples straight into a preallocated vector.
+fn samples<TS: FromRedisValue, V: FromRedisValue>(values: &[Value]) -> RedisResult<Vec<(TS, V)>> {
+    let mut result = Vec::with_capacity(values.len());
+    for value in values {
+        match *value {
+            Value::Array(ref pair) => result.push(sample(pair)?),
+            _ => return Err(no_value_data()),
+        }
+    }
+    Ok(result)
+}
+
+/// Decodes a list of samples straight into a preallocated vector, taking
+/// ownership of the values.
+fn owned_samples<TS: FromRedisValue, V: FromRedisValue>(
+    values: Vec<Value>,
+) -> RedisResult<Vec<(TS, V)>> {
+    let mut result = Vec::with_capacity(values.len());
+    for value in values {
+        match value {
+            Value::Array(pair) => result.push(owned_sample(pair)?),
+            _ => return Err(no_value_data()),
+        }
+    }
+    Ok(result)
+}
+
+/// Decodes the labels of a multi series reply, a RESP2 array of
+/// `[name, value]` pairs or a RESP3 map. Labels without a value, eg. missing
+/// SELECTED_LABELS, are skipped.
+pub(crate) fn labels(v: &Value) -> RedisResult<Vec<(String, String)>> {
+    let label = |name: &Value, value: &Value| -> RedisResult<Option<(String, String)>> {
+        match *value {
+            Value::Nil => Ok(None),
+            _ => Ok(Some((from_redis_value(name)?, from_redis_value(value)?))),
+        }
+    };
+    let mut result = vec![];
+    match *v {
+        Value::Array(ref values) => {
+            result.reserve(values.len());
+            for value in values {
+                if let Value::Array(ref pair) = *value {
+                    if let [name, value] = pair.as_slice() {
+                        result.extend(label(name, value)?);
+                    }
+                }
+            }
+        }
+        Value::Map(ref values) => {
+            result.reserve(values.len());
+            for (name, value) in values {
+                result.extend(label(name, value)?);
+            }
+        }
+        _ => {}
+    }
+    Ok(result)
+}
+
+/// Decodes the labels of a multi series reply like labels, taking ownership
+/// of the label strings.
+pub(crate) fn owned_labels(v: Value) -> RedisResult<Vec<(String, String)>> {
+    let label = |name: Value, value: Value| -> RedisResult<Option<(String, String)>> {
+        match value {
+            Value::Nil => Ok(None),
+            _ => Ok(Some((
+                String::from_owned_redis_value(name)?,
+                String::from_owned_redis_value(value)?,
             ))),
         }
+    };
+    let mut result = vec![];
+    match v {
+        Value::Array(values) => {
+            result.reserve(values.len());
+            for value in values {
+                if let Value::Array(pair) = value {
+                    let mut pair = pair.into_iter();
+                    if let (Some(name), Some(value), None) = (pair.next(), pair.next(), pair.next())
+                    {
+                        result.extend(label(name, value)?);
+                    }
+                }
+            }
+        }
+        Value::Map(values) => {
+            result.reserve(values.len());
+            for (name, value) in values {
+                result.extend(label(name, value)?);
+            }
+        }
+        _ => {}
     }
+    Ok(result)
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, Copy)]
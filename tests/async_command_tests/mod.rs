@@ -2,23 +2,27 @@ extern crate async_std;
 extern crate redis;
 extern crate redis_ts;
 
-use redis::aio::Connection;
+use futures::StreamExt;
+use futures_timer::Delay;
+use redis::aio::MultiplexedConnection;
 use redis::AsyncCommands;
 use redis_ts::AsyncTsCommands;
 use redis_ts::{
-    TsAggregationType, TsDuplicatePolicy, TsFilterOptions, TsInfo, TsMget, TsMrange, TsOptions,
-    TsRange, TsRangeQuery,
+    ts_changes, ts_follow, ts_mfollow, TsAggregationType, TsCandle, TsChangeEvent, TsChangeKind,
+    TsChangeOptions, TsColumns, TsDuplicatePolicy, TsFilterOptions, TsFollowOptions,
+    TsFollowSample, TsInfo, TsMcandles, TsMcolumns, TsMget, TsMrange, TsOptions, TsRange,
+    TsRangeQuery,
 };
 use std::env;
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-async fn get_con() -> Connection {
+async fn get_con() -> MultiplexedConnection {
     let client = redis::Client::open(get_redis_url()).unwrap();
-    client.get_async_connection().await.unwrap()
+    client.get_multiplexed_async_connection().await.unwrap()
 }
 
-async fn prepare_ts(name: &str) -> Connection {
+async fn prepare_ts(name: &str) -> MultiplexedConnection {
     let mut con = get_con().await;
     let _: () = con.del(name).await.unwrap();
     let _: () = con.ts_create(name, TsOptions::default()).await.unwrap();
@@ -547,6 +551,290 @@ pub async fn ts_queryindex(name: &str) {
     assert!(index.contains(&name.to_string()));
 }
 
+pub async fn ts_candles(name: &str) {
+    let mut con = prepare_ts(name).await;
+    let _: () = con
+        .ts_madd(&[
+            (name, 0, 1.0),
+            (name, 10, 3.0),
+            (name, 20, 0.5),
+            (name, 30, 2.0),
+            (name, 100, 2.0),
+            (name, 150, 4.0),
+        ])
+        .await
+        .unwrap();
+
+    let res: Vec<TsCandle> = con.ts_candles(name, 0, 1000, 100).await.unwrap();
+    assert_eq!(
+        res,
+        vec![
+            TsCandle {
+                ts: 0,
+                open: 1.0,
+                high: 3.0,
+                low: 0.5,
+                close: 2.0,
+                count: 4
+            },
+            TsCandle {
+                ts: 100,
+                open: 2.0,
+                high: 4.0,
+                low: 2.0,
+                close: 4.0,
+                count: 2
+            }
+        ]
+    );
+}
+
+pub async fn ts_mcandles(name: &str) {
+    let name2: &str = &format!("{:}2", name);
+    let label = &format!("{:}label", name);
+
+    let mut con = get_con().await;
+    let _: () = con.del(name).await.unwrap();
+    let _: () = con.del(name2).await.unwrap();
+    let opts: TsOptions = TsOptions::default().label("l", label);
+    let _: () = con.ts_create(name, opts.clone()).await.unwrap();
+    let _: () = con.ts_create(name2, opts.clone()).await.unwrap();
+    let _: () = con
+        .ts_madd(&[
+            (name, 0, 1.0),
+            (name, 50, 3.0),
+            (name2, 10, 5.0),
+            (name2, 20, 4.0),
+            (name2, 110, 6.0),
+        ])
+        .await
+        .unwrap();
+
+    let res: TsMcandles = con
+        .ts_mcandles(
+            0,
+            1000,
+            100,
+            TsFilterOptions::default()
+                .equals("l", label)
+                .with_labels(true),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.values.len(), 2);
+    assert_eq!(res.values[0].key, name);
+    assert_eq!(
+        res.values[0].candles,
+        vec![TsCandle {
+            ts: 0,
+            open: 1.0,
+            high: 3.0,
+            low: 1.0,
+            close: 3.0,
+            count: 2
+        }]
+    );
+    assert_eq!(res.values[1].key, name2);
+    assert_eq!(res.values[1].candles.len(), 2);
+}
+
+pub async fn ts_rate(name: &str) {
+    let mut con = prepare_ts(name).await;
+    let _: () = con
+        .ts_madd(&[
+            (name, 10000, 10.0),
+            (name, 20000, 20.0),
+            (name, 30000, 30.0),
+        ])
+        .await
+        .unwrap();
+
+    let res: TsRange<u64, f64> = con
+        .ts_rate(name, TsRangeQuery::default(), 20000)
+        .await
+        .unwrap();
+    assert_eq!(res.values, vec![(20000, 1.0), (30000, 1.0)]);
+}
+
+pub async fn ts_range_columns(name: &str) {
+    let mut con = prepare_ts(name).await;
+    let _: () = con
+        .ts_madd(&[(name, 10, 1.0), (name, 20, 2.0)])
+        .await
+        .unwrap();
+
+    let res: TsColumns = con
+        .ts_range_columns(name, TsRangeQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(res.timestamps, vec![10, 20]);
+    assert_eq!(res.values, vec![1.0, 2.0]);
+
+    let res: TsMcolumns = con
+        .ts_mrange_columns(
+            TsRangeQuery::default(),
+            TsFilterOptions::default().equals("l", name),
+        )
+        .await
+        .unwrap();
+    assert!(res.values.is_empty());
+}
+
+pub async fn ts_follow_key(name: &str) {
+    let mut con = prepare_ts(name).await;
+    let _: () = con
+        .ts_madd(&[(name, 1000, 1.0), (name, 2000, 2.0)])
+        .await
+        .unwrap();
+    let options = TsFollowOptions::default()
+        .interval(Duration::from_millis(10))
+        .lookback(1500);
+    let mut samples = Box::pin(ts_follow(get_con().await, name, options));
+
+    let (first, _) = futures::join!(samples.next(), async {
+        Delay::new(Duration::from_millis(200)).await;
+        // 1800 is late but inside the lookback, 400 is outside of it
+        let _: () = con
+            .ts_madd(&[(name, 3000, 3.0), (name, 1800, 1.8), (name, 400, 0.4)])
+            .await
+            .unwrap();
+    });
+    assert_eq!(first.unwrap().unwrap(), (1800, 1.8));
+    assert_eq!(samples.next().await.unwrap().unwrap(), (3000, 3.0));
+
+    let (next, _) = futures::join!(samples.next(), async {
+        Delay::new(Duration::from_millis(100)).await;
+        let _: () = con.ts_add(name, 4000, 4.0).await.unwrap();
+    });
+    assert_eq!(next.unwrap().unwrap(), (4000, 4.0));
+}
+
+pub async fn ts_follow_filter(name: &str) {
+    let name2 = &format!("{}2", name);
+    let label = &format!("{}label", name);
+    let mut con = get_con().await;
+    let _: () = con.del(name).await.unwrap();
+    let _: () = con.del(name2).await.unwrap();
+    let opts: TsOptions = TsOptions::default().label("l", label);
+    let _: () = con.ts_create(name, opts.clone()).await.unwrap();
+    let _: () = con.ts_create(name2, opts.clone()).await.unwrap();
+    let _: () = con
+        .ts_madd(&[(name, 10, 1.0), (name2, 20, 2.0), (name, 30, 3.0)])
+        .await
+        .unwrap();
+
+    let filter = TsFilterOptions::default()
+        .equals("l", label)
+        .with_labels(true);
+    let options = TsFollowOptions::default()
+        .interval(Duration::from_millis(10))
+        .from(20);
+    let samples: Vec<TsFollowSample> = ts_mfollow(get_con().await, filter, options)
+        .take(2)
+        .map(|s| s.unwrap())
+        .collect()
+        .await;
+    let labels = vec![("l".to_string(), label.to_string())];
+    assert_eq!(
+        samples,
+        vec![
+            TsFollowSample {
+                key: name2.to_string(),
+                labels: labels.clone(),
+                ts: 20,
+                value: 2.0
+            },
+            TsFollowSample {
+                key: name.to_string(),
+                labels,
+                ts: 30,
+                value: 3.0
+            }
+        ]
+    );
+}
+
+pub async fn ts_follow_lagging(name: &str) {
+    let name2 = &format!("{}2", name);
+    let label = &format!("{}label", name);
+    let mut con = get_con().await;
+    let _: () = con.del(name).await.unwrap();
+    let _: () = con.del(name2).await.unwrap();
+    let opts: TsOptions = TsOptions::default().label("l", label);
+    let _: () = con.ts_create(name, opts.clone()).await.unwrap();
+    let _: () = con.ts_create(name2, opts.clone()).await.unwrap();
+    let _: () = con
+        .ts_madd(&[(name, 10000, 1.0), (name2, 1000, 2.0)])
+        .await
+        .unwrap();
+
+    let filter = TsFilterOptions::default().equals("l", label);
+    let options = TsFollowOptions::default().interval(Duration::from_millis(10));
+    let mut samples = Box::pin(ts_mfollow(get_con().await, filter, options));
+
+    let (first, _) = futures::join!(samples.next(), async {
+        Delay::new(Duration::from_millis(200)).await;
+        // the second series lags behind the first one but is ahead of its own
+        // latest sample
+        let _: () = con.ts_add(name2, 1500, 2.5).await.unwrap();
+    });
+    let sample = first.unwrap().unwrap();
+    assert_eq!((sample.key.as_str(), sample.ts), (name2.as_str(), 1500));
+}
+
+pub async fn ts_changes_filter(name: &str) {
+    assert_eq!(TsChangeKind::from("ts.incrby"), TsChangeKind::IncrBy);
+    assert_eq!(TsChangeKind::from("expired"), TsChangeKind::Removed);
+    assert_eq!(
+        TsChangeKind::from("set"),
+        TsChangeKind::Other("set".to_string())
+    );
+
+    let other = &format!("{}_other", name);
+    let label = &format!("{}label", name);
+    let mut con = get_con().await;
+    let _: () = redis::cmd("CONFIG")
+        .arg("SET")
+        .arg("notify-keyspace-events")
+        .arg("Kgd")
+        .query_async(&mut con)
+        .await
+        .unwrap();
+    let _: () = con.del(name).await.unwrap();
+    let _: () = con.del(other).await.unwrap();
+    let _: () = con.ts_create(other, TsOptions::default()).await.unwrap();
+
+    let client = redis::Client::open(get_redis_url()).unwrap();
+    let pubsub = client.get_async_pubsub().await.unwrap();
+    let options = TsChangeOptions::default()
+        .filter(TsFilterOptions::default().equals("l", label))
+        .fetch_sample(true);
+    let changes = ts_changes(pubsub, get_con().await, options).await.unwrap();
+
+    let _: () = con
+        .ts_create(name, TsOptions::default().label("l", label))
+        .await
+        .unwrap();
+    let _: () = con.ts_add(other, 1, 1.0).await.unwrap();
+    let _: () = con.ts_add(name, 10, 1.5).await.unwrap();
+    let _: () = con.del(name).await.unwrap();
+
+    let events: Vec<TsChangeEvent> = changes.take(3).map(|e| e.unwrap()).collect().await;
+    let event = |kind: TsChangeKind, sample: Option<(u64, f64)>| TsChangeEvent {
+        key: name.to_string(),
+        kind,
+        sample,
+    };
+    assert_eq!(
+        events,
+        vec![
+            event(TsChangeKind::Create, None),
+            event(TsChangeKind::Add, Some((10, 1.5))),
+            event(TsChangeKind::Removed, None),
+        ]
+    );
+}
+
 fn get_redis_url() -> String {
     let redis_host_key = "REDIS_HOST";
     let redis_host_port = "REDIS_PORT";
@@ -0,0 +1,201 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{Commands, Connection, ToRedisArgs};
+use redis_ts::alert::{
+    parse_rules, TsAlertEngine, TsAlertEvent, TsAlertOp, TsAlertRule, TsAlertState, TsManualClock,
+};
+use redis_ts::{
+    TsAggregationType, TsCommands, TsFilterOptions, TsMrange, TsMrangeEntry, TsOptions,
+};
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn args<T: ToRedisArgs>(value: T) -> Vec<String> {
+    value
+        .to_redis_args()
+        .into_iter()
+        .map(|a| String::from_utf8(a).unwrap())
+        .collect()
+}
+
+fn result(values: &[(&str, f64)]) -> TsMrange<u64, f64> {
+    TsMrange {
+        values: values
+            .iter()
+            .map(|(key, value)| TsMrangeEntry {
+                key: key.to_string(),
+                labels: vec![("service".to_string(), "api".to_string())],
+                values: vec![(0, *value)],
+            })
+            .collect(),
+    }
+}
+
+fn states(events: &[TsAlertEvent]) -> Vec<(&str, TsAlertState)> {
+    events.iter().map(|e| (e.key.as_str(), e.state)).collect()
+}
+
+#[test]
+fn test_parse_rules() {
+    let rules = parse_rules(
+        "# load of all api hosts
+        api_load: avg(5m) service=api env!=(dev,test) > 0.9 for 3
+
+        disk_free: min(30s) mount=/data <= 10",
+    )
+    .unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].name, "api_load");
+
+    let (query, filter) = rules[0].query(1_000_000);
+    assert_eq!(
+        args(query),
+        vec![
+            "700000",
+            "999999",
+            "ALIGN",
+            "-",
+            "AGGREGATION",
+            "avg",
+            "300000"
+        ]
+    );
+    assert_eq!(
+        args(filter),
+        vec!["WITHLABELS", "FILTER", "service=api", "env!=(dev,test)"]
+    );
+    let (query, _) = rules[1].query(1_000_000);
+    assert_eq!(args(query)[4..], ["AGGREGATION", "min", "30000"]);
+
+    assert!(parse_rules("avg(5m) service=api > 1").is_err());
+    assert!(parse_rules("load: median(5m) service=api > 1").is_err());
+    assert!(parse_rules("load: avg(5x) service=api > 1").is_err());
+    assert!(parse_rules("load: avg(5m) service=api ~ 1").is_err());
+    assert!(parse_rules("load: avg(5m) > 1").is_err());
+    assert!(parse_rules("load: avg(5m) service=api > 1 for x").is_err());
+}
+
+#[test]
+fn test_state_transitions() {
+    let clock = TsManualClock::new(60000);
+    let rule = TsAlertRule::new(
+        "load",
+        TsFilterOptions::default().equals("service", "api"),
+        TsAggregationType::Avg(60000),
+    )
+    .condition(TsAlertOp::Gt, 0.9)
+    .for_evaluations(3);
+    let mut engine = TsAlertEngine::new(vec![]).clock(clock.clone()).rule(rule);
+
+    let events = engine
+        .apply(vec![result(&[("a", 0.95), ("b", 0.5)])])
+        .unwrap();
+    assert_eq!(states(&events), vec![("a", TsAlertState::Pending)]);
+    assert_eq!(events[0].value, Some(0.95));
+    assert_eq!(events[0].ts, 60000);
+    assert_eq!(
+        events[0].labels,
+        vec![("service".to_string(), "api".to_string())]
+    );
+
+    clock.advance(60000);
+    let events = engine
+        .apply(vec![result(&[("a", 0.99), ("b", 0.95)])])
+        .unwrap();
+    assert_eq!(states(&events), vec![("b", TsAlertState::Pending)]);
+
+    clock.advance(60000);
+    let events = engine
+        .apply(vec![result(&[("a", 0.92), ("b", 0.1)])])
+        .unwrap();
+    assert_eq!(states(&events), vec![("a", TsAlertState::Firing)]);
+    assert_eq!(events[0].ts, 180000);
+    assert_eq!(engine.state("load", "a"), TsAlertState::Firing);
+    assert_eq!(engine.state("load", "b"), TsAlertState::Inactive);
+
+    clock.advance(60000);
+    assert!(engine
+        .apply(vec![result(&[("a", 0.93)])])
+        .unwrap()
+        .is_empty());
+
+    // a series without samples in the window is no longer breached
+    clock.advance(60000);
+    let events = engine.apply(vec![result(&[])]).unwrap();
+    assert_eq!(states(&events), vec![("a", TsAlertState::Resolved)]);
+    assert_eq!(events[0].value, None);
+    assert_eq!(engine.state("load", "a"), TsAlertState::Inactive);
+
+    assert_eq!(
+        states(engine.notifier()),
+        vec![
+            ("a", TsAlertState::Pending),
+            ("b", TsAlertState::Pending),
+            ("a", TsAlertState::Firing),
+            ("a", TsAlertState::Resolved),
+        ]
+    );
+}
+
+#[test]
+fn test_multiple_rules() {
+    let rules = parse_rules(
+        "high: max(1m) service=api > 10
+        low: min(1m) service=api < 1",
+    )
+    .unwrap();
+    let mut engine = TsAlertEngine::new(vec![])
+        .clock(TsManualClock::new(120000))
+        .rules(rules);
+    assert_eq!(engine.queries().len(), 2);
+
+    let events = engine
+        .apply(vec![result(&[("a", 20.0)]), result(&[("a", 0.5)])])
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].rule, "high");
+    assert_eq!(events[0].state, TsAlertState::Firing);
+    assert_eq!(events[1].rule, "low");
+    assert_eq!(events[1].state, TsAlertState::Firing);
+}
+
+#[test]
+fn test_evaluate() {
+    let _: () = get_con().del("test_alert_a").unwrap();
+    let _: () = get_con()
+        .ts_create(
+            "test_alert_a",
+            TsOptions::default().label("alert", "test_alert"),
+        )
+        .unwrap();
+    let _: () = get_con()
+        .ts_madd(&[
+            ("test_alert_a", 10000, 1.0),
+            ("test_alert_a", 20000, 3.0),
+            ("test_alert_a", 70000, 0.0),
+        ])
+        .unwrap();
+
+    let clock = TsManualClock::new(60000);
+    let mut engine = TsAlertEngine::new(vec![])
+        .clock(clock.clone())
+        .rules(parse_rules("avg: avg(1m) alert=test_alert >= 2").unwrap());
+    let events = engine.evaluate(&mut get_con()).unwrap();
+    assert_eq!(
+        states(&events),
+        vec![("test_alert_a", TsAlertState::Firing)]
+    );
+    assert_eq!(events[0].value, Some(2.0));
+
+    clock.set(120000);
+    let events = engine.evaluate(&mut get_con()).unwrap();
+    assert_eq!(
+        states(&events),
+        vec![("test_alert_a", TsAlertState::Resolved)]
+    );
+    assert_eq!(events[0].value, Some(0.0));
+}
@@ -0,0 +1,187 @@
+extern crate redis_ts;
+
+use redis_ts::{TsAnomalyDetector, TsAnomalyInterval, TsMrange, TsMrangeEntry, TsRange};
+
+fn range(values: Vec<f64>) -> TsRange<u64, f64> {
+    TsRange {
+        values: values
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (i as u64 * 10, v))
+            .collect(),
+    }
+}
+
+fn flagged(r: &TsRange<u64, f64>, detector: TsAnomalyDetector) -> Vec<u64> {
+    r.anomaly_scores(detector)
+        .into_iter()
+        .filter(|s| s.anomaly)
+        .map(|s| s.ts)
+        .collect()
+}
+
+#[test]
+fn test_zscore() {
+    let r = range(vec![1.0, 2.0, 1.0, 2.0, 1.0, 2.0, 10.0, 1.0, 2.0]);
+    let detector = TsAnomalyDetector::ZScore {
+        window: 4,
+        threshold: 3.0,
+    };
+    let scores = r.anomaly_scores(detector);
+    assert_eq!(scores.len(), 9);
+    assert_eq!(scores[3].baseline, None);
+    assert_eq!(scores[4].baseline, Some(1.5));
+    assert_eq!(scores[4].score, Some(-1.0));
+    assert_eq!(scores[6].score, Some(17.0));
+    assert_eq!(flagged(&r, detector), vec![60]);
+}
+
+#[test]
+fn test_mad() {
+    // the earlier outlier does not mask the later one
+    let r = range(vec![5.0, 6.0, 5.0, 6.0, 50.0, 5.0, 40.0, 6.0]);
+    let detector = TsAnomalyDetector::Mad {
+        window: 4,
+        threshold: 3.5,
+    };
+    assert_eq!(flagged(&r, detector), vec![40, 60]);
+    let scores = r.anomaly_scores(detector);
+    assert_eq!(scores[4].baseline, Some(5.5));
+    assert!(!scores[7].anomaly);
+}
+
+#[test]
+fn test_ewma() {
+    let r = range(vec![10.0, 11.0, 10.0, 11.0, 10.0, 11.0, 30.0, 10.0]);
+    let detector = TsAnomalyDetector::Ewma {
+        alpha: 0.5,
+        threshold: 3.0,
+    };
+    let scores = r.anomaly_scores(detector);
+    // the first 1 / alpha samples warm up the average
+    assert_eq!(scores[0].baseline, None);
+    assert_eq!(scores[1].baseline, None);
+    assert_eq!(scores[1].score, None);
+    assert_eq!(scores[2].baseline, Some(10.5));
+    assert_eq!(scores[2].score, Some(-1.0));
+    assert!(scores[6].anomaly);
+    assert!(!scores[5].anomaly);
+}
+
+#[test]
+fn test_ewma_warm_up() {
+    // noisy first samples are not flagged against a baseline of a single sample
+    let r = range(vec![
+        10.0, 30.0, 5.0, 25.0, 10.0, 20.0, 15.0, 12.0, 18.0, 14.0, 15.0, 16.0, 15.0, 100.0,
+    ]);
+    let detector = TsAnomalyDetector::Ewma {
+        alpha: 0.1,
+        threshold: 3.0,
+    };
+    let scores = r.anomaly_scores(detector);
+    assert!(scores[..10].iter().all(|s| s.score.is_none()));
+    assert!(scores[10].score.is_some());
+    assert_eq!(flagged(&r, detector), vec![130]);
+}
+
+#[test]
+fn test_seasonal() {
+    let day = 86_400_000;
+    let r = TsRange {
+        values: vec![
+            (0, 100.0),
+            (1000, 10.0),
+            (day + 50, 110.0),
+            (day + 1000, 10.0),
+            (2 * day + 20, 100.0),
+            (2 * day + 1000, 30.0),
+        ],
+    };
+    let detector = TsAnomalyDetector::daily(2, 100, 0.5);
+    let scores = r.anomaly_scores(detector);
+    assert_eq!(scores[0].baseline, None);
+    assert_eq!(scores[2].baseline, Some(100.0));
+    assert_eq!(scores[4].baseline, Some(105.0));
+    assert_eq!(scores[5].baseline, Some(10.0));
+    assert_eq!(scores[5].score, Some(2.0));
+    assert_eq!(
+        r.anomalies(detector),
+        vec![TsAnomalyInterval {
+            start: 2 * day + 1000,
+            end: 2 * day + 1000,
+            samples: 1,
+            max_score: 2.0
+        }]
+    );
+    assert_eq!(
+        TsAnomalyDetector::weekly(1, 0, 0.5),
+        TsAnomalyDetector::Seasonal {
+            period: 7 * day,
+            seasons: 1,
+            tolerance: 0,
+            threshold: 0.5
+        }
+    );
+}
+
+#[test]
+fn test_intervals() {
+    let r = range(vec![
+        1.0,
+        1.0,
+        1.0,
+        5.0,
+        f64::NAN,
+        6.0,
+        1.0,
+        1.0,
+        1.0,
+        1.0,
+        -3.0,
+    ]);
+    let detector = TsAnomalyDetector::Mad {
+        window: 3,
+        threshold: 3.5,
+    };
+    let intervals = r.anomalies(detector);
+    assert_eq!(intervals.len(), 2);
+    assert_eq!((intervals[0].start, intervals[0].end), (30, 50));
+    assert_eq!(intervals[0].samples, 2);
+    assert_eq!(intervals[0].max_score, f64::INFINITY);
+    assert_eq!((intervals[1].start, intervals[1].end), (100, 100));
+}
+
+#[test]
+fn test_descending_and_mrange() {
+    let mut values = range(vec![1.0, 2.0, 1.0, 2.0, 9.0]).values;
+    values.reverse();
+    let r = TsRange {
+        values: values.clone(),
+    };
+    let detector = TsAnomalyDetector::ZScore {
+        window: 4,
+        threshold: 3.0,
+    };
+    assert_eq!(flagged(&r, detector), vec![40]);
+    assert_eq!(r.anomaly_scores(detector)[0].ts, 0);
+
+    let mrange = TsMrange {
+        values: vec![
+            TsMrangeEntry {
+                key: "a".to_string(),
+                labels: vec![],
+                values,
+            },
+            TsMrangeEntry {
+                key: "b".to_string(),
+                labels: vec![],
+                values: vec![(0, 1.0)],
+            },
+        ],
+    };
+    let anomalies = mrange.anomalies(detector);
+    assert_eq!(anomalies[0].0, "a");
+    assert_eq!(anomalies[0].1.len(), 1);
+    assert_eq!(anomalies[1], ("b".to_string(), vec![]));
+    assert_eq!(mrange.values[1].anomaly_scores(detector)[0].score, None);
+}
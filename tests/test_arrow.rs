@@ -0,0 +1,170 @@
+extern crate redis;
+extern crate redis_ts;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int32Type, TimestampMillisecondType};
+use arrow_array::Array;
+use arrow_schema::{DataType, TimeUnit};
+use redis::{Commands, Connection};
+use redis_ts::arrow::{
+    mget_to_record_batch, mrange_entry_to_record_batch, mrange_to_record_batch,
+    range_record_batches, range_to_record_batch, TsRecordBatchBuilder, KEY_METADATA,
+};
+use redis_ts::{
+    TsAggregationType, TsCommands, TsMget, TsMgetEntry, TsMrange, TsMrangeEntry, TsOptions,
+    TsRange, TsRangeQuery,
+};
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn entry(key: &str, labels: Vec<(&str, &str)>, values: Vec<(u64, f64)>) -> TsMrangeEntry<u64, f64> {
+    TsMrangeEntry {
+        key: key.to_string(),
+        labels: labels
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        values,
+    }
+}
+
+#[test]
+fn test_range_to_record_batch() {
+    let range = TsRange {
+        values: vec![(1000, 1.5), (2000, 2.5)],
+    };
+    let batch = range_to_record_batch(&range).unwrap();
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(
+        batch.schema().field(0).data_type(),
+        &DataType::Timestamp(TimeUnit::Millisecond, None)
+    );
+    let ts = batch.column(0).as_primitive::<TimestampMillisecondType>();
+    assert_eq!(ts.values().to_vec(), vec![1000, 2000]);
+    let values = batch.column(1).as_primitive::<Float64Type>();
+    assert_eq!(values.values().to_vec(), vec![1.5, 2.5]);
+
+    let empty = range_to_record_batch(&TsRange { values: vec![] }).unwrap();
+    assert_eq!(empty.num_rows(), 0);
+    assert_eq!(empty.num_columns(), 2);
+}
+
+#[test]
+fn test_mrange_entry_metadata() {
+    let e = entry("temp_1", vec![("sensor", "temp")], vec![(1, 1.0)]);
+    let batch = mrange_entry_to_record_batch(&e).unwrap();
+    let schema = batch.schema();
+    assert_eq!(schema.metadata().get(KEY_METADATA).unwrap(), "temp_1");
+    assert_eq!(
+        schema.metadata().get("redis_ts.label.sensor").unwrap(),
+        "temp"
+    );
+}
+
+#[test]
+fn test_mrange_to_record_batch() {
+    let mrange = TsMrange {
+        values: vec![
+            entry("a", vec![("sensor", "temp")], vec![(1, 1.0), (2, 2.0)]),
+            entry("b", vec![("room", "kitchen")], vec![(3, 3.0)]),
+        ],
+    };
+    let batch = mrange_to_record_batch(&mrange).unwrap();
+    assert_eq!(batch.num_rows(), 3);
+    let names: Vec<String> = batch
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name().clone())
+        .collect();
+    assert_eq!(names, vec!["key", "room", "sensor", "timestamp", "value"]);
+
+    let keys = batch.column(0).as_dictionary::<Int32Type>();
+    let key_values = keys.values().as_string::<i32>();
+    let resolved: Vec<&str> = keys
+        .keys()
+        .values()
+        .iter()
+        .map(|k| key_values.value(*k as usize))
+        .collect();
+    assert_eq!(resolved, vec!["a", "a", "b"]);
+
+    let room = batch.column(1).as_dictionary::<Int32Type>();
+    assert!(room.is_null(0));
+    assert!(room.is_valid(2));
+}
+
+#[test]
+fn test_mget_to_record_batch() {
+    let mget = TsMget {
+        values: vec![
+            TsMgetEntry {
+                key: "a".to_string(),
+                labels: vec![],
+                value: Some((10, 1.0)),
+            },
+            TsMgetEntry {
+                key: "b".to_string(),
+                labels: vec![],
+                value: None,
+            },
+        ],
+    };
+    let batch = mget_to_record_batch(&mget).unwrap();
+    assert_eq!(batch.num_rows(), 2);
+    let values = batch.column(2).as_primitive::<Float64Type>();
+    assert_eq!(values.value(0), 1.0);
+    assert!(values.is_null(1));
+}
+
+#[test]
+fn test_record_batch_builder() {
+    let mut builder = TsRecordBatchBuilder::new(2);
+    let first = builder.append(&[(1, 1.0)]).unwrap();
+    assert!(first.is_empty());
+    let second = builder
+        .append(&[(2, 2.0), (3, 3.0), (4, 4.0), (5, 5.0)])
+        .unwrap();
+    assert_eq!(second.len(), 2);
+    assert!(second.iter().all(|b| b.num_rows() == 2));
+    let rest = builder.finish().unwrap().unwrap();
+    assert_eq!(rest.num_rows(), 1);
+    assert!(builder.finish().unwrap().is_none());
+}
+
+#[test]
+fn test_range_record_batches() {
+    let key = "test_arrow_range_record_batches";
+    let mut con = get_con();
+    let _: () = con.del(key).unwrap();
+    let _: () = con.ts_create(key, TsOptions::default()).unwrap();
+    let samples: Vec<(&str, u64, f64)> = (1..=5).map(|ts| (key, ts, ts as f64)).collect();
+    let _: Vec<u64> = con.ts_madd(&samples).unwrap();
+
+    let rows = |query: TsRangeQuery| -> Vec<usize> {
+        range_record_batches(&mut get_con(), key, query, 2)
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .collect()
+    };
+    assert_eq!(rows(TsRangeQuery::default()), vec![2, 2, 1]);
+    assert_eq!(rows(TsRangeQuery::default().count(3)), vec![2, 1]);
+    assert_eq!(rows(TsRangeQuery::default().from(4)), vec![2]);
+
+    let batches: Vec<_> = range_record_batches(&mut con, key, TsRangeQuery::default(), 2)
+        .unwrap()
+        .collect();
+    let ts = batches[2].as_ref().unwrap().column(0);
+    assert_eq!(
+        ts.as_primitive::<TimestampMillisecondType>()
+            .values()
+            .to_vec(),
+        vec![5]
+    );
+
+    let aggregated = TsRangeQuery::default().aggregation_type(TsAggregationType::Sum(2));
+    assert!(range_record_batches(&mut con, key, aggregated, 2).is_err());
+}
@@ -125,3 +125,43 @@ fn test_ts_mrevrange() {
 fn test_ts_queryindex() {
     let _: () = block_on(ts_queryindex("async_test_ts_queryindex_std"));
 }
+
+#[test]
+fn test_ts_candles() {
+    let _: () = block_on(ts_candles("async_test_ts_candles_std"));
+}
+
+#[test]
+fn test_ts_mcandles() {
+    let _: () = block_on(ts_mcandles("async_test_ts_mcandles_std"));
+}
+
+#[test]
+fn test_ts_rate() {
+    let _: () = block_on(ts_rate("async_test_ts_rate_std"));
+}
+
+#[test]
+fn test_ts_range_columns() {
+    let _: () = block_on(ts_range_columns("async_test_ts_range_columns_std"));
+}
+
+#[test]
+fn test_ts_follow_key() {
+    let _: () = block_on(ts_follow_key("async_test_ts_follow_key_std"));
+}
+
+#[test]
+fn test_ts_follow_filter() {
+    let _: () = block_on(ts_follow_filter("async_test_ts_follow_filter_std"));
+}
+
+#[test]
+fn test_ts_follow_lagging() {
+    let _: () = block_on(ts_follow_lagging("async_test_ts_follow_lagging_std"));
+}
+
+#[test]
+fn test_ts_changes_filter() {
+    let _: () = block_on(ts_changes_filter("async_test_ts_changes_filter_std"));
+}
@@ -126,3 +126,85 @@ fn test_ts_mrevrange() {
 fn test_ts_queryindex() {
     let _: () = block_on(ts_queryindex("async_test_ts_queryindex_tokio"));
 }
+
+#[test]
+fn test_ts_candles() {
+    let _: () = block_on(ts_candles("async_test_ts_candles_tokio"));
+}
+
+#[test]
+fn test_ts_mcandles() {
+    let _: () = block_on(ts_mcandles("async_test_ts_mcandles_tokio"));
+}
+
+#[test]
+fn test_ts_rate() {
+    let _: () = block_on(ts_rate("async_test_ts_rate_tokio"));
+}
+
+#[test]
+fn test_ts_range_columns() {
+    let _: () = block_on(ts_range_columns("async_test_ts_range_columns_tokio"));
+}
+
+#[test]
+fn test_ts_follow_key() {
+    let _: () = block_on(ts_follow_key("async_test_ts_follow_key_tokio"));
+}
+
+#[test]
+fn test_ts_follow_filter() {
+    let _: () = block_on(ts_follow_filter("async_test_ts_follow_filter_tokio"));
+}
+
+#[test]
+fn test_ts_follow_lagging() {
+    let _: () = block_on(ts_follow_lagging("async_test_ts_follow_lagging_tokio"));
+}
+
+#[test]
+fn test_ts_changes_filter() {
+    let _: () = block_on(ts_changes_filter("async_test_ts_changes_filter_tokio"));
+}
+
+#[cfg(feature = "connection-manager")]
+#[test]
+fn test_connection_manager() {
+    use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+    use redis::AsyncCommands;
+    use redis_ts::{AsyncTsCommands, TsFilterOptions, TsMrange, TsOptions, TsRange, TsRangeQuery};
+
+    let name = "async_test_connection_manager_tokio";
+    let mut builder = tokio::runtime::Builder::new_current_thread();
+    let runtime = builder.enable_all().build().unwrap();
+    runtime.block_on(async {
+        let client = redis::Client::open("redis://localhost/").unwrap();
+        let config = ConnectionManagerConfig::new().set_number_of_retries(1);
+        let mut con = ConnectionManager::new_with_config(client, config)
+            .await
+            .unwrap();
+        let _: () = con.del(name).await.unwrap();
+        let _: () = con
+            .ts_create(name, TsOptions::default().label("manager", name))
+            .await
+            .unwrap();
+        let _: () = con
+            .ts_madd(&[(name, 10, 1.0), (name, 20, 2.0)])
+            .await
+            .unwrap();
+
+        // a clone shares the underlying connection
+        let mut other = con.clone();
+        let range: TsRange<u64, f64> = other.ts_range(name, TsRangeQuery::default()).await.unwrap();
+        assert_eq!(range.values, vec![(10, 1.0), (20, 2.0)]);
+
+        let mrange: TsMrange<u64, f64> = con
+            .ts_mrange(
+                TsRangeQuery::default(),
+                TsFilterOptions::default().equals("manager", name),
+            )
+            .await
+            .unwrap();
+        assert_eq!(mrange.values.len(), 1);
+    });
+}
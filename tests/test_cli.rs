@@ -0,0 +1,138 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{Commands, Connection};
+use serde_json::Value;
+use std::process::{Command, Output};
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn redis_ts(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_redis-ts"))
+        .args(["--url", "redis://localhost/"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(args: &[&str]) -> String {
+    let output = redis_ts(args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn stderr(args: &[&str]) -> String {
+    let output = redis_ts(args);
+    assert!(!output.status.success());
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn test_help() {
+    let help = stdout(&["--help"]);
+    for command in [
+        "info", "list", "range", "mget", "create", "alter", "rule", "import", "export", "tail",
+    ] {
+        assert!(help.contains(command), "{} missing in help", command);
+    }
+}
+
+#[test]
+fn test_invalid_arguments() {
+    assert!(stderr(&["range", "key", "--agg", "median:1000"]).contains("unknown aggregation"));
+    assert!(stderr(&["range", "key", "--agg", "avg"]).contains("name:bucket"));
+    assert!(stderr(&["range", "key", "--from", "yesterday"]).contains("invalid time"));
+    assert!(stderr(&["range", "key", "--filter", "a=b"]).contains("cannot be used"));
+    assert!(stderr(&["range"]).contains("required"));
+    assert!(stderr(&["create", "key", "--label", "name"]).contains("name=value"));
+}
+
+#[test]
+fn test_commands() {
+    let _: () = get_con().del("test_cli_a").unwrap();
+    let _: () = get_con().del("test_cli_a:avg").unwrap();
+    let path = std::env::temp_dir().join("redis_ts_test_cli.csv");
+    let file = path.to_str().unwrap();
+    std::fs::write(
+        &path,
+        "key,timestamp,value\ntest_cli_a,1000,1.5\ntest_cli_a,2000,2.5\n",
+    )
+    .unwrap();
+
+    stdout(&[
+        "create",
+        "test_cli_a",
+        "--retention",
+        "600000",
+        "--label",
+        "app=test_cli",
+    ]);
+    let created: Value = serde_json::from_str(&stdout(&[
+        "create",
+        "test_cli_a:avg",
+        "--label",
+        "app=test_cli_avg",
+        "--json",
+    ]))
+    .unwrap();
+    assert_eq!(created["created"], "test_cli_a:avg");
+    let added: Value = serde_json::from_str(&stdout(&[
+        "--json",
+        "rule",
+        "add",
+        "test_cli_a",
+        "test_cli_a:avg",
+        "--agg",
+        "avg:60000",
+    ]))
+    .unwrap();
+    assert_eq!(added["added"]["dest"], "test_cli_a:avg");
+    assert_eq!(stdout(&["import", "csv", file]), "imported 2 samples\n");
+
+    let info: Value = serde_json::from_str(&stdout(&["--json", "info", "test_cli_a"])).unwrap();
+    assert_eq!(info["totalSamples"], 2);
+    assert_eq!(info["retentionTime"], 600000);
+    assert_eq!(info["labels"]["app"], "test_cli");
+    assert_eq!(info["rules"][0]["dest"], "test_cli_a:avg");
+
+    let table = stdout(&["list", "--filter", "app=test_cli"]);
+    assert_eq!(table, "key\n----------\ntest_cli_a\n");
+
+    let range: Value = serde_json::from_str(&stdout(&[
+        "range",
+        "--filter",
+        "app=test_cli",
+        "--from",
+        "1970-01-01T00:00:01.500Z",
+        "--json",
+    ]))
+    .unwrap();
+    assert_eq!(range[0]["key"], "test_cli_a");
+    assert_eq!(range[0]["samples"], serde_json::json!([[2000, 2.5]]));
+
+    let mget = stdout(&["mget", "--filter", "app=test_cli"]);
+    assert!(mget.contains("test_cli_a  2000       2.5    app=test_cli"));
+
+    stdout(&["export", "csv", "test_cli_a", "--output", file]);
+    let exported = "key,labels,timestamp,value\ntest_cli_a,,1000,1.5\ntest_cli_a,,2000,2.5\n";
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), exported);
+    // a failing query keeps the previous output
+    stderr(&["export", "csv", "--filter", "app", "--output", file]);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), exported);
+
+    stdout(&["rule", "del", "test_cli_a", "test_cli_a:avg"]);
+    assert_eq!(
+        stdout(&["alter", "test_cli_a", "--retention", "0"]),
+        "altered test_cli_a\n"
+    );
+    let info: Value = serde_json::from_str(&stdout(&["info", "test_cli_a", "--json"])).unwrap();
+    assert_eq!(info["rules"], serde_json::json!([]));
+    assert_eq!(info["retentionTime"], 0);
+}
@@ -0,0 +1,205 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{FromRedisValue, Value};
+use redis_ts::{TsColumns, TsInfo, TsMcolumns, TsMrange, TsMrangeEntry, TsRange, TsValue};
+
+fn samples(values: &[(i64, &str)]) -> Value {
+    Value::Array(
+        values
+            .iter()
+            .map(|(ts, v)| {
+                Value::Array(vec![
+                    Value::Int(*ts),
+                    Value::BulkString(v.as_bytes().to_vec()),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn data(text: &str) -> Value {
+    Value::BulkString(text.as_bytes().to_vec())
+}
+
+fn mrange() -> Value {
+    Value::Array(vec![
+        Value::Array(vec![
+            data("a"),
+            Value::Array(vec![Value::Array(vec![data("host"), data("a")])]),
+            samples(&[(1, "1"), (2, "2.5")]),
+        ]),
+        Value::Array(vec![
+            data("b"),
+            Value::Array(vec![]),
+            samples(&[(3, "-inf")]),
+        ]),
+    ])
+}
+
+#[test]
+fn test_columns_decode() {
+    let reply = samples(&[(1, "1.5"), (2, "nan"), (3, "inf")]);
+    let columns = TsColumns::from_redis_value(&reply).unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(columns.timestamps, vec![1, 2, 3]);
+    assert_eq!(columns.values[0], 1.5);
+    assert!(columns.values[1].is_nan());
+    assert_eq!(columns.values[2], f64::INFINITY);
+    assert!(TsColumns::from_redis_value(&Value::Nil).is_err());
+    assert!(TsColumns::from_redis_value(&samples(&[(1, "x")])).is_err());
+    assert!(TsColumns::from_redis_value(&Value::Array(vec![]))
+        .unwrap()
+        .is_empty());
+
+    let mcolumns = TsMcolumns::from_redis_value(&mrange()).unwrap();
+    assert_eq!(mcolumns.values.len(), 2);
+    assert_eq!(mcolumns.values[0].key, "a");
+    assert_eq!(
+        mcolumns.values[0].labels,
+        vec![("host".to_string(), "a".to_string())]
+    );
+    assert_eq!(mcolumns.values[0].columns.values, vec![1.0, 2.5]);
+    assert_eq!(mcolumns.values[1].columns.timestamps, vec![3]);
+    assert_eq!(
+        TsMcolumns::from_owned_redis_value(mrange()).unwrap(),
+        mcolumns
+    );
+}
+
+#[test]
+fn test_owned_decode_matches_borrowed() {
+    let reply = samples(&[(1, "1"), (2, "2")]);
+    let borrowed = TsRange::<u64, f64>::from_redis_value(&reply).unwrap();
+    let owned = TsRange::<u64, f64>::from_owned_redis_value(reply).unwrap();
+    assert_eq!(borrowed.values, owned.values);
+
+    let borrowed = TsMrange::<u64, String>::from_redis_value(&mrange()).unwrap();
+    let owned = TsMrange::<u64, String>::from_owned_redis_value(mrange()).unwrap();
+    assert_eq!(borrowed.values.len(), owned.values.len());
+    for (b, o) in borrowed.values.iter().zip(owned.values.iter()) {
+        assert_eq!(b.key, o.key);
+        assert_eq!(b.labels, o.labels);
+        assert_eq!(b.values, o.values);
+    }
+    assert!(
+        TsRange::<u64, f64>::from_owned_redis_value(Value::Array(vec![Value::Int(1)])).is_err()
+    );
+}
+
+#[test]
+fn test_info_decode() {
+    let status = |text: &str| Value::SimpleString(text.to_string());
+    // field names are replied as bulk or as simple strings
+    for name in [data, status].iter() {
+        let reply = Value::Array(vec![
+            name("totalSamples"),
+            Value::Int(3),
+            name("lastTimestamp"),
+            Value::Int(30),
+            name("labels"),
+            Value::Array(vec![Value::Array(vec![data("host"), data("a")])]),
+            name("rules"),
+            Value::Array(vec![]),
+        ]);
+        let info = TsInfo::from_redis_value(&reply).unwrap();
+        assert_eq!(info.total_samples, 3);
+        assert_eq!(info.last_timestamp, 30);
+        assert_eq!(info.labels, vec![("host".to_string(), "a".to_string())]);
+    }
+}
+
+fn columns(count: u64) -> TsColumns {
+    (0..count).map(|i| (i * 10, i as f64)).collect()
+}
+
+#[test]
+fn test_columns_conversion() {
+    let range: TsRange<u64, f64> = TsRange {
+        values: vec![(1, 1.0), (2, 2.0)],
+    };
+    let columns = range.columns();
+    assert_eq!(columns.timestamps, vec![1, 2]);
+    assert_eq!(columns.values, vec![1.0, 2.0]);
+    assert_eq!(TsColumns::from(range), columns);
+    assert_eq!(
+        columns.clone().into_range().values,
+        vec![(1, 1.0), (2, 2.0)]
+    );
+
+    let range: TsRange<u64, TsValue> = TsRange {
+        values: vec![(1, TsValue(0.5))],
+    };
+    assert_eq!(range.columns().values, vec![0.5]);
+
+    let entry: TsMrangeEntry<u64, f32> = TsMrangeEntry {
+        key: "a".to_string(),
+        labels: vec![],
+        values: vec![(3, 1.5)],
+    };
+    assert_eq!(entry.columns().timestamps, vec![3]);
+    assert_eq!(entry.columns().values, vec![1.5]);
+}
+
+#[test]
+fn test_columns_iter_and_search() {
+    let columns = columns(10);
+    assert_eq!(columns.iter().len(), 10);
+    assert_eq!(columns.iter().next_back(), Some((90, 9.0)));
+    assert_eq!((&columns).into_iter().nth(2), Some((20, 2.0)));
+
+    assert_eq!(columns.search(30), Ok(3));
+    assert_eq!(columns.search(35), Err(4));
+    assert_eq!(columns.get(40), Some(4.0));
+    assert_eq!(columns.get(41), None);
+
+    let slice = columns.range(15, 50);
+    assert_eq!(slice.timestamps, &[20, 30, 40, 50]);
+    assert_eq!(slice.values, &[2.0, 3.0, 4.0, 5.0]);
+    assert_eq!(slice.range(30, 30).to_columns().values, vec![3.0]);
+    assert_eq!(slice.get(50), Some(5.0));
+    assert!(columns.range(91, 100).is_empty());
+    assert!(columns.range(50, 10).is_empty());
+    assert_eq!(columns.range(0, u64::MAX).len(), 10);
+}
+
+#[test]
+fn test_columns_stats() {
+    let columns = columns(21);
+    assert_eq!(columns.sum(), 210.0);
+    assert_eq!(columns.mean(), Some(10.0));
+    assert_eq!(columns.min(), Some(0.0));
+    assert_eq!(columns.max(), Some(20.0));
+
+    let slice = columns.range(55, 125);
+    assert_eq!(slice.sum(), 6.0 + 7.0 + 8.0 + 9.0 + 10.0 + 11.0 + 12.0);
+    assert_eq!(slice.min(), Some(6.0));
+    assert_eq!(slice.max(), Some(12.0));
+
+    let empty = TsColumns::default();
+    assert_eq!(empty.sum(), 0.0);
+    assert_eq!(empty.mean(), None);
+    assert_eq!(empty.min(), None);
+    assert_eq!(empty.max(), None);
+
+    let mut special = TsColumns::default();
+    special.push(1, f64::NAN);
+    assert_eq!(special.min(), None);
+    special.push(2, -1.0);
+    special.push(3, f64::INFINITY);
+    assert_eq!(special.min(), Some(-1.0));
+    assert_eq!(special.max(), Some(f64::INFINITY));
+    assert_eq!(special.sum(), f64::INFINITY);
+
+    // NaN values of empty buckets are neither summed up nor counted
+    let mut buckets = TsColumns::default();
+    buckets.push(1, f64::NAN);
+    assert_eq!(buckets.sum(), 0.0);
+    assert_eq!(buckets.mean(), None);
+    for (ts, value) in [(2, 1.0), (3, f64::NAN), (4, 3.0)].iter() {
+        buckets.push(*ts, *value);
+    }
+    assert_eq!(buckets.sum(), 4.0);
+    assert_eq!(buckets.mean(), Some(2.0));
+    assert_eq!(buckets.range(2, 3).mean(), Some(1.0));
+}
@@ -3,8 +3,8 @@ extern crate redis_ts;
 
 use redis::{Commands, Connection, Value};
 use redis_ts::{
-    TsAggregationType, TsCommands, TsDuplicatePolicy, TsFilterOptions, TsInfo, TsMget, TsMrange,
-    TsOptions, TsRange, TsRangeQuery,
+    TsAggregationType, TsCandle, TsColumns, TsCommands, TsDuplicatePolicy, TsFilterOptions, TsInfo,
+    TsMcandles, TsMcolumns, TsMget, TsMrange, TsOptions, TsRange, TsRangeQuery, TsReducer, TsValue,
 };
 
 use std::thread;
@@ -639,6 +639,38 @@ fn test_ts_mrange() {
     assert!(res2.values.is_empty());
 }
 
+#[test]
+fn test_ts_mrange_groupby() {
+    let _: () = get_con().del("test_ts_mrange_groupby").unwrap();
+    let _: () = get_con().del("test_ts_mrange_groupby2").unwrap();
+    let opts: TsOptions = TsOptions::default().label("l", "mrange_groupby");
+    let _: () = get_con()
+        .ts_create("test_ts_mrange_groupby", opts.clone().label("g", "x"))
+        .unwrap();
+    let _: () = get_con()
+        .ts_create("test_ts_mrange_groupby2", opts.label("g", "x"))
+        .unwrap();
+    let _: () = get_con()
+        .ts_madd(&[
+            ("test_ts_mrange_groupby", 12, 1.0),
+            ("test_ts_mrange_groupby", 123, 2.0),
+            ("test_ts_mrange_groupby2", 12, 3.0),
+        ])
+        .unwrap();
+
+    let res: TsMrange<u64, f64> = get_con()
+        .ts_mrange(
+            TsRangeQuery::default(),
+            TsFilterOptions::default()
+                .equals("l", "mrange_groupby")
+                .group_by("g", TsReducer::Sum),
+        )
+        .unwrap();
+    assert_eq!(res.values.len(), 1);
+    assert_eq!(res.values[0].key, "g=x");
+    assert_eq!(res.values[0].values, vec![(12, 4.0), (123, 2.0)]);
+}
+
 #[test]
 fn test_ts_mrevrange() {
     let _: () = get_con().del("test_ts_mrevrange").unwrap();
@@ -704,3 +736,234 @@ fn test_ts_queryindex() {
         .unwrap();
     assert!(index.contains(&"test_ts_queryindex".to_string()));
 }
+
+#[test]
+fn test_ts_candles() {
+    let _: () = get_con().del("test_ts_candles").unwrap();
+    let _: () = get_con()
+        .ts_create("test_ts_candles", default_settings())
+        .unwrap();
+    let _: () = get_con()
+        .ts_madd(&[
+            ("test_ts_candles", 0, 1.0),
+            ("test_ts_candles", 10, 3.0),
+            ("test_ts_candles", 20, 0.5),
+            ("test_ts_candles", 30, 2.0),
+            ("test_ts_candles", 100, 2.0),
+            ("test_ts_candles", 150, 4.0),
+        ])
+        .unwrap();
+
+    let res: Vec<TsCandle> = get_con()
+        .ts_candles("test_ts_candles", 0, 1000, 100)
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![
+            TsCandle {
+                ts: 0,
+                open: 1.0,
+                high: 3.0,
+                low: 0.5,
+                close: 2.0,
+                count: 4
+            },
+            TsCandle {
+                ts: 100,
+                open: 2.0,
+                high: 4.0,
+                low: 2.0,
+                close: 4.0,
+                count: 2
+            }
+        ]
+    );
+
+    let empty: Vec<TsCandle> = get_con()
+        .ts_candles("test_ts_candles", 2000, 3000, 100)
+        .unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_ts_mcandles() {
+    let _: () = get_con().del("test_ts_mcandles").unwrap();
+    let _: () = get_con().del("test_ts_mcandles2").unwrap();
+    let opts: TsOptions = TsOptions::default().label("l", "mcandles");
+    let _: () = get_con()
+        .ts_create("test_ts_mcandles", opts.clone())
+        .unwrap();
+    let _: () = get_con()
+        .ts_create("test_ts_mcandles2", opts.clone())
+        .unwrap();
+    let _: () = get_con()
+        .ts_madd(&[
+            ("test_ts_mcandles", 0, 1.0),
+            ("test_ts_mcandles", 50, 3.0),
+            ("test_ts_mcandles2", 10, 5.0),
+            ("test_ts_mcandles2", 20, 4.0),
+            ("test_ts_mcandles2", 110, 6.0),
+        ])
+        .unwrap();
+
+    let res: TsMcandles = get_con()
+        .ts_mcandles(
+            0,
+            1000,
+            100,
+            TsFilterOptions::default()
+                .equals("l", "mcandles")
+                .with_labels(true),
+        )
+        .unwrap();
+    assert_eq!(res.values.len(), 2);
+    assert_eq!(res.values[0].key, "test_ts_mcandles");
+    assert_eq!(
+        res.values[0].labels,
+        vec![("l".to_string(), "mcandles".to_string())]
+    );
+    assert_eq!(
+        res.values[0].candles,
+        vec![TsCandle {
+            ts: 0,
+            open: 1.0,
+            high: 3.0,
+            low: 1.0,
+            close: 3.0,
+            count: 2
+        }]
+    );
+    assert_eq!(res.values[1].key, "test_ts_mcandles2");
+    assert_eq!(res.values[1].candles.len(), 2);
+    assert_eq!(res.values[1].candles[0].open, 5.0);
+    assert_eq!(res.values[1].candles[0].close, 4.0);
+    assert_eq!(res.values[1].candles[1].count, 1);
+}
+
+#[test]
+fn test_ts_rate() {
+    let _: () = get_con().del("test_ts_rate").unwrap();
+    let _: () = get_con()
+        .ts_create("test_ts_rate", default_settings().retention_time(0))
+        .unwrap();
+    let _: () = get_con()
+        .ts_madd(&[
+            ("test_ts_rate", 10000, 10.0),
+            ("test_ts_rate", 20000, 20.0),
+            ("test_ts_rate", 30000, 30.0),
+        ])
+        .unwrap();
+
+    let res: TsRange<u64, f64> = get_con()
+        .ts_rate("test_ts_rate", TsRangeQuery::default(), 20000)
+        .unwrap();
+    assert_eq!(res.values, vec![(20000, 1.0), (30000, 1.0)]);
+}
+
+#[test]
+fn test_ts_range_special_values() {
+    let _: () = get_con().del("test_ts_range_special").unwrap();
+    let _: () = get_con()
+        .ts_create("test_ts_range_special", default_settings())
+        .unwrap();
+    let _: () = get_con()
+        .ts_madd(&[
+            ("test_ts_range_special", 10, TsValue(1.0)),
+            ("test_ts_range_special", 150, TsValue(2.0)),
+        ])
+        .unwrap();
+
+    let res: TsRange<u64, TsValue> = get_con()
+        .ts_range(
+            "test_ts_range_special",
+            TsRangeQuery::default().aggregation_type(TsAggregationType::StdS(100)),
+        )
+        .unwrap();
+    // the sample standard deviation of a single sample is nan
+    assert_eq!(res.values.len(), 2);
+    assert!(res.values.iter().all(|(_, v)| v.value().is_nan()));
+
+    let raw: TsRange<u64, String> = get_con()
+        .ts_range("test_ts_range_special", TsRangeQuery::default())
+        .unwrap();
+    assert_eq!(raw.values[0], (10, "1".to_string()));
+}
+
+#[test]
+fn test_ts_range_columns() {
+    let _: () = get_con().del("test_ts_range_columns").unwrap();
+    let _: () = get_con()
+        .ts_create(
+            "test_ts_range_columns",
+            TsOptions::default().label("columns", "test_ts_range_columns"),
+        )
+        .unwrap();
+    let _: () = get_con()
+        .ts_madd(&[
+            ("test_ts_range_columns", 10, 1.0),
+            ("test_ts_range_columns", 20, 2.0),
+        ])
+        .unwrap();
+
+    let res: TsColumns = get_con()
+        .ts_range_columns("test_ts_range_columns", TsRangeQuery::default())
+        .unwrap();
+    assert_eq!(res.timestamps, vec![10, 20]);
+    assert_eq!(res.values, vec![1.0, 2.0]);
+
+    let res: TsMcolumns = get_con()
+        .ts_mrange_columns(
+            TsRangeQuery::default(),
+            TsFilterOptions::default()
+                .equals("columns", "test_ts_range_columns")
+                .with_labels(true),
+        )
+        .unwrap();
+    assert_eq!(res.values.len(), 1);
+    assert_eq!(res.values[0].key, "test_ts_range_columns");
+    assert_eq!(res.values[0].columns.values, vec![1.0, 2.0]);
+}
+
+#[test]
+fn test_resp3_replies() {
+    let client = redis::Client::open("redis://localhost/?protocol=resp3").unwrap();
+    let mut con = client.get_connection().expect("Failed to get connection!");
+    let _: () = con.del("test_resp3_replies").unwrap();
+    let _: () = con
+        .ts_create(
+            "test_resp3_replies",
+            default_settings().label("resp3", "test_resp3_replies"),
+        )
+        .unwrap();
+    let _: () = con
+        .ts_madd(&[
+            ("test_resp3_replies", 10, 1.5),
+            ("test_resp3_replies", 20, 2.5),
+        ])
+        .unwrap();
+    let filter = TsFilterOptions::default()
+        .equals("resp3", "test_resp3_replies")
+        .with_labels(true);
+
+    let info: TsInfo = con.ts_info("test_resp3_replies").unwrap();
+    assert_eq!(info.total_samples, 2);
+    assert_eq!(info.labels.len(), 2);
+
+    let range: TsRange<u64, f64> = con
+        .ts_range("test_resp3_replies", TsRangeQuery::default())
+        .unwrap();
+    assert_eq!(range.values, vec![(10, 1.5), (20, 2.5)]);
+
+    let mget: TsMget<u64, f64> = con.ts_mget(filter.clone()).unwrap();
+    assert_eq!(mget.values.len(), 1);
+    assert_eq!(mget.values[0].value, Some((20, 2.5)));
+
+    let mrange: TsMrange<u64, f64> = con
+        .ts_mrange(
+            TsRangeQuery::default(),
+            filter.group_by("resp3", TsReducer::Sum),
+        )
+        .unwrap();
+    assert_eq!(mrange.values.len(), 1);
+    assert_eq!(mrange.values[0].values, vec![(10, 1.5), (20, 2.5)]);
+}
@@ -0,0 +1,206 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{Commands, Connection};
+use redis_ts::io::csv::{
+    import, parse_timestamp, write_mrange, write_range, TsCsvFormat, TsCsvImport, TsTimestampFormat,
+};
+use redis_ts::{
+    TsCommands, TsDuplicatePolicy, TsMrange, TsMrangeEntry, TsOptions, TsRange, TsRangeQuery,
+};
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn mrange() -> TsMrange<u64, f64> {
+    TsMrange {
+        values: vec![
+            TsMrangeEntry {
+                key: "a".to_string(),
+                labels: vec![
+                    ("sensor".to_string(), "temp".to_string()),
+                    ("room".to_string(), "kitchen".to_string()),
+                ],
+                values: vec![(10, 1.5), (20, 2.0)],
+            },
+            TsMrangeEntry {
+                key: "b".to_string(),
+                labels: vec![],
+                values: vec![(20, 3.0)],
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_write_range() {
+    let mut out = vec![];
+    let range = TsRange {
+        values: vec![(10, 1.5), (20, 2.0)],
+    };
+    write_range(&mut out, "my_ts", &range).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "key,labels,timestamp,value\nmy_ts,,10,1.5\nmy_ts,,20,2\n"
+    );
+}
+
+#[test]
+fn test_write_mrange_long() {
+    let mut out = vec![];
+    write_mrange(&mut out, &mrange(), TsCsvFormat::Long).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "key,labels,timestamp,value\n\
+         a,sensor=temp;room=kitchen,10,1.5\n\
+         a,sensor=temp;room=kitchen,20,2\n\
+         b,,20,3\n"
+    );
+}
+
+#[test]
+fn test_write_mrange_wide() {
+    let mut out = vec![];
+    write_mrange(&mut out, &mrange(), TsCsvFormat::Wide).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "timestamp,a,b\n10,1.5,\n20,2,3\n"
+    );
+}
+
+#[test]
+fn test_parse_timestamp() {
+    assert_eq!(
+        parse_timestamp("1234", TsTimestampFormat::Millis).unwrap(),
+        1234
+    );
+    assert_eq!(
+        parse_timestamp("1.5", TsTimestampFormat::Seconds).unwrap(),
+        1500
+    );
+    assert_eq!(
+        parse_timestamp("1970-01-01T00:00:01Z", TsTimestampFormat::Rfc3339).unwrap(),
+        1000
+    );
+    assert_eq!(
+        parse_timestamp("2021-03-04T10:00:00.5Z", TsTimestampFormat::Rfc3339).unwrap(),
+        1614852000500
+    );
+    assert_eq!(
+        parse_timestamp("2021-03-04T12:00:00+02:00", TsTimestampFormat::Rfc3339).unwrap(),
+        1614852000000
+    );
+    assert!(parse_timestamp("yesterday", TsTimestampFormat::Rfc3339).is_err());
+    assert!(parse_timestamp("-5", TsTimestampFormat::Millis).is_err());
+}
+
+#[test]
+fn test_import_long() {
+    let _: () = get_con().del("test_csv_import_long").unwrap();
+    let data = "key,labels,timestamp,value\n\
+                test_csv_import_long,l=csv_long,10,1.5\n\
+                test_csv_import_long,l=csv_long,20,2\n";
+    let config = TsCsvImport::long("key", "timestamp", "value")
+        .labels_column("labels")
+        .options(TsOptions::default().retention_time(0))
+        .batch_size(1);
+    let report = import(&mut get_con(), data.as_bytes(), &config).unwrap();
+    assert_eq!(report.imported, 2);
+    assert!(report.rejected.is_empty());
+
+    let range: TsRange<u64, f64> = get_con()
+        .ts_range("test_csv_import_long", TsRangeQuery::default())
+        .unwrap();
+    assert_eq!(range.values, vec![(10, 1.5), (20, 2.0)]);
+    let info = get_con().ts_info("test_csv_import_long").unwrap();
+    assert_eq!(info.labels, vec![("l".to_string(), "csv_long".to_string())]);
+}
+
+#[test]
+fn test_import_wide() {
+    let _: () = get_con().del("csv:test_csv_import_wide").unwrap();
+    let _: () = get_con().del("csv:test_csv_import_wide2").unwrap();
+    let data = "time;test_csv_import_wide;test_csv_import_wide2\n\
+                1970-01-01T00:00:01Z;1;\n\
+                1970-01-01T00:00:02Z;2;3\n";
+    let config = TsCsvImport::wide("time")
+        .delimiter(b';')
+        .timestamp_format(TsTimestampFormat::Rfc3339)
+        .key_prefix("csv:")
+        .options(TsOptions::default());
+    let report = import(&mut get_con(), data.as_bytes(), &config).unwrap();
+    assert_eq!(report.imported, 3);
+
+    let range: TsRange<u64, f64> = get_con()
+        .ts_range("csv:test_csv_import_wide2", TsRangeQuery::default())
+        .unwrap();
+    assert_eq!(range.values, vec![(2000, 3.0)]);
+}
+
+#[test]
+fn test_write_escaped_labels() {
+    let mrange = TsMrange {
+        values: vec![TsMrangeEntry {
+            key: "a".to_string(),
+            labels: vec![("query".to_string(), "a=1;b\\2".to_string())],
+            values: vec![(10, 1.0)],
+        }],
+    };
+    let mut out = vec![];
+    write_mrange(&mut out, &mrange, TsCsvFormat::Long).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "key,labels,timestamp,value\na,query=a\\=1\\;b\\\\2,10,1\n"
+    );
+}
+
+#[test]
+fn test_import_escaped_labels() {
+    let _: () = get_con().del("test_csv_import_escaped").unwrap();
+    let labels = vec![
+        ("query".to_string(), "a=1;b\\2".to_string()),
+        ("l".to_string(), "csv_escaped".to_string()),
+    ];
+    let mrange = TsMrange {
+        values: vec![TsMrangeEntry {
+            key: "test_csv_import_escaped".to_string(),
+            labels: labels.clone(),
+            values: vec![(10, 1.0)],
+        }],
+    };
+    let mut data = vec![];
+    write_mrange(&mut data, &mrange, TsCsvFormat::Long).unwrap();
+
+    let config = TsCsvImport::long("key", "timestamp", "value")
+        .labels_column("labels")
+        .options(TsOptions::default());
+    let report = import(&mut get_con(), data.as_slice(), &config).unwrap();
+    assert_eq!(report.imported, 1);
+    let info = get_con().ts_info("test_csv_import_escaped").unwrap();
+    assert_eq!(info.labels, labels);
+}
+
+#[test]
+fn test_import_rejected() {
+    let _: () = get_con().del("test_csv_import_rejected").unwrap();
+    let data = "key,timestamp,value\n\
+                test_csv_import_rejected,10,1\n\
+                test_csv_import_rejected,10,2\n\
+                test_csv_import_rejected,20,3\n";
+    let config = TsCsvImport::long("key", "timestamp", "value")
+        .options(TsOptions::default().duplicate_policy(TsDuplicatePolicy::Block))
+        .batch_size(2);
+    let report = import(&mut get_con(), data.as_bytes(), &config).unwrap();
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(report.rejected[0].line, 3);
+    assert_eq!(report.rejected[0].ts, 10);
+    assert_eq!(report.rejected[0].value, 2.0);
+
+    let range: TsRange<u64, f64> = get_con()
+        .ts_range("test_csv_import_rejected", TsRangeQuery::default())
+        .unwrap();
+    assert_eq!(range.values, vec![(10, 1.0), (20, 3.0)]);
+}
@@ -0,0 +1,309 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{Commands, Connection, ToRedisArgs};
+use redis_ts::schema::{TsSchema, TsSeries};
+use redis_ts::{
+    TsAggregationType, TsCommands, TsDuplicatePolicy, TsMget, TsMgetEntry, TsMrange, TsMrangeEntry,
+    TsOptions, TsRangeQuery, TsRecord,
+};
+use std::fmt;
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn args<T: ToRedisArgs>(value: T) -> Vec<String> {
+    value
+        .to_redis_args()
+        .into_iter()
+        .map(|a| String::from_utf8(a).unwrap())
+        .collect()
+}
+
+enum Kind {
+    Temperature,
+    Humidity,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Temperature => write!(f, "temperature"),
+            Kind::Humidity => write!(f, "humidity"),
+        }
+    }
+}
+
+#[derive(redis_ts::TsSeries)]
+#[ts(name = "sensor", key = "sensor:{site}:{kind}", retention = 86400000)]
+#[ts(duplicate_policy = "last")]
+#[ts(compaction(
+    suffix = "1h",
+    aggregation = "avg",
+    bucket = 3600000,
+    retention = 2592000000
+))]
+struct Sensor {
+    site: String,
+    #[ts(values("temperature", "humidity"))]
+    kind: Kind,
+    #[ts(rename = "fw")]
+    firmware: Option<String>,
+    #[ts(skip)]
+    #[allow(dead_code)]
+    note: String,
+}
+
+#[derive(redis_ts::TsSeries)]
+#[ts(key = "meter:{id}", duplicate_policy = "sum")]
+struct Meter {
+    id: u32,
+}
+
+#[test]
+fn test_derive_schema() {
+    assert_eq!(
+        Sensor::schema(),
+        TsSchema::new("sensor", "sensor:{site}:{kind}")
+            .label("site")
+            .label_values("kind", &["temperature", "humidity"])
+            .optional_label("fw")
+            .retention(86400000)
+            .duplicate_policy(TsDuplicatePolicy::Last)
+            .compaction("1h", TsAggregationType::Avg(3600000), 2592000000)
+    );
+    assert_eq!(
+        Meter::schema(),
+        TsSchema::new("meter", "meter:{id}")
+            .label("id")
+            .duplicate_policy(TsDuplicatePolicy::Sum)
+    );
+}
+
+#[test]
+fn test_derive_labels() {
+    let sensor = Sensor {
+        site: "berlin".to_string(),
+        kind: Kind::Humidity,
+        firmware: Some("1.2".to_string()),
+        note: "ignored".to_string(),
+    };
+    assert_eq!(
+        sensor.labels(),
+        vec![
+            ("site".to_string(), "berlin".to_string()),
+            ("kind".to_string(), "humidity".to_string()),
+            ("fw".to_string(), "1.2".to_string()),
+        ]
+    );
+    assert_eq!(sensor.key().unwrap(), "sensor:berlin:humidity");
+    assert!(sensor.options().is_ok());
+
+    let sensor = Sensor {
+        site: "paris".to_string(),
+        kind: Kind::Temperature,
+        firmware: None,
+        note: String::new(),
+    };
+    assert_eq!(sensor.labels().len(), 2);
+    assert_eq!(sensor.key().unwrap(), "sensor:paris:temperature");
+    assert_eq!(Meter { id: 7 }.key().unwrap(), "meter:7");
+}
+
+#[derive(redis_ts::TsRecord, Debug, PartialEq)]
+#[ts(name = "cpu", key = "cpu:{host}:{field}")]
+struct Cpu {
+    #[ts(timestamp)]
+    ts: u64,
+    #[ts(label)]
+    host: String,
+    #[ts(label)]
+    core: Option<u8>,
+    #[ts(value)]
+    user: f64,
+    #[ts(value, rename = "sys")]
+    system: Option<f32>,
+    #[ts(value)]
+    procs: u32,
+    #[ts(skip)]
+    note: String,
+}
+
+#[derive(redis_ts::TsRecord, Debug, PartialEq)]
+struct Temperature {
+    #[ts(timestamp)]
+    at: i64,
+    #[ts(label)]
+    room: String,
+    #[ts(value)]
+    celsius: f64,
+}
+
+fn cpu(ts: u64, host: &str, user: f64, system: Option<f32>) -> Cpu {
+    Cpu {
+        ts,
+        host: host.to_string(),
+        core: None,
+        user,
+        system,
+        procs: 12,
+        note: String::new(),
+    }
+}
+
+fn entry(key: &str, labels: &[(&str, &str)], values: Vec<(u64, f64)>) -> TsMrangeEntry<u64, f64> {
+    TsMrangeEntry {
+        key: key.to_string(),
+        labels: labels
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect(),
+        values,
+    }
+}
+
+#[test]
+fn test_derive_record_samples() {
+    let records = vec![cpu(1000, "a", 0.5, Some(0.25)), cpu(2000, "b", 0.75, None)];
+    assert_eq!(
+        Cpu::samples(&records),
+        vec![
+            ("cpu:a:user".to_string(), 1000, 0.5),
+            ("cpu:a:sys".to_string(), 1000, 0.25),
+            ("cpu:a:procs".to_string(), 1000, 12.0),
+            ("cpu:b:user".to_string(), 2000, 0.75),
+            ("cpu:b:procs".to_string(), 2000, 12.0),
+        ]
+    );
+
+    let series = records[0].series_options(TsOptions::default().retention_time(60000));
+    assert_eq!(series.len(), 3);
+    assert_eq!(series[1].0, "cpu:a:sys");
+    assert_eq!(
+        args(&series[1].1),
+        vec![
+            "RETENTION",
+            "60000",
+            "LABELS",
+            "__record__",
+            "cpu",
+            "__field__",
+            "sys",
+            "host",
+            "a"
+        ]
+    );
+    assert_eq!(
+        args(Cpu::filter()),
+        vec!["WITHLABELS", "FILTER", "__record__=cpu"]
+    );
+
+    let temperature = Temperature {
+        at: 5,
+        room: "attic".to_string(),
+        celsius: 21.5,
+    };
+    assert_eq!(Temperature::record_name(), "temperature");
+    assert_eq!(temperature.key("celsius"), "temperature:attic:celsius");
+    assert_eq!(temperature.timestamp(), 5);
+}
+
+#[test]
+fn test_derive_record_read() {
+    let labels = |field: &'static str, host: &'static str| {
+        vec![("__record__", "cpu"), ("__field__", field), ("host", host)]
+    };
+    let mrange = TsMrange {
+        values: vec![
+            entry(
+                "cpu:a:user",
+                &labels("user", "a"),
+                vec![(1000, 0.5), (2000, 0.6)],
+            ),
+            entry(
+                "cpu:a:procs",
+                &labels("procs", "a"),
+                vec![(1000, 12.0), (2000, 12.0)],
+            ),
+            entry("cpu:a:sys", &labels("sys", "a"), vec![(2000, 0.25)]),
+            entry("cpu:b:user", &labels("user", "b"), vec![(1000, 0.75)]),
+            entry("cpu:b:procs", &labels("procs", "b"), vec![(1000, 12.0)]),
+        ],
+    };
+    assert_eq!(
+        Cpu::from_mrange(&mrange).unwrap(),
+        vec![
+            cpu(1000, "a", 0.5, None),
+            cpu(1000, "b", 0.75, None),
+            cpu(2000, "a", 0.6, Some(0.25)),
+        ]
+    );
+
+    let mget = TsMget {
+        values: vec![
+            TsMgetEntry {
+                key: "cpu:c:user".to_string(),
+                labels: vec![
+                    ("__record__".to_string(), "cpu".to_string()),
+                    ("__field__".to_string(), "user".to_string()),
+                    ("host".to_string(), "c".to_string()),
+                    ("core".to_string(), "3".to_string()),
+                ],
+                value: Some((3000, 0.1)),
+            },
+            TsMgetEntry {
+                key: "cpu:c:procs".to_string(),
+                labels: vec![
+                    ("__record__".to_string(), "cpu".to_string()),
+                    ("__field__".to_string(), "procs".to_string()),
+                    ("core".to_string(), "3".to_string()),
+                    ("host".to_string(), "c".to_string()),
+                ],
+                value: Some((3000, 4.0)),
+            },
+        ],
+    };
+    let records = Cpu::from_mget(&mget).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].core, Some(3));
+    assert_eq!(records[0].procs, 4);
+
+    // a record without its required user value can not be read
+    let partial = TsMrange {
+        values: vec![entry(
+            "cpu:a:procs",
+            &labels("procs", "a"),
+            vec![(1000, 12.0)],
+        )],
+    };
+    assert!(Cpu::from_mrange(&partial).is_err());
+    let invalid = TsMrange {
+        values: vec![entry(
+            "cpu:a:user",
+            &[("__field__", "user"), ("host", "a"), ("core", "x")],
+            vec![(1000, 1.0)],
+        )],
+    };
+    assert!(Cpu::from_mrange(&invalid).is_err());
+}
+
+#[test]
+fn test_derive_record_roundtrip() {
+    let mut con = get_con();
+    let records = vec![cpu(1000, "test_derive", 0.5, Some(0.25))];
+    for (key, options) in records[0].series_options(TsOptions::default()) {
+        let _: () = con.del(key.as_str()).unwrap();
+        let _: () = con.ts_create(key, options).unwrap();
+    }
+    let _: Vec<u64> = con.ts_madd(&Cpu::samples(&records)).unwrap();
+
+    let mrange: TsMrange<u64, f64> = con
+        .ts_mrange(
+            TsRangeQuery::default(),
+            Cpu::filter().equals("host", "test_derive"),
+        )
+        .unwrap();
+    assert_eq!(Cpu::from_mrange(&mrange).unwrap(), records);
+}
@@ -0,0 +1,77 @@
+extern crate redis_ts;
+
+use redis_ts::io::exposition::{TsExposition, TsExpositionFormat, TsMetricName};
+use redis_ts::{TsMget, TsMgetEntry};
+
+fn mget() -> TsMget<u64, f64> {
+    TsMget {
+        values: vec![
+            TsMgetEntry {
+                key: "temp:room-1".to_string(),
+                labels: vec![
+                    ("metric".to_string(), "temperature".to_string()),
+                    ("room".to_string(), "living \"room\"".to_string()),
+                ],
+                value: Some((1500, 21.5)),
+            },
+            TsMgetEntry {
+                key: "temp:room-2".to_string(),
+                labels: vec![
+                    ("metric".to_string(), "temperature".to_string()),
+                    ("room".to_string(), "kitchen".to_string()),
+                ],
+                value: Some((2000, f64::INFINITY)),
+            },
+            TsMgetEntry {
+                key: "hum:room-1".to_string(),
+                labels: vec![("metric".to_string(), "humidity".to_string())],
+                value: None,
+            },
+            TsMgetEntry {
+                key: "2nd".to_string(),
+                labels: vec![],
+                value: Some((3000, 1.0)),
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_render_metric_name_from_label() {
+    let body = TsExposition::new(TsMetricName::Label("metric".to_string()))
+        .prefix("redis_")
+        .render(&mget());
+    assert_eq!(
+        body,
+        "# TYPE redis_temperature gauge\n\
+         redis_temperature{room=\"living \\\"room\\\"\"} 21.5 1500\n\
+         redis_temperature{room=\"kitchen\"} +Inf 2000\n"
+    );
+}
+
+#[test]
+fn test_render_metric_name_from_key() {
+    let body = TsExposition::new(TsMetricName::Key)
+        .timestamps(false)
+        .render(&mget());
+    assert_eq!(
+        body,
+        "# TYPE _2nd gauge\n\
+         _2nd 1\n\
+         # TYPE temp:room_1 gauge\n\
+         temp:room_1{metric=\"temperature\",room=\"living \\\"room\\\"\"} 21.5\n\
+         # TYPE temp:room_2 gauge\n\
+         temp:room_2{metric=\"temperature\",room=\"kitchen\"} +Inf\n"
+    );
+}
+
+#[test]
+fn test_render_open_metrics() {
+    let body = TsExposition::new(TsMetricName::Constant("value".to_string()))
+        .format(TsExpositionFormat::OpenMetrics)
+        .metric_type("unknown")
+        .render(&TsMget {
+            values: vec![mget().values.remove(3)],
+        });
+    assert_eq!(body, "# TYPE value unknown\nvalue 1 3\n# EOF\n");
+}
@@ -0,0 +1,96 @@
+extern crate redis_ts;
+
+use redis_ts::{TsFrame, TsJoin, TsMrange, TsMrangeEntry, TsRange};
+
+fn ranges() -> (TsRange<u64, f64>, TsRange<u64, f64>) {
+    (
+        TsRange {
+            values: vec![(10, 1.0), (20, 2.0), (30, 3.0)],
+        },
+        TsRange {
+            values: vec![(5, 0.5), (20, 5.0), (40, 6.0)],
+        },
+    )
+}
+
+#[test]
+fn test_frame_outer_join() {
+    let (a, b) = ranges();
+    let frame = TsFrame::from_ranges(vec![("a", &a), ("b", &b)], TsJoin::Outer);
+    assert_eq!(frame.timestamps, vec![5, 10, 20, 30, 40]);
+    assert_eq!(
+        frame.column("a").unwrap().values,
+        vec![None, Some(1.0), Some(2.0), Some(3.0), None]
+    );
+    assert_eq!(
+        frame.column("b").unwrap().values,
+        vec![Some(0.5), None, Some(5.0), None, Some(6.0)]
+    );
+    assert_eq!(frame.len(), 5);
+    assert_eq!(frame.row(2), Some((20, vec![Some(2.0), Some(5.0)])));
+    assert_eq!(frame.row(5), None);
+}
+
+#[test]
+fn test_frame_inner_join() {
+    let (a, b) = ranges();
+    let frame = TsFrame::from_ranges(vec![("a", &a), ("b", &b)], TsJoin::Inner);
+    assert_eq!(frame.timestamps, vec![20]);
+    assert_eq!(frame.column("a").unwrap().values, vec![Some(2.0)]);
+    assert_eq!(frame.column("b").unwrap().values, vec![Some(5.0)]);
+}
+
+#[test]
+fn test_frame_as_of_join() {
+    let (a, b) = ranges();
+    let frame = TsFrame::from_ranges(vec![("a", &a), ("b", &b)], TsJoin::AsOf(5));
+    assert_eq!(frame.timestamps, vec![10, 20, 30]);
+    assert_eq!(
+        frame.column("b").unwrap().values,
+        vec![Some(0.5), Some(5.0), None]
+    );
+
+    let wide = TsFrame::from_ranges(vec![("a", &a), ("b", &b)], TsJoin::AsOf(100));
+    assert_eq!(
+        wide.column("b").unwrap().values,
+        vec![Some(0.5), Some(5.0), Some(5.0)]
+    );
+}
+
+#[test]
+fn test_frame_from_mrange() {
+    let mrange = TsMrange {
+        values: vec![
+            TsMrangeEntry {
+                key: "a".to_string(),
+                labels: vec![("sensor".to_string(), "temp".to_string())],
+                values: vec![(20, 2.0), (10, 1.0)],
+            },
+            TsMrangeEntry {
+                key: "b".to_string(),
+                labels: vec![],
+                values: vec![(10, 3.0)],
+            },
+        ],
+    };
+    let mut frame = TsFrame::from_mrange(&mrange, TsJoin::Outer);
+    assert_eq!(frame.timestamps, vec![10, 20]);
+    assert_eq!(
+        frame.column("a").unwrap().labels,
+        vec![("sensor".to_string(), "temp".to_string())]
+    );
+    assert_eq!(frame.column("b").unwrap().values, vec![Some(3.0), None]);
+
+    frame.set_labels("b", vec![("sensor", "pressure")]);
+    assert_eq!(
+        frame.column("b").unwrap().labels,
+        vec![("sensor".to_string(), "pressure".to_string())]
+    );
+}
+
+#[test]
+fn test_frame_empty() {
+    let frame = TsFrame::from_ranges(vec![], TsJoin::Inner);
+    assert!(frame.is_empty());
+    assert!(frame.columns.is_empty());
+}
@@ -0,0 +1,152 @@
+extern crate redis_ts;
+
+use redis_ts::{TsFillStrategy, TsGap, TsMrange, TsMrangeEntry, TsRange};
+
+fn range(values: Vec<(u64, f64)>) -> TsRange<u64, f64> {
+    TsRange { values }
+}
+
+#[test]
+fn test_gaps_none() {
+    let r = range(vec![(0, 1.0), (10, 2.0), (20, 3.0)]);
+    let report = r.gaps(10).unwrap();
+    assert!(report.is_empty());
+    assert_eq!(report.resolution, 10);
+    assert_eq!(report.missing_samples(), 0);
+}
+
+#[test]
+fn test_gaps_missing_and_nan() {
+    let r = range(vec![(0, 1.0), (30, 2.0), (40, f64::NAN), (50, 3.0)]);
+    let report = r.gaps(10).unwrap();
+    assert_eq!(
+        report.gaps,
+        vec![
+            TsGap {
+                start: 10,
+                end: 20,
+                missing: 2
+            },
+            TsGap {
+                start: 40,
+                end: 40,
+                missing: 1
+            }
+        ]
+    );
+    assert_eq!(report.missing_samples(), 3);
+}
+
+#[test]
+fn test_gaps_trailing_nan() {
+    let r = range(vec![(0, 1.0), (10, f64::NAN), (20, f64::NAN)]);
+    let report = r.gaps(10).unwrap();
+    assert_eq!(
+        report.gaps,
+        vec![TsGap {
+            start: 10,
+            end: 20,
+            missing: 2
+        }]
+    );
+}
+
+#[test]
+fn test_fill_previous() {
+    let r = range(vec![(0, 1.0), (30, 4.0), (40, f64::NAN)]);
+    let filled = r.fill(10, TsFillStrategy::Previous).unwrap();
+    assert_eq!(
+        filled.values,
+        vec![(0, 1.0), (10, 1.0), (20, 1.0), (30, 4.0), (40, 4.0)]
+    );
+}
+
+#[test]
+fn test_fill_linear() {
+    let r = range(vec![(10, f64::NAN), (20, 1.0), (50, 4.0), (60, f64::NAN)]);
+    let filled = r.fill(10, TsFillStrategy::Linear).unwrap();
+    assert_eq!(
+        filled.values,
+        vec![(20, 1.0), (30, 2.0), (40, 3.0), (50, 4.0)]
+    );
+}
+
+#[test]
+fn test_fill_constant() {
+    let r = range(vec![(0, 1.0), (20, f64::NAN), (30, 4.0)]);
+    let filled = r.fill(10, TsFillStrategy::Constant(0.0)).unwrap();
+    assert_eq!(
+        filled.values,
+        vec![(0, 1.0), (10, 0.0), (20, 0.0), (30, 4.0)]
+    );
+}
+
+#[test]
+fn test_fill_drop() {
+    let r = range(vec![(0, 1.0), (20, f64::NAN), (40, 4.0)]);
+    let filled = r.fill(10, TsFillStrategy::Drop).unwrap();
+    assert_eq!(filled.values, vec![(0, 1.0), (40, 4.0)]);
+}
+
+#[test]
+fn test_fill_descending() {
+    let r = range(vec![(30, 4.0), (0, 1.0)]);
+    let filled = r.fill(10, TsFillStrategy::Linear).unwrap();
+    assert_eq!(
+        filled.values,
+        vec![(30, 4.0), (20, 3.0), (10, 2.0), (0, 1.0)]
+    );
+    assert_eq!(r.gaps(10).unwrap().missing_samples(), 2);
+}
+
+#[test]
+fn test_mrange_gaps_and_fill() {
+    let mrange = TsMrange {
+        values: vec![
+            TsMrangeEntry {
+                key: "a".to_string(),
+                labels: vec![("l".to_string(), "v".to_string())],
+                values: vec![(0, 1.0), (20, 3.0)],
+            },
+            TsMrangeEntry {
+                key: "b".to_string(),
+                labels: vec![],
+                values: vec![(0, 1.0), (10, 3.0)],
+            },
+        ],
+    };
+    let reports = mrange.gaps(10).unwrap();
+    assert_eq!(reports[0].0, "a");
+    assert_eq!(reports[0].1.missing_samples(), 1);
+    assert!(reports[1].1.is_empty());
+
+    let filled = mrange.fill(10, TsFillStrategy::Previous).unwrap();
+    assert_eq!(filled.values[0].key, "a");
+    assert_eq!(filled.values[0].labels, mrange.values[0].labels);
+    assert_eq!(
+        filled.values[0].values,
+        vec![(0, 1.0), (10, 1.0), (20, 3.0)]
+    );
+    assert_eq!(filled.values[1].values, vec![(0, 1.0), (10, 3.0)]);
+}
+
+#[test]
+fn test_gaps_large() {
+    let r = range(vec![(0, 1.0), (u64::MAX / 2, 2.0)]);
+    let report = r.gaps(1).unwrap();
+    assert_eq!(
+        report.gaps,
+        vec![TsGap {
+            start: 1,
+            end: u64::MAX / 2 - 1,
+            missing: u64::MAX / 2 - 1
+        }]
+    );
+}
+
+#[test]
+fn test_gaps_zero_resolution() {
+    let r = range(vec![(0, 1.0), (10, f64::NAN)]);
+    assert!(r.gaps(0).is_err());
+    assert!(r.fill(0, TsFillStrategy::Previous).is_err());
+}
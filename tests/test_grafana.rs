@@ -0,0 +1,239 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{Commands, Connection, ToRedisArgs};
+use redis_ts::io::grafana::{parse_filter, GrafanaQuery, GrafanaQueryRequest, TsGrafanaHandler};
+use redis_ts::{TsCommands, TsFilterOptions, TsMrange, TsMrangeEntry, TsOptions};
+use serde_json::Value;
+
+static QUERY: &str = include_str!("fixtures/grafana/query.json");
+static QUERY_RESPONSE: &str = include_str!("fixtures/grafana/query_response.json");
+static SEARCH: &str = include_str!("fixtures/grafana/search.json");
+static ANNOTATIONS: &str = include_str!("fixtures/grafana/annotations.json");
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn args<T: ToRedisArgs>(value: T) -> Vec<String> {
+    value
+        .to_redis_args()
+        .into_iter()
+        .map(|a| String::from_utf8(a).unwrap())
+        .collect()
+}
+
+fn entry(key: &str, labels: &[(&str, &str)], values: Vec<(u64, f64)>) -> TsMrangeEntry<u64, f64> {
+    TsMrangeEntry {
+        key: key.to_string(),
+        labels: labels
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect(),
+        values,
+    }
+}
+
+#[test]
+fn test_parse_filter() {
+    let filter = parse_filter(
+        TsFilterOptions::default(),
+        "a=1  b!=2 c=(x,y) d!=(z) e= f!=",
+    )
+    .unwrap();
+    assert_eq!(
+        args(filter),
+        vec!["FILTER", "a=1", "b!=2", "c=(x,y)", "d!=(z)", "e=", "f!="]
+    );
+    assert!(parse_filter(TsFilterOptions::default(), "a").is_err());
+    assert!(parse_filter(TsFilterOptions::default(), "=1").is_err());
+}
+
+#[test]
+fn test_query_plan() {
+    let request: GrafanaQueryRequest = serde_json::from_str(QUERY).unwrap();
+    let plan = TsGrafanaHandler::new()
+        .base_filter("app=dashboard")
+        .query_plan(&request)
+        .unwrap();
+    assert_eq!(plan.len(), 2);
+
+    match plan[0].clone() {
+        GrafanaQuery::Mrange(query, filter) => {
+            assert_eq!(
+                args(query),
+                vec![
+                    "1609459200000",
+                    "1609462800000",
+                    "ALIGN",
+                    "-",
+                    "AGGREGATION",
+                    "max",
+                    "60000"
+                ]
+            );
+            assert_eq!(
+                args(filter),
+                vec![
+                    "WITHLABELS",
+                    "FILTER",
+                    "app=dashboard",
+                    "sensor=temperature",
+                    "room!=(attic,cellar)",
+                    "floor=1"
+                ]
+            );
+        }
+        _ => panic!("expected mrange"),
+    }
+    match plan[1].clone() {
+        GrafanaQuery::Range(key, query) => {
+            assert_eq!(key, "sensor:kitchen");
+            assert_eq!(args(query), vec!["1609459200000", "1609462800000"]);
+        }
+        _ => panic!("expected range"),
+    }
+
+    let mut invalid = request.clone();
+    invalid.targets[0].payload = Some(serde_json::json!({"aggregation": "median"}));
+    assert!(TsGrafanaHandler::new().query_plan(&invalid).is_err());
+}
+
+#[test]
+fn test_query_plan_adhoc_filters() {
+    let mut request: GrafanaQueryRequest = serde_json::from_str(QUERY).unwrap();
+    request.adhoc_filters[0].value = "first floor".to_string();
+    let plan = TsGrafanaHandler::new().query_plan(&request).unwrap();
+    match plan[0].clone() {
+        GrafanaQuery::Mrange(_, filter) => assert_eq!(
+            args(filter),
+            vec![
+                "WITHLABELS",
+                "FILTER",
+                "sensor=temperature",
+                "room!=(attic,cellar)",
+                "floor=first floor"
+            ]
+        ),
+        _ => panic!("expected mrange"),
+    }
+
+    for (key, operator, value) in [
+        ("floor", "=", "(1,2)"),
+        ("floor", "=", "1,2"),
+        ("floor!", "=", "1"),
+        ("floor", "=~", "1"),
+    ] {
+        let mut invalid = request.clone();
+        invalid.adhoc_filters[0].key = key.to_string();
+        invalid.adhoc_filters[0].operator = operator.to_string();
+        invalid.adhoc_filters[0].value = value.to_string();
+        assert!(TsGrafanaHandler::new().query_plan(&invalid).is_err());
+    }
+}
+
+#[test]
+fn test_query_response() {
+    let request: GrafanaQueryRequest = serde_json::from_str(QUERY).unwrap();
+    let response = TsGrafanaHandler::new().name_label("room").query_response(
+        &request,
+        vec![
+            TsMrange {
+                values: vec![
+                    entry(
+                        "sensor:kitchen",
+                        &[("room", "kitchen")],
+                        vec![(1609459200000, 21.5), (1609459260000, 22.0)],
+                    ),
+                    entry("sensor:attic", &[], vec![]),
+                ],
+            },
+            TsMrange {
+                values: vec![entry(
+                    "sensor:kitchen",
+                    &[],
+                    vec![(1609459200000, 21.0), (1609459230000, 21.25)],
+                )],
+            },
+        ],
+    );
+    let expected: Value = serde_json::from_str(QUERY_RESPONSE).unwrap();
+    assert_eq!(response, expected);
+}
+
+#[test]
+fn test_handle() {
+    let _: () = get_con().del("test_grafana_a").unwrap();
+    let _: () = get_con().del("test_grafana_b").unwrap();
+    let opts = TsOptions::default().label("app", "test_grafana");
+    let _: () = get_con()
+        .ts_create(
+            "test_grafana_a",
+            opts.clone()
+                .label("sensor", "temperature")
+                .label("room", "kitchen")
+                .label("floor", "1"),
+        )
+        .unwrap();
+    let _: () = get_con()
+        .ts_create("test_grafana_b", opts.label("event", "deploy"))
+        .unwrap();
+    let _: () = get_con()
+        .ts_madd(&[
+            ("test_grafana_a", 1609459200000u64, 20.0),
+            ("test_grafana_a", 1609459210000, 22.0),
+            ("test_grafana_a", 1609459270000, 23.0),
+            ("test_grafana_b", 1609459300000, 1.0),
+        ])
+        .unwrap();
+
+    let handler = TsGrafanaHandler::new().base_filter("app=test_grafana");
+    let mut con = get_con();
+    assert_eq!(handler.handle(&mut con, "/", "").unwrap(), "\"OK\"");
+
+    let search: Value =
+        serde_json::from_str(&handler.handle(&mut con, "/search", SEARCH).unwrap()).unwrap();
+    assert_eq!(search, serde_json::json!(["test_grafana_a"]));
+    let all: Value = serde_json::from_str(
+        &handler
+            .handle(&mut con, "/search", "{\"target\": \"\"}")
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(all, serde_json::json!(["test_grafana_a", "test_grafana_b"]));
+    let unscoped: Value = serde_json::from_str(
+        &TsGrafanaHandler::new()
+            .handle(&mut con, "/search", "{}")
+            .unwrap(),
+    )
+    .unwrap();
+    assert!(unscoped
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("test_grafana_b")));
+
+    let mut request: Value = serde_json::from_str(QUERY).unwrap();
+    request["targets"][1]["target"] = "test_grafana_a".into();
+    let body = request.to_string();
+    let query: Value =
+        serde_json::from_str(&handler.handle(&mut con, "/query", &body).unwrap()).unwrap();
+    assert_eq!(query[0]["target"], "test_grafana_a");
+    assert_eq!(
+        query[0]["datapoints"],
+        serde_json::json!([[22.0, 1609459200000u64], [23.0, 1609459260000u64]])
+    );
+    assert_eq!(query[1]["rows"].as_array().unwrap().len(), 3);
+
+    let annotations: Value = serde_json::from_str(
+        &handler
+            .handle(&mut con, "/annotations", ANNOTATIONS)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(annotations.as_array().unwrap().len(), 1);
+    assert_eq!(annotations[0]["time"], 1609459300000u64);
+    assert_eq!(annotations[0]["annotation"]["name"], "deploys");
+
+    assert!(handler.handle(&mut con, "/unknown", "{}").is_err());
+}
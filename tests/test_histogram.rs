@@ -0,0 +1,140 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{Commands, Connection, ToRedisArgs};
+use redis_ts::{
+    histogram_quantile, TsAggregationType, TsDuplicatePolicy, TsHistogram, TsMrange, TsMrangeEntry,
+    TsOptions, TsRangeQuery,
+};
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn args<T: ToRedisArgs>(value: T) -> Vec<String> {
+    value
+        .to_redis_args()
+        .into_iter()
+        .map(|a| String::from_utf8(a).unwrap())
+        .collect()
+}
+
+fn bucket(le: &str, values: Vec<(u64, f64)>) -> TsMrangeEntry<u64, f64> {
+    TsMrangeEntry {
+        key: format!("le={}", le),
+        labels: vec![],
+        values,
+    }
+}
+
+#[test]
+fn test_histogram_series() {
+    let histogram = TsHistogram::new("latency", vec![0.5, 0.1, 0.1, f64::INFINITY])
+        .options(TsOptions::default().label("service", "api"));
+    assert_eq!(histogram.bounds, vec![0.1, 0.5, f64::INFINITY]);
+    assert_eq!(
+        histogram.keys(),
+        vec![
+            ("latency:le=0.1".to_string(), "0.1".to_string()),
+            ("latency:le=0.5".to_string(), "0.5".to_string()),
+            ("latency:le=+Inf".to_string(), "+Inf".to_string()),
+        ]
+    );
+
+    // the SUM duplicate policy is kept when the options do not set one
+    let series = histogram.series();
+    assert_eq!(
+        args(&series[2].1),
+        vec![
+            "DUPLICATE_POLICY",
+            "SUM",
+            "LABELS",
+            "service",
+            "api",
+            "histogram",
+            "latency",
+            "le",
+            "+Inf"
+        ]
+    );
+
+    let last = TsHistogram::new("latency", vec![0.1])
+        .options(TsOptions::default().duplicate_policy(TsDuplicatePolicy::Last));
+    assert_eq!(
+        &args(&last.series()[0].1)[..2],
+        &["DUPLICATE_POLICY", "LAST"]
+    );
+
+    assert_eq!(
+        histogram.samples(1000, &[0.05, 0.2, 0.7, f64::NAN]),
+        vec![
+            ("latency:le=0.1".to_string(), 1000, 1.0),
+            ("latency:le=0.5".to_string(), 1000, 2.0),
+            ("latency:le=+Inf".to_string(), 1000, 3.0),
+        ]
+    );
+
+    let (_, filter) = histogram
+        .metric("api_latency")
+        .query(TsRangeQuery::default());
+    assert_eq!(
+        args(filter),
+        vec![
+            "WITHLABELS",
+            "FILTER",
+            "histogram=api_latency",
+            "GROUPBY",
+            "le",
+            "REDUCE",
+            "sum"
+        ]
+    );
+}
+
+#[test]
+fn test_histogram_quantile() {
+    let buckets = TsMrange {
+        values: vec![
+            bucket("+Inf", vec![(0, 100.0), (10, 0.0), (20, 4.0)]),
+            bucket("0.1", vec![(0, 10.0), (10, 0.0), (20, 4.0)]),
+            bucket("0.5", vec![(0, 60.0), (10, 0.0), (20, 4.0), (30, 1.0)]),
+        ],
+    };
+    let median = histogram_quantile(&buckets, 0.5);
+    assert_eq!(median.values.len(), 3);
+    assert!((median.values[0].1 - 0.42).abs() < 1e-9);
+    assert_eq!(median.values[0].0, 0);
+    assert!(median.values[1].1.is_nan());
+    assert!((median.values[2].1 - 0.05).abs() < 1e-9);
+
+    let values = |q: f64| histogram_quantile(&buckets, q).values[0].1;
+    assert!((values(0.05) - 0.05).abs() < 1e-9);
+    assert_eq!(values(0.99), 0.5);
+    assert_eq!(values(1.5), f64::INFINITY);
+    assert_eq!(values(-1.0), f64::NEG_INFINITY);
+}
+
+#[test]
+fn test_histogram_record_and_quantile() {
+    let histogram = TsHistogram::new("test_histogram", vec![10.0, 100.0]);
+    for (key, _) in histogram.keys() {
+        let _: () = get_con().del(key).unwrap();
+    }
+    histogram.create(&mut get_con()).unwrap();
+    histogram
+        .record(&mut get_con(), 1000, &[5.0, 50.0, 500.0])
+        .unwrap();
+    histogram
+        .record(&mut get_con(), 1000, &[5.0, 20.0, 80.0, 90.0])
+        .unwrap();
+
+    let p50 = histogram
+        .quantile(
+            &mut get_con(),
+            TsRangeQuery::default().aggregation_type(TsAggregationType::Sum(60000)),
+            0.5,
+        )
+        .unwrap();
+    assert_eq!(p50.values, vec![(0, 43.75)]);
+}
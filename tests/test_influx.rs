@@ -0,0 +1,153 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{Commands, Connection};
+use redis_ts::io::influx::{parse_line, parse_lines, TsInfluxSink, TsLinePoint, TsPrecision};
+use redis_ts::{TsCommands, TsDuplicatePolicy, TsOptions, TsRange, TsRangeQuery};
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+#[test]
+fn test_parse_line() {
+    let point = parse_line("cpu,host=web01,region=eu usage=1.5,count=3i,up=t 1700000000000000000")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        point,
+        TsLinePoint {
+            measurement: "cpu".to_string(),
+            tags: vec![
+                ("host".to_string(), "web01".to_string()),
+                ("region".to_string(), "eu".to_string())
+            ],
+            fields: vec![
+                ("usage".to_string(), 1.5),
+                ("count".to_string(), 3.0),
+                ("up".to_string(), 1.0)
+            ],
+            timestamp: Some(1700000000000000000),
+        }
+    );
+}
+
+#[test]
+fn test_parse_line_escapes_and_strings() {
+    let point = parse_line(r#"disk\ io,path=C:\\,name=a\=b\,c msg="x, y=z",free=-2i"#)
+        .unwrap()
+        .unwrap();
+    assert_eq!(point.measurement, "disk io");
+    assert_eq!(
+        point.tags,
+        vec![
+            ("path".to_string(), "C:\\".to_string()),
+            ("name".to_string(), "a=b,c".to_string())
+        ]
+    );
+    assert_eq!(point.fields, vec![("free".to_string(), -2.0)]);
+    assert_eq!(point.timestamp, None);
+}
+
+#[test]
+fn test_parse_line_unsigned() {
+    let point = parse_line("net bytes=18446744073709551615u,drops=-1u 1")
+        .unwrap()
+        .unwrap();
+    assert_eq!(point.fields, vec![("bytes".to_string(), u64::MAX as f64)]);
+}
+
+#[test]
+fn test_parse_lines_skips_comments() {
+    let points = parse_lines("# comment\n\ncpu v=1 1\nmem v=2 2\n").unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[1].measurement, "mem");
+}
+
+#[test]
+fn test_parse_line_errors() {
+    assert!(parse_line("cpu").is_err());
+    assert!(parse_line("cpu v=1 abc").is_err());
+    assert!(parse_line("cpu,host v=1").is_err());
+    assert!(parse_line("cpu v 1").is_err());
+}
+
+#[test]
+fn test_precision() {
+    assert_eq!(TsPrecision::Nanoseconds.to_millis(1_500_000), 1);
+    assert_eq!(TsPrecision::Microseconds.to_millis(1_500), 1);
+    assert_eq!(TsPrecision::Milliseconds.to_millis(15), 15);
+    assert_eq!(TsPrecision::Seconds.to_millis(2), 2000);
+}
+
+#[test]
+fn test_sink_samples() {
+    let sink = TsInfluxSink::new("{measurement}:{host}:{field}").precision(TsPrecision::Seconds);
+    let points = parse_lines("cpu,host=web01 idle=97.5,user=1.2 10\ncpu idle=50 20").unwrap();
+    let samples = sink.samples(&points);
+    assert_eq!(samples.len(), 3);
+    assert_eq!(samples[0].key, "cpu:web01:idle");
+    assert_eq!(samples[0].ts, 10000);
+    assert_eq!(
+        samples[0].labels,
+        vec![
+            ("host".to_string(), "web01".to_string()),
+            ("measurement".to_string(), "cpu".to_string()),
+            ("field".to_string(), "idle".to_string())
+        ]
+    );
+    assert_eq!(samples[1].key, "cpu:web01:user");
+    assert_eq!(samples[2].key, "cpu::idle");
+
+    let plain = TsInfluxSink::new("{measurement}.{field}").measurement_labels(false);
+    assert_eq!(plain.samples(&points)[2].labels, vec![]);
+}
+
+#[test]
+fn test_sink_write() {
+    let _: () = get_con().del("influx:test_influx_write:idle").unwrap();
+    let _: () = get_con().del("influx:test_influx_write:user").unwrap();
+    let mut sink = TsInfluxSink::new("influx:{measurement}:{field}")
+        .precision(TsPrecision::Milliseconds)
+        .options(TsOptions::default().retention_time(0))
+        .batch_size(2);
+    let report = sink
+        .write(
+            &mut get_con(),
+            "test_influx_write,host=a idle=1,user=2 10\ntest_influx_write,host=a idle=3 20",
+        )
+        .unwrap();
+    assert_eq!(report.written, 3);
+    assert!(report.rejected.is_empty());
+
+    let idle: TsRange<u64, f64> = get_con()
+        .ts_range("influx:test_influx_write:idle", TsRangeQuery::default())
+        .unwrap();
+    assert_eq!(idle.values, vec![(10, 1.0), (20, 3.0)]);
+    let info = get_con().ts_info("influx:test_influx_write:user").unwrap();
+    assert!(info.labels.contains(&("host".to_string(), "a".to_string())));
+}
+
+#[test]
+fn test_sink_write_rejected() {
+    let _: () = get_con().del("influx:test_influx_rejected").unwrap();
+    let mut sink = TsInfluxSink::new("influx:{measurement}")
+        .precision(TsPrecision::Milliseconds)
+        .options(TsOptions::default().duplicate_policy(TsDuplicatePolicy::Block));
+    let report = sink
+        .write(
+            &mut get_con(),
+            "test_influx_rejected v=1 10\ntest_influx_rejected v=2 10\ntest_influx_rejected v=3 20",
+        )
+        .unwrap();
+    assert_eq!(report.written, 2);
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(report.rejected[0].0.ts, 10);
+    assert_eq!(report.rejected[0].0.value, 2.0);
+
+    let range: TsRange<u64, f64> = get_con()
+        .ts_range("influx:test_influx_rejected", TsRangeQuery::default())
+        .unwrap();
+    assert_eq!(range.values, vec![(10, 1.0), (20, 3.0)]);
+}
@@ -0,0 +1,186 @@
+extern crate redis;
+extern crate redis_ts;
+
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider, Temporality};
+use opentelemetry_sdk::Resource;
+use redis::Connection;
+use redis_ts::otel::{metric_samples, TsMetricExporter, TsOtelSample};
+use redis_ts::{TsAggregationType, TsCommands, TsFilterOptions, TsMrange, TsOptions, TsRangeQuery};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+/// Collects the samples of all exports without a redis connection.
+#[derive(Clone, Default)]
+struct SampleCollector(Arc<Mutex<Vec<TsOtelSample>>>);
+
+impl PushMetricExporter for SampleCollector {
+    async fn export(&self, metrics: &ResourceMetrics) -> OTelSdkResult {
+        let mut samples = metric_samples("otel", metrics);
+        self.0.lock().unwrap().append(&mut samples);
+        Ok(())
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn temporality(&self) -> Temporality {
+        Temporality::Cumulative
+    }
+}
+
+fn provider(reader: PeriodicReader<impl PushMetricExporter>) -> SdkMeterProvider {
+    SdkMeterProvider::builder()
+        .with_resource(
+            Resource::builder_empty()
+                .with_attribute(KeyValue::new("service.name", "test"))
+                .build(),
+        )
+        .with_reader(reader)
+        .build()
+}
+
+fn record(provider: &SdkMeterProvider, name: &str) {
+    let meter = provider.meter("test_otel");
+    meter
+        .f64_gauge(format!("{name}.temperature"))
+        .build()
+        .record(21.5, &[KeyValue::new("room", "kitchen")]);
+    let counter = meter.u64_counter(format!("{name}.requests")).build();
+    counter.add(2, &[KeyValue::new("route", "/")]);
+    counter.add(3, &[KeyValue::new("route", "/")]);
+    let histogram = meter
+        .f64_histogram(format!("{name}.duration"))
+        .with_boundaries(vec![1.0, 5.0])
+        .build();
+    for v in [0.5, 2.0, 3.0, 10.0] {
+        histogram.record(v, &[]);
+    }
+}
+
+fn find<'a>(
+    samples: &'a [TsOtelSample],
+    name: &str,
+    label: Option<(&str, &str)>,
+) -> &'a TsOtelSample {
+    samples
+        .iter()
+        .find(|s| {
+            s.name == name
+                && label.is_none_or(|(n, v)| s.labels.iter().any(|(ln, lv)| ln == n && lv == v))
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_metric_samples() {
+    let collector = SampleCollector::default();
+    let provider = provider(PeriodicReader::builder(collector.clone()).build());
+    record(&provider, "samples");
+    provider.force_flush().unwrap();
+    let samples = collector.0.lock().unwrap().clone();
+
+    let gauge = find(&samples, "samples.temperature", None);
+    assert_eq!(gauge.value, 21.5);
+    assert!(gauge.ts > 0);
+    assert!(gauge.key.starts_with("otel:samples.temperature:"));
+    assert!(gauge
+        .labels
+        .contains(&("service.name".to_string(), "test".to_string())));
+    assert!(gauge
+        .labels
+        .contains(&("room".to_string(), "kitchen".to_string())));
+    assert!(gauge
+        .labels
+        .contains(&("__name__".to_string(), "samples.temperature".to_string())));
+
+    assert_eq!(find(&samples, "samples.requests", None).value, 5.0);
+
+    let bucket = "samples.duration_bucket";
+    assert_eq!(find(&samples, bucket, Some(("le", "1"))).value, 1.0);
+    assert_eq!(find(&samples, bucket, Some(("le", "5"))).value, 3.0);
+    assert_eq!(find(&samples, bucket, Some(("le", "+Inf"))).value, 4.0);
+    assert_eq!(find(&samples, "samples.duration_sum", None).value, 15.5);
+    assert_eq!(find(&samples, "samples.duration_count", None).value, 4.0);
+
+    let keys: HashSet<&str> = samples.iter().map(|s| s.key.as_str()).collect();
+    assert_eq!(keys.len(), samples.len());
+
+    record(&provider, "samples");
+    provider.force_flush().unwrap();
+    let all = collector.0.lock().unwrap().clone();
+    let again = &all[samples.len()..];
+    assert_eq!(
+        find(again, "samples.requests", None).key,
+        find(&samples, "samples.requests", None).key
+    );
+    assert_eq!(find(again, "samples.requests", None).value, 10.0);
+}
+
+fn exporter(
+    runtime: &tokio::runtime::Runtime,
+) -> TsMetricExporter<redis::aio::MultiplexedConnection> {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    let con = runtime
+        .block_on(client.get_multiplexed_async_connection())
+        .expect("Failed to get connection!");
+    TsMetricExporter::new(con, "test_otel")
+        .options(TsOptions::default().retention_time(600000))
+        .compaction(
+            "exporter.temperature",
+            "1m",
+            TsAggregationType::Avg(60000),
+            TsOptions::default(),
+        )
+        .temporality(Temporality::Cumulative)
+}
+
+#[test]
+fn test_exporter() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let provider = provider(PeriodicReader::builder(exporter(&runtime)).build());
+    record(&provider, "exporter");
+    provider.force_flush().unwrap();
+
+    let res: TsMrange<u64, f64> = get_con()
+        .ts_mrange(
+            TsRangeQuery::default(),
+            TsFilterOptions::default()
+                .equals("__name__", "exporter.requests")
+                .equals("service.name", "test")
+                .with_labels(true),
+        )
+        .unwrap();
+    assert_eq!(res.values.len(), 1);
+    assert_eq!(res.values[0].values.last().unwrap().1, 5.0);
+
+    let rules: Vec<String> = get_con()
+        .ts_queryindex(TsFilterOptions::default().equals("__name__", "exporter.temperature:1m"))
+        .unwrap();
+    assert_eq!(rules.len(), 1);
+
+    // A new exporter keeps the existing rule of the series.
+    let restarted = self::provider(PeriodicReader::builder(exporter(&runtime)).build());
+    record(&restarted, "exporter");
+    restarted.force_flush().unwrap();
+    let source = rules[0].trim_end_matches(":1m");
+    assert_eq!(get_con().ts_info(source).unwrap().rules.len(), 1);
+}
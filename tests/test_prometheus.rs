@@ -0,0 +1,191 @@
+extern crate redis;
+extern crate redis_ts;
+
+use prost::Message;
+use redis::{Commands, Connection, ToRedisArgs};
+use redis_ts::io::prometheus::{
+    decode_read_request, decode_write_request, encode_read_response, translate_matchers,
+    LabelMatcher, MatcherType, ReadResponse, TsPrometheusStorage,
+};
+use redis_ts::{TsCommands, TsDuplicatePolicy, TsOptions, TsRange, TsRangeQuery};
+
+static WRITE_REQUEST: &[u8] = include_bytes!("fixtures/prometheus/write_request.bin");
+static READ_REQUEST: &[u8] = include_bytes!("fixtures/prometheus/read_request.bin");
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn labels(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect()
+}
+
+fn matcher(t: MatcherType, name: &str, value: &str) -> LabelMatcher {
+    LabelMatcher {
+        r#type: t as i32,
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn args<T: ToRedisArgs>(value: T) -> Vec<String> {
+    value
+        .to_redis_args()
+        .into_iter()
+        .map(|a| String::from_utf8(a).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_decode_write_request_fixture() {
+    let request = decode_write_request(WRITE_REQUEST).unwrap();
+    assert_eq!(request.timeseries.len(), 2);
+    let first = &request.timeseries[0];
+    assert_eq!(first.labels[0].name, "__name__");
+    assert_eq!(first.labels[0].value, "http_requests_total");
+    assert_eq!(first.samples.len(), 2);
+    assert_eq!(first.samples[1].timestamp, 2000);
+    assert_eq!(first.samples[1].value, 2.0);
+    assert!(request.timeseries[1].samples[1].value.is_nan());
+}
+
+#[test]
+fn test_decode_read_request_fixture() {
+    let request = decode_read_request(READ_REQUEST).unwrap();
+    assert_eq!(request.queries.len(), 1);
+    let query = &request.queries[0];
+    assert_eq!(
+        (query.start_timestamp_ms, query.end_timestamp_ms),
+        (0, 3000)
+    );
+    assert_eq!(
+        query.matchers[1],
+        matcher(MatcherType::Re, "job", "api|web")
+    );
+}
+
+#[test]
+fn test_decode_invalid_payload() {
+    assert!(decode_write_request(b"not snappy").is_err());
+}
+
+#[test]
+fn test_translate_matchers() {
+    let matchers = vec![
+        matcher(MatcherType::Eq, "__name__", "up"),
+        matcher(MatcherType::Neq, "env", ""),
+        matcher(MatcherType::Re, "job", "api|web"),
+        matcher(MatcherType::Nre, "dc", "eu-1"),
+        matcher(MatcherType::Re, "instance", "a:.*"),
+    ];
+    let (filter, client) = translate_matchers("prom", &matchers).unwrap();
+    assert_eq!(
+        args(filter),
+        vec![
+            "WITHLABELS",
+            "FILTER",
+            "__redis_ts_storage__=prom",
+            "__name__=up",
+            "env!=",
+            "job=(api,web)",
+            "dc!=(eu-1)",
+        ]
+    );
+    assert_eq!(client.len(), 1);
+    assert!(client[0].matches(&labels(&[("instance", "a:9090")])));
+    assert!(!client[0].matches(&labels(&[("instance", "ba:9090")])));
+    assert!(!client[0].matches(&labels(&[])));
+}
+
+#[test]
+fn test_translate_matchers_with_set_syntax() {
+    let matchers = vec![
+        matcher(MatcherType::Eq, "__name__", "up"),
+        matcher(MatcherType::Eq, "path", "/a,b"),
+        matcher(MatcherType::Neq, "query", "f(x)"),
+    ];
+    let (filter, client) = translate_matchers("prom", &matchers).unwrap();
+    assert_eq!(
+        args(filter),
+        vec![
+            "WITHLABELS",
+            "FILTER",
+            "__redis_ts_storage__=prom",
+            "__name__=up",
+        ]
+    );
+    assert_eq!(client.len(), 2);
+    assert!(client[0].matches(&labels(&[("path", "/a,b")])));
+    assert!(!client[0].matches(&labels(&[("path", "/a")])));
+    assert!(!client[1].matches(&labels(&[("query", "f(x)")])));
+    assert!(client[1].matches(&labels(&[("query", "fx")])));
+}
+
+#[test]
+fn test_series_key_is_order_independent() {
+    let storage = TsPrometheusStorage::new("prom");
+    let a = storage.series_key(&labels(&[("job", "api"), ("__name__", "up")]));
+    let b = storage.series_key(&labels(&[("__name__", "up"), ("job", "api")]));
+    let c = storage.series_key(&labels(&[("__name__", "up"), ("job", "web")]));
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert!(a.starts_with("prom:"));
+}
+
+#[test]
+fn test_encode_read_response() {
+    let encoded = encode_read_response(&ReadResponse { results: vec![] }).unwrap();
+    let decoded = snap::raw::Decoder::new().decompress_vec(&encoded).unwrap();
+    assert_eq!(
+        ReadResponse::decode(decoded.as_slice()).unwrap(),
+        ReadResponse { results: vec![] }
+    );
+}
+
+#[test]
+fn test_write_and_read_fixtures() {
+    let mut storage = TsPrometheusStorage::new("test_prometheus")
+        .options(TsOptions::default().duplicate_policy(TsDuplicatePolicy::Block))
+        .batch_size(1);
+    let api = storage.series_key(&labels(&[
+        ("__name__", "http_requests_total"),
+        ("job", "api"),
+        ("instance", "a:9090"),
+    ]));
+    let web = storage.series_key(&labels(&[
+        ("__name__", "http_requests_total"),
+        ("job", "web"),
+        ("instance", "b:9090"),
+    ]));
+    let _: () = get_con().del(&api).unwrap();
+    let _: () = get_con().del(&web).unwrap();
+
+    let report = storage.write(&mut get_con(), WRITE_REQUEST).unwrap();
+    assert_eq!(report.written, 3);
+    assert_eq!(report.rejected, 0);
+    // a retried request is rejected under BLOCK instead of failing
+    let retried = storage.write(&mut get_con(), WRITE_REQUEST).unwrap();
+    assert_eq!(retried.written, 0);
+    assert_eq!(retried.rejected, 3);
+    let range: TsRange<u64, f64> = get_con().ts_range(&web, TsRangeQuery::default()).unwrap();
+    assert_eq!(range.values, vec![(1000, 5.0)]);
+
+    let body = storage.read(&mut get_con(), READ_REQUEST).unwrap();
+    let decoded = snap::raw::Decoder::new().decompress_vec(&body).unwrap();
+    let response = ReadResponse::decode(decoded.as_slice()).unwrap();
+    assert_eq!(response.results.len(), 1);
+    let series = &response.results[0].timeseries;
+    assert_eq!(series.len(), 1);
+    let names: Vec<&str> = series[0].labels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["__name__", "instance", "job"]);
+    let samples: Vec<(i64, f64)> = series[0]
+        .samples
+        .iter()
+        .map(|s| (s.timestamp, s.value))
+        .collect();
+    assert_eq!(samples, vec![(1000, 1.0), (2000, 2.0)]);
+}
@@ -0,0 +1,285 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{Commands, Connection, ToRedisArgs};
+use redis_ts::promql::{
+    parse, query_range, PromExpr, PromFunction, PromMatchOp, PromMatcher, PromSelector,
+};
+use redis_ts::{TsCommands, TsMrange, TsMrangeEntry, TsOptions, TsRange};
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn args<T: ToRedisArgs>(value: T) -> Vec<String> {
+    value
+        .to_redis_args()
+        .into_iter()
+        .map(|a| String::from_utf8(a).unwrap())
+        .collect()
+}
+
+fn labels(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect()
+}
+
+fn entry(key: &str, pairs: &[(&str, &str)], values: Vec<(u64, f64)>) -> TsMrangeEntry<u64, f64> {
+    TsMrangeEntry {
+        key: key.to_string(),
+        labels: labels(pairs),
+        values,
+    }
+}
+
+#[test]
+fn test_parse_selector() {
+    let expr = parse(r#"http_requests{job!="web", env=~'prod|staging', dc=""}[1m30s]"#);
+    assert!(expr.is_err());
+
+    let expr =
+        parse(r#"avg_over_time(http_requests{job!="web", env=~'prod|staging', dc=""}[1m30s])"#)
+            .unwrap();
+    let matcher = |name: &str, op: PromMatchOp, value: &str| PromMatcher {
+        name: name.to_string(),
+        op,
+        value: value.to_string(),
+    };
+    assert_eq!(
+        expr,
+        PromExpr::Call(
+            PromFunction::AvgOverTime,
+            PromSelector {
+                matchers: vec![
+                    matcher("__name__", PromMatchOp::Eq, "http_requests"),
+                    matcher("job", PromMatchOp::Neq, "web"),
+                    matcher("env", PromMatchOp::Re, "prod|staging"),
+                    matcher("dc", PromMatchOp::Eq, ""),
+                ],
+                range: Some(90000),
+            }
+        )
+    );
+}
+
+#[test]
+fn test_parse_sum() {
+    let prefix = parse("sum by (job, env) (rate(requests[5m]))").unwrap();
+    let suffix = parse("sum(rate(requests[5m])) by (job, env)").unwrap();
+    assert_eq!(prefix, suffix);
+    match prefix {
+        PromExpr::Sum { by, expr } => {
+            assert_eq!(by, vec!["job", "env"]);
+            assert!(matches!(*expr, PromExpr::Call(PromFunction::Rate, _)));
+        }
+        _ => panic!("expected sum"),
+    }
+    assert!(parse("sum(requests)").is_ok());
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(parse("requests[5m]").is_err());
+    assert!(parse("rate(requests)").is_err());
+    assert!(parse("sum without (job) (requests)").is_err());
+    assert!(parse("histogram_quantile(0.9, requests)").is_err());
+    assert!(parse(r#"requests{job=~"web.*"}"#).is_err());
+    assert!(parse(r#"requests{job="web"#).is_err());
+    assert!(parse(r#"requests{path="/a,b"}"#).is_err());
+    assert!(parse(r#"requests{query!="f(x)"}"#).is_err());
+    assert!(parse("{}").is_err());
+    assert!(parse("requests extra").is_err());
+}
+
+#[test]
+fn test_pushed_down_queries() {
+    let expr = parse(r#"sum by (job) (max_over_time(requests{env=~"a|b"}[1m]))"#).unwrap();
+    let queries = expr.queries(120000, 240000, 60000);
+    assert_eq!(queries.len(), 1);
+    let (query, filter) = queries[0].clone();
+    assert_eq!(
+        args(query),
+        vec![
+            "60000",
+            "239999",
+            "ALIGN",
+            "120000",
+            "AGGREGATION",
+            "max",
+            "60000",
+            "BUCKETTIMESTAMP",
+            "+"
+        ]
+    );
+    assert_eq!(
+        args(filter),
+        vec![
+            "WITHLABELS",
+            "FILTER",
+            "__name__=requests",
+            "env=(a,b)",
+            "GROUPBY",
+            "job",
+            "REDUCE",
+            "sum"
+        ]
+    );
+}
+
+#[test]
+fn test_client_side_queries() {
+    let expr = parse("sum by (job, env) (rate(requests[2m]))").unwrap();
+    let queries = expr.queries(120000, 240000, 60000);
+    assert_eq!(queries.len(), 1);
+    let (query, filter) = queries[0].clone();
+    assert_eq!(args(query), vec!["0", "239999"]);
+    assert_eq!(
+        args(filter),
+        vec!["WITHLABELS", "FILTER", "__name__=requests"]
+    );
+}
+
+#[test]
+fn test_evaluate_rate_and_sum() {
+    let expr = parse("sum by (job) (rate(requests[2000ms]))").unwrap();
+    let result = expr
+        .evaluate(
+            vec![TsMrange {
+                values: vec![
+                    entry(
+                        "a",
+                        &[("__name__", "requests"), ("job", "api")],
+                        vec![(0, 0.0), (1000, 10.0), (2000, 4.0), (3000, 8.0)],
+                    ),
+                    entry(
+                        "b",
+                        &[("__name__", "requests"), ("job", "api")],
+                        vec![(0, 0.0), (1000, 2.0), (2000, 4.0), (3000, 6.0)],
+                    ),
+                    entry("c", &[("__name__", "requests")], vec![(0, 1.0)]),
+                ],
+            }],
+            2000,
+            4000,
+            1000,
+        )
+        .unwrap();
+
+    assert_eq!(result.values.len(), 1);
+    assert_eq!(result.values[0].key, "job=api");
+    assert_eq!(result.values[0].labels, labels(&[("job", "api")]));
+    // [0, 2000): a +10, b +2; [1000, 3000): a reset +4, b +2; [2000, 4000): a +4, b +2
+    // the increases over one second of samples are extrapolated to the window of two
+    assert_eq!(
+        result.values[0].values,
+        vec![(2000, 12.0), (3000, 6.0), (4000, 6.0)]
+    );
+}
+
+#[test]
+fn test_evaluate_rate_like_range_rate() {
+    let samples = vec![
+        (0, 10.0),
+        (1000, 14.0),
+        (2000, 20.0),
+        (3000, 3.0),
+        (4000, 9.0),
+    ];
+    let expr = parse("rate(requests[3s])").unwrap();
+    let result = expr
+        .evaluate(
+            vec![TsMrange {
+                values: vec![entry("a", &[("__name__", "requests")], samples.clone())],
+            }],
+            3001,
+            4001,
+            1000,
+        )
+        .unwrap();
+    // the window [t - 3000, t) at t = 3001 holds the samples of the window
+    // (ts - 3000, ts] of TsRange::rate at ts = 3000
+    let expected = TsRange { values: samples }.rate(3000);
+    let promql: Vec<f64> = result.values[0].values.iter().map(|v| v.1).collect();
+    let range: Vec<f64> = expected
+        .values
+        .iter()
+        .filter(|v| v.0 >= 3000)
+        .map(|v| v.1)
+        .collect();
+    assert_eq!(promql.len(), 2);
+    assert_eq!(range.len(), 2);
+    for (a, b) in promql.iter().zip(range.iter()) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+}
+
+#[test]
+fn test_evaluate_over_time() {
+    let expr = parse("avg_over_time(requests[2s])").unwrap();
+    let result = expr
+        .evaluate(
+            vec![TsMrange {
+                values: vec![entry(
+                    "a",
+                    &[("__name__", "requests"), ("job", "api")],
+                    vec![(0, 1.0), (1000, 3.0), (2000, 5.0)],
+                )],
+            }],
+            1000,
+            3000,
+            1000,
+        )
+        .unwrap();
+    assert_eq!(result.values[0].labels, labels(&[("job", "api")]));
+    assert_eq!(
+        result.values[0].values,
+        vec![(1000, 1.0), (2000, 2.0), (3000, 4.0)]
+    );
+}
+
+#[test]
+fn test_query_range() {
+    let _: () = get_con().del("test_promql_a").unwrap();
+    let _: () = get_con().del("test_promql_b").unwrap();
+    let opts = TsOptions::default().label("__name__", "test_promql_requests");
+    let _: () = get_con()
+        .ts_create("test_promql_a", opts.clone().label("job", "api"))
+        .unwrap();
+    let _: () = get_con()
+        .ts_create("test_promql_b", opts.label("job", "web"))
+        .unwrap();
+    let _: () = get_con()
+        .ts_madd(&[
+            ("test_promql_a", 1000, 1.0),
+            ("test_promql_a", 1500, 3.0),
+            ("test_promql_a", 2500, 5.0),
+            ("test_promql_b", 1000, 10.0),
+            ("test_promql_b", 2000, 20.0),
+        ])
+        .unwrap();
+
+    let max = query_range(
+        &mut get_con(),
+        r#"max_over_time(test_promql_requests{job="api"}[1s])"#,
+        2000,
+        3000,
+        1000,
+    )
+    .unwrap();
+    assert_eq!(max.values.len(), 1);
+    assert_eq!(max.values[0].values, vec![(2000, 3.0), (3000, 5.0)]);
+
+    let sum = query_range(
+        &mut get_con(),
+        r#"sum by (__name__) (test_promql_requests{job=~"api|web"})"#,
+        2000,
+        3000,
+        1000,
+    )
+    .unwrap();
+    assert_eq!(sum.values.len(), 1);
+    assert_eq!(sum.values[0].values, vec![(2000, 13.0), (3000, 25.0)]);
+}
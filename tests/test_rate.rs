@@ -0,0 +1,85 @@
+extern crate redis_ts;
+
+use redis_ts::TsRange;
+
+fn range(values: Vec<(u64, f64)>) -> TsRange<u64, f64> {
+    TsRange { values }
+}
+
+fn assert_close(actual: &TsRange<u64, f64>, expected: Vec<(u64, f64)>) {
+    assert_eq!(actual.values.len(), expected.len(), "{:?}", actual.values);
+    for (a, e) in actual.values.iter().zip(expected.iter()) {
+        assert_eq!(a.0, e.0);
+        assert!((a.1 - e.1).abs() < 1e-9, "{:?} != {:?}", a, e);
+    }
+}
+
+#[test]
+fn test_rate_steady_counter() {
+    let r = range(vec![
+        (0, 100.0),
+        (10000, 110.0),
+        (20000, 120.0),
+        (30000, 130.0),
+    ]);
+    assert_close(
+        &r.rate(20000),
+        vec![(10000, 1.0), (20000, 1.0), (30000, 1.0)],
+    );
+    // the window (0, 30000] excludes the sample at its start
+    assert_close(
+        &r.rate(30000),
+        vec![(10000, 0.5), (20000, 1.0), (30000, 1.0)],
+    );
+    assert_close(
+        &r.increase(30000),
+        vec![(10000, 15.0), (20000, 30.0), (30000, 30.0)],
+    );
+}
+
+#[test]
+fn test_rate_extrapolation() {
+    // the gap before the first sample is larger than 1.1 times the average
+    // interval, only half an interval is extrapolated
+    let r = range(vec![(30000, 100.0), (40000, 110.0), (50000, 120.0)]);
+    assert_close(&r.increase(60000), vec![(40000, 15.0), (50000, 25.0)]);
+
+    // extrapolation stops at the point where the counter would be zero
+    let r = range(vec![(30000, 5.0), (40000, 15.0), (50000, 25.0)]);
+    assert_close(&r.increase(60000), vec![(40000, 15.0), (50000, 25.0)]);
+    let r = range(vec![(55000, 1.0), (60000, 2.0)]);
+    assert_close(&r.increase(60000), vec![(60000, 2.0)]);
+}
+
+#[test]
+fn test_rate_counter_reset() {
+    let r = range(vec![(0, 10.0), (10000, 20.0), (20000, 5.0), (30000, 15.0)]);
+    assert_close(
+        &r.increase(40000),
+        vec![(10000, 20.0), (20000, 18.75), (30000, 100.0 / 3.0)],
+    );
+    assert_close(
+        &r.irate(40000),
+        vec![(10000, 1.0), (20000, 0.5), (30000, 1.0)],
+    );
+}
+
+#[test]
+fn test_delta() {
+    // decreases of gauges are no resets
+    let r = range(vec![(0, 10.0), (10000, 20.0), (20000, 5.0)]);
+    assert_close(&r.delta(20000), vec![(10000, 20.0), (20000, -30.0)]);
+}
+
+#[test]
+fn test_rate_unordered_and_nan() {
+    let r = range(vec![
+        (20000, 20.0),
+        (10000, f64::NAN),
+        (0, 0.0),
+        (30000, 30.0),
+    ]);
+    assert_close(&r.irate(30000), vec![(20000, 1.0), (30000, 1.0)]);
+    assert!(range(vec![(0, 1.0)]).rate(1000).values.is_empty());
+    assert!(range(vec![]).irate(1000).values.is_empty());
+}
@@ -0,0 +1,251 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{FromRedisValue, Value};
+use redis_ts::{
+    TsColumns, TsDuplicatePolicy, TsInfo, TsMcolumns, TsMget, TsMrange, TsRange, TsValue,
+};
+
+fn bulk(text: &str) -> Value {
+    Value::BulkString(text.as_bytes().to_vec())
+}
+
+fn simple(text: &str) -> Value {
+    Value::SimpleString(text.to_string())
+}
+
+fn resp2_sample(ts: i64, value: &str) -> Value {
+    Value::Array(vec![Value::Int(ts), bulk(value)])
+}
+
+fn resp3_sample(ts: i64, value: f64) -> Value {
+    Value::Array(vec![Value::Int(ts), Value::Double(value)])
+}
+
+fn resp2_labels(labels: &[(&str, &str)]) -> Value {
+    Value::Array(
+        labels
+            .iter()
+            .map(|(n, v)| Value::Array(vec![bulk(n), bulk(v)]))
+            .collect(),
+    )
+}
+
+fn resp3_labels(labels: &[(&str, &str)]) -> Value {
+    Value::Map(labels.iter().map(|(n, v)| (bulk(n), bulk(v))).collect())
+}
+
+fn labels(labels: &[(&str, &str)]) -> Vec<(String, String)> {
+    labels
+        .iter()
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect()
+}
+
+fn resp2_info() -> Value {
+    Value::Array(vec![
+        bulk("totalSamples"),
+        Value::Int(2),
+        bulk("lastTimestamp"),
+        Value::Int(20),
+        bulk("duplicatePolicy"),
+        bulk("max"),
+        bulk("sourceKey"),
+        Value::Nil,
+        bulk("labels"),
+        resp2_labels(&[("a", "b")]),
+        bulk("rules"),
+        Value::Array(vec![Value::Array(vec![
+            bulk("dest"),
+            Value::Int(1000),
+            bulk("AVG"),
+            Value::Int(0),
+        ])]),
+    ])
+}
+
+fn resp3_info() -> Value {
+    Value::Map(vec![
+        (simple("totalSamples"), Value::Int(2)),
+        (simple("lastTimestamp"), Value::Int(20)),
+        (simple("duplicatePolicy"), simple("max")),
+        (simple("sourceKey"), Value::Nil),
+        (simple("labels"), resp3_labels(&[("a", "b")])),
+        (
+            simple("rules"),
+            Value::Map(vec![(
+                bulk("dest"),
+                Value::Array(vec![Value::Int(1000), simple("AVG"), Value::Int(0)]),
+            )]),
+        ),
+    ])
+}
+
+#[test]
+fn test_info_both_encodings() {
+    for reply in [resp2_info(), resp3_info()].iter() {
+        let info = TsInfo::from_redis_value(reply).unwrap();
+        assert_eq!(info.total_samples, 2);
+        assert_eq!(info.last_timestamp, 20);
+        assert_eq!(info.duplicate_policy, Some(TsDuplicatePolicy::Max));
+        assert_eq!(info.source_key, None);
+        assert_eq!(info.labels, labels(&[("a", "b")]));
+        assert_eq!(
+            info.rules,
+            vec![("dest".to_string(), 1000, "AVG".to_string())]
+        );
+    }
+}
+
+#[test]
+fn test_range_both_encodings() {
+    let resp2 = Value::Array(vec![resp2_sample(10, "1.5"), resp2_sample(20, "nan")]);
+    let resp3 = Value::Array(vec![resp3_sample(10, 1.5), resp3_sample(20, f64::NAN)]);
+    for reply in [resp2, resp3].iter() {
+        let range: TsRange<u64, f64> = FromRedisValue::from_redis_value(reply).unwrap();
+        assert_eq!(range.values[0], (10, 1.5));
+        assert!(range.values[1].1.is_nan());
+
+        let range: TsRange<u64, TsValue> = FromRedisValue::from_redis_value(reply).unwrap();
+        assert_eq!(range.values[0], (10, TsValue(1.5)));
+
+        let columns = TsColumns::from_redis_value(reply).unwrap();
+        assert_eq!(columns.timestamps, vec![10, 20]);
+        assert_eq!(columns.values[0], 1.5);
+
+        let owned: TsRange<u64, f64> =
+            FromRedisValue::from_owned_redis_value(reply.clone()).unwrap();
+        assert_eq!(owned.values[0], (10, 1.5));
+    }
+}
+
+fn resp2_mget() -> Value {
+    Value::Array(vec![
+        Value::Array(vec![
+            bulk("a"),
+            resp2_labels(&[("host", "a")]),
+            resp2_sample(10, "1"),
+        ]),
+        Value::Array(vec![bulk("b"), resp2_labels(&[]), Value::Array(vec![])]),
+    ])
+}
+
+fn resp3_mget() -> Value {
+    Value::Map(vec![
+        (
+            bulk("a"),
+            Value::Array(vec![resp3_labels(&[("host", "a")]), resp3_sample(10, 1.0)]),
+        ),
+        (
+            bulk("b"),
+            Value::Array(vec![resp3_labels(&[]), Value::Array(vec![])]),
+        ),
+    ])
+}
+
+#[test]
+fn test_mget_both_encodings() {
+    for reply in [resp2_mget(), resp3_mget()].iter() {
+        for mget in [
+            TsMget::<u64, f64>::from_redis_value(reply).unwrap(),
+            TsMget::<u64, f64>::from_owned_redis_value(reply.clone()).unwrap(),
+        ]
+        .iter()
+        {
+            assert_eq!(mget.values.len(), 2);
+            assert_eq!(mget.values[0].key, "a");
+            assert_eq!(mget.values[0].labels, labels(&[("host", "a")]));
+            assert_eq!(mget.values[0].value, Some((10, 1.0)));
+            assert_eq!(mget.values[1].key, "b");
+            assert_eq!(mget.values[1].value, None);
+        }
+    }
+}
+
+fn resp2_mrange() -> Value {
+    Value::Array(vec![Value::Array(vec![
+        bulk("host=a"),
+        resp2_labels(&[
+            ("host", "a"),
+            ("__reducer__", "sum"),
+            ("__source__", "a1,a2"),
+        ]),
+        Value::Array(vec![resp2_sample(10, "1"), resp2_sample(20, "2")]),
+    ])])
+}
+
+fn resp3_mrange() -> Value {
+    // GROUPBY replies carry the reducer and sources as metadata between the
+    // labels and the samples.
+    Value::Map(vec![(
+        bulk("host=a"),
+        Value::Array(vec![
+            resp3_labels(&[
+                ("host", "a"),
+                ("__reducer__", "sum"),
+                ("__source__", "a1,a2"),
+            ]),
+            Value::Map(vec![
+                (simple("reducers"), Value::Array(vec![simple("sum")])),
+                (
+                    simple("sources"),
+                    Value::Array(vec![bulk("a1"), bulk("a2")]),
+                ),
+            ]),
+            Value::Array(vec![resp3_sample(10, 1.0), resp3_sample(20, 2.0)]),
+        ]),
+    )])
+}
+
+#[test]
+fn test_mrange_both_encodings() {
+    for reply in [resp2_mrange(), resp3_mrange()].iter() {
+        for mrange in [
+            TsMrange::<u64, f64>::from_redis_value(reply).unwrap(),
+            TsMrange::<u64, f64>::from_owned_redis_value(reply.clone()).unwrap(),
+        ]
+        .iter()
+        {
+            assert_eq!(mrange.values.len(), 1);
+            assert_eq!(mrange.values[0].key, "host=a");
+            assert_eq!(
+                mrange.values[0].labels[0],
+                ("host".to_string(), "a".to_string())
+            );
+            assert_eq!(mrange.values[0].values, vec![(10, 1.0), (20, 2.0)]);
+        }
+
+        let mcolumns = TsMcolumns::from_redis_value(reply).unwrap();
+        assert_eq!(mcolumns.values[0].key, "host=a");
+        assert_eq!(mcolumns.values[0].columns.values, vec![1.0, 2.0]);
+        assert_eq!(
+            TsMcolumns::from_owned_redis_value(reply.clone()).unwrap(),
+            mcolumns
+        );
+    }
+}
+
+#[test]
+fn test_missing_selected_labels() {
+    let resp2 = Value::Array(vec![Value::Array(vec![
+        bulk("a"),
+        Value::Array(vec![
+            Value::Array(vec![bulk("host"), bulk("a")]),
+            Value::Array(vec![bulk("dc"), Value::Nil]),
+        ]),
+        Value::Array(vec![]),
+    ])]);
+    let resp3 = Value::Map(vec![(
+        bulk("a"),
+        Value::Array(vec![
+            Value::Map(vec![(bulk("host"), bulk("a")), (bulk("dc"), Value::Nil)]),
+            Value::Array(vec![]),
+        ]),
+    )]);
+    for reply in [resp2, resp3].iter() {
+        let mrange = TsMrange::<u64, f64>::from_redis_value(reply).unwrap();
+        assert_eq!(mrange.values[0].labels, labels(&[("host", "a")]));
+        let mrange = TsMrange::<u64, f64>::from_owned_redis_value(reply.clone()).unwrap();
+        assert_eq!(mrange.values[0].labels, labels(&[("host", "a")]));
+    }
+}
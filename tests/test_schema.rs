@@ -0,0 +1,204 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{Commands, Connection, ToRedisArgs};
+use redis_ts::schema::{TsSchema, TsSchemaRegistry};
+use redis_ts::{TsAggregationType, TsCommands, TsDuplicatePolicy, TsFilterOptions};
+
+fn get_con() -> Connection {
+    let client = redis::Client::open("redis://localhost/").unwrap();
+    client.get_connection().expect("Failed to get connection!")
+}
+
+fn args<T: ToRedisArgs>(value: T) -> Vec<String> {
+    value
+        .to_redis_args()
+        .into_iter()
+        .map(|a| String::from_utf8(a).unwrap())
+        .collect()
+}
+
+fn sensor() -> TsSchema {
+    TsSchema::new("sensor", "sensor:{site}:{kind}")
+        .label("site")
+        .label_values("kind", &["temperature", "humidity"])
+        .optional_label("firmware")
+        .retention(86400000)
+        .duplicate_policy(TsDuplicatePolicy::Last)
+        .compaction("1h", TsAggregationType::Avg(3600000), 2592000000)
+}
+
+#[test]
+fn test_schema_key_and_options() {
+    let schema = sensor();
+    let labels = [("site", "berlin"), ("kind", "temperature")];
+    assert_eq!(schema.key(&labels).unwrap(), "sensor:berlin:temperature");
+    assert_eq!(
+        args(schema.options(&labels).unwrap()),
+        vec![
+            "RETENTION",
+            "86400000",
+            "DUPLICATE_POLICY",
+            "LAST",
+            "LABELS",
+            "__schema__",
+            "sensor",
+            "site",
+            "berlin",
+            "kind",
+            "temperature"
+        ]
+    );
+
+    assert!(schema.key(&[("site", "berlin")]).is_err());
+    assert!(schema
+        .key(&[("site", "berlin"), ("kind", "pressure")])
+        .is_err());
+    assert!(schema
+        .key(&[("site", "berlin"), ("kind", "humidity"), ("room", "attic")])
+        .is_err());
+    assert!(schema.key(&[("site", ""), ("kind", "humidity")]).is_err());
+
+    let tier = schema.tier("1h").unwrap();
+    assert_eq!(tier.name, "sensor:1h");
+    assert_eq!(tier.key(&labels).unwrap(), "sensor:berlin:temperature:1h");
+    assert_eq!(tier.retention, Some(2592000000));
+    assert!(schema.tier("1d").is_none());
+}
+
+#[test]
+fn test_schema_parse_and_validate_write() {
+    let schema = sensor();
+    assert_eq!(
+        schema.parse_key("sensor:berlin:humidity"),
+        Some(vec![
+            ("site".to_string(), "berlin".to_string()),
+            ("kind".to_string(), "humidity".to_string())
+        ])
+    );
+    assert_eq!(schema.parse_key("sensor::humidity"), None);
+    assert_eq!(schema.parse_key("sensors:berlin:humidity"), None);
+
+    let labels = [
+        ("site", "berlin"),
+        ("kind", "humidity"),
+        ("firmware", "1.2"),
+    ];
+    assert!(schema
+        .validate_write("sensor:berlin:humidity", &labels)
+        .is_ok());
+    assert!(schema
+        .validate_write("sensor:paris:humidity", &labels)
+        .is_err());
+}
+
+#[test]
+fn test_schema_filter() {
+    let schema = sensor();
+    let filter = schema.filter(&[("kind", "humidity")]).unwrap();
+    assert_eq!(
+        args(filter.clone()),
+        vec!["FILTER", "__schema__=sensor", "kind=humidity"]
+    );
+    assert!(schema.validate_filter(&filter).is_ok());
+    assert!(schema.filter(&[("kind", "pressure")]).is_err());
+
+    let valid = TsFilterOptions::default()
+        .in_set("kind", vec!["temperature", "humidity"])
+        .has_label("firmware");
+    assert!(schema.validate_filter(&valid).is_ok());
+    let unknown = TsFilterOptions::default().equals("room", "attic");
+    assert!(schema.validate_filter(&unknown).is_err());
+    let invalid = TsFilterOptions::default().not_in_set("kind", vec!["humidity", "pressure"]);
+    assert!(schema.validate_filter(&invalid).is_err());
+}
+
+#[test]
+fn test_registry() {
+    let registry = TsSchemaRegistry::new()
+        .register(sensor())
+        .register(TsSchema::new("meter", "meter:{id}").label("id"));
+    assert_eq!(registry.schemas().len(), 3);
+    assert_eq!(
+        registry
+            .validate_key("sensor:berlin:humidity")
+            .unwrap()
+            .name,
+        "sensor"
+    );
+    assert_eq!(
+        registry
+            .validate_key("sensor:berlin:humidity:1h")
+            .unwrap()
+            .name,
+        "sensor:1h"
+    );
+    assert!(registry.validate_key("other:1").is_err());
+    assert!(registry
+        .validate_samples(&[("meter:1", 1000, 1.0), ("sensor:a:humidity", 1000, 2.0)])
+        .is_ok());
+    assert!(registry
+        .validate_samples(&[("meter:1", 1000, 1.0), ("other:1", 1000, 2.0)])
+        .is_err());
+    assert!(registry.validate_write("meter:7", &[("id", "7")]).is_ok());
+
+    let filter = registry
+        .get("meter")
+        .unwrap()
+        .filter(&[("id", "7")])
+        .unwrap();
+    assert_eq!(registry.validate_query(&filter).unwrap().name, "meter");
+    assert!(registry
+        .validate_query(&TsFilterOptions::default().equals("id", "7"))
+        .is_err());
+    assert!(registry
+        .validate_query(&filter.equals("kind", "humidity"))
+        .is_err());
+}
+
+#[test]
+fn test_registry_key_values() {
+    let registry = TsSchemaRegistry::new().register(sensor()).register(
+        TsSchema::new("reading", "sensor:{site}:{kind}:{unit}")
+            .label("site")
+            .label("kind")
+            .label_values("unit", &["c", "f"]),
+    );
+    let err = registry.validate_key("sensor:berlin:pressure").unwrap_err();
+    assert!(err.to_string().contains("invalid value pressure"));
+    assert!(registry.validate_key("sensor:x:pressure:1h").is_err());
+    assert!(registry
+        .validate_samples(&[("sensor:berlin:pressure", 1000, 1.0)])
+        .is_err());
+    // the longer reading template matches too, but 1h is no unit
+    assert_eq!(
+        registry.validate_key("sensor:x:humidity:1h").unwrap().name,
+        "sensor:1h"
+    );
+    assert_eq!(
+        registry.validate_key("sensor:x:pressure:c").unwrap().name,
+        "reading"
+    );
+}
+
+#[test]
+fn test_schema_create() {
+    let schema = sensor();
+    let labels = [("site", "test_schema"), ("kind", "humidity")];
+    let _: () = get_con()
+        .del(&[
+            "sensor:test_schema:humidity",
+            "sensor:test_schema:humidity:1h",
+        ])
+        .unwrap();
+    let key = schema.create(&mut get_con(), &labels).unwrap();
+    assert_eq!(key, "sensor:test_schema:humidity");
+
+    let info = get_con().ts_info(key.as_str()).unwrap();
+    assert_eq!(info.retention_time, 86400000);
+    assert_eq!(info.rules.len(), 1);
+    let keys = get_con()
+        .ts_queryindex(schema.tier("1h").unwrap().filter(&labels).unwrap())
+        .unwrap();
+    assert_eq!(keys, vec!["sensor:test_schema:humidity:1h"]);
+}
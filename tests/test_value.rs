@@ -0,0 +1,84 @@
+extern crate redis;
+extern crate redis_ts;
+
+use redis::{FromRedisValue, ToRedisArgs, Value};
+use redis_ts::{TsMget, TsMrange, TsRange, TsValue};
+
+fn sample(ts: i64, value: &str) -> Value {
+    Value::Array(vec![
+        Value::Int(ts),
+        Value::BulkString(value.as_bytes().to_vec()),
+    ])
+}
+
+#[test]
+fn test_value_decodes_special_values() {
+    let reply = Value::Array(vec![
+        sample(1, "1.5"),
+        sample(2, "nan"),
+        sample(3, "inf"),
+        sample(4, "-inf"),
+        sample(5, "-nan"),
+        sample(6, "+inf"),
+    ]);
+    let range: TsRange<u64, TsValue> = FromRedisValue::from_redis_value(&reply).unwrap();
+    let values: Vec<f64> = range.values.iter().map(|(_, v)| v.value()).collect();
+    assert_eq!(values[0], 1.5);
+    assert!(values[1].is_nan());
+    assert_eq!(values[2], f64::INFINITY);
+    assert_eq!(values[3], f64::NEG_INFINITY);
+    assert!(values[4].is_nan());
+    assert_eq!(values[5], f64::INFINITY);
+    assert!(!range.values[1].1.is_finite());
+    assert_eq!(
+        TsValue::from_redis_value(&Value::Int(3)).unwrap(),
+        TsValue(3.0)
+    );
+    assert!(TsValue::from_redis_value(&Value::BulkString(b"x".to_vec())).is_err());
+}
+
+#[test]
+fn test_value_writes_special_values() {
+    let args: Vec<Vec<u8>> =
+        (TsValue(f64::NAN), TsValue(f64::INFINITY), TsValue(-1.5)).to_redis_args();
+    assert_eq!(
+        args,
+        vec![b"nan".to_vec(), b"inf".to_vec(), b"-1.5".to_vec()]
+    );
+    assert_eq!(
+        TsValue(f64::NEG_INFINITY).to_redis_args(),
+        vec![b"-inf".to_vec()]
+    );
+}
+
+#[test]
+fn test_value_non_copy_types() {
+    let reply = Value::Array(vec![sample(1, "1.25"), sample(2, "nan")]);
+    let range: TsRange<u64, String> = FromRedisValue::from_redis_value(&reply).unwrap();
+    assert_eq!(
+        range.values,
+        vec![(1, "1.25".to_string()), (2, "nan".to_string())]
+    );
+
+    let entry = |key: &str, samples: Value| {
+        Value::Array(vec![
+            Value::BulkString(key.as_bytes().to_vec()),
+            Value::Array(vec![]),
+            samples,
+        ])
+    };
+    let reply = Value::Array(vec![entry("a", reply.clone())]);
+    let mrange: TsMrange<u64, String> = FromRedisValue::from_redis_value(&reply).unwrap();
+    assert_eq!(mrange.values[0].values[1], (2, "nan".to_string()));
+
+    let reply = Value::Array(vec![entry("a", sample(7, "inf"))]);
+    let mget: TsMget<u64, TsValue> = FromRedisValue::from_redis_value(&reply).unwrap();
+    assert_eq!(mget.values[0].value, Some((7, TsValue(f64::INFINITY))));
+}
+
+#[test]
+fn test_value_invalid_reply() {
+    let reply = Value::Array(vec![sample(1, "one")]);
+    let range: redis::RedisResult<TsRange<u64, f64>> = FromRedisValue::from_redis_value(&reply);
+    assert!(range.is_err());
+}